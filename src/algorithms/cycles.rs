@@ -0,0 +1,175 @@
+use std::fmt;
+
+use crate::algorithms::equivalence::solved_after;
+use crate::models::{PieceSet, PuzzleType};
+
+/// Pieces that move round in a loop. `slots[0]` goes to `slots[1]` and so
+/// on, and `twist` is how far the pieces have turned by the time each one
+/// is back where it started (0 if they come back unturned).
+pub struct Cycle {
+    pub kind: &'static str,
+    pub slots: Vec<String>,
+    pub twist: u8,
+    pub orientations: u8
+}
+
+pub struct Analysis {
+    pub cycles: Vec<Cycle>,
+    /// Kinds of piece the algorithm leaves alone.
+    pub untouched: Vec<&'static str>,
+    /// How many times the algorithm has to be done to get back to solved.
+    pub order: u64
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+fn plural(kind: &str) -> String {
+    if kind.ends_with('x') { format!("{kind}es") } else { format!("{kind}s") }
+}
+
+fn describe_twist(twist: u8, orientations: u8) -> &'static str {
+    match (orientations, twist) {
+        (2, _) => "flipped",
+        (3, 1) => "twisted clockwise",
+        (3, _) => "twisted anticlockwise",
+        _ => "turned"
+    }
+}
+
+/// Splits one kind of piece into cycles, leaving out pieces that stay put
+/// without turning.
+fn cycles_of(pieces: &PieceSet) -> Vec<Cycle> {
+    // `permutation[slot]` is the piece now in `slot`, so the piece that
+    // started in slot `p` has gone to the slot holding `p`.
+    let mut destination = vec![0; pieces.permutation.len()];
+    for (slot, &piece) in pieces.permutation.iter().enumerate() {
+        destination[piece as usize] = slot;
+    }
+
+    let mut seen = vec![false; destination.len()];
+    let mut cycles = Vec::new();
+
+    for start in 0..destination.len() {
+        if seen[start] {
+            continue;
+        }
+
+        let mut slots = Vec::new();
+        let mut twist = 0;
+        let mut slot = start;
+        while !seen[slot] {
+            seen[slot] = true;
+            slots.push(pieces.names[slot].clone());
+            slot = destination[slot];
+            twist = (twist + pieces.orientation[slot]) % pieces.orientations;
+        }
+
+        if slots.len() > 1 || twist != 0 {
+            cycles.push(Cycle { kind: pieces.kind, slots, twist, orientations: pieces.orientations });
+        }
+    }
+    cycles
+}
+
+/// Works out what `alg` does to a solved puzzle of the same kind as `puzzle`.
+/// Slices, wide moves and rotations are done as the cube does them, so a
+/// rotation left at the end moves every piece.
+pub fn analyse(puzzle: &PuzzleType, alg: &str) -> Result<Analysis, String> {
+    let solved = solved_after(puzzle, alg)?;
+
+    let mut analysis = Analysis { cycles: Vec::new(), untouched: Vec::new(), order: 1 };
+
+    for pieces in solved.pieces()? {
+        let cycles = cycles_of(&pieces);
+        if cycles.is_empty() {
+            analysis.untouched.push(pieces.kind);
+        }
+
+        for cycle in cycles.iter() {
+            let turns = cycle.orientations as u64 / gcd(cycle.twist as u64, cycle.orientations as u64);
+            let length = cycle.slots.len() as u64 * turns;
+            analysis.order = analysis.order / gcd(analysis.order, length) * length;
+        }
+        analysis.cycles.extend(cycles);
+    }
+    Ok(analysis)
+}
+
+impl fmt::Display for Cycle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.slots.len() == 1 {
+            return write!(f, "{} {} {}", self.kind, self.slots[0], describe_twist(self.twist, self.orientations));
+        }
+
+        let arrow = if self.slots.len() == 2 { "↔" } else { "→" };
+        write!(f, "{}-cycle of {}: {}", self.slots.len(), plural(self.kind), self.slots.join(arrow))?;
+        if self.twist != 0 {
+            write!(f, " ({})", describe_twist(self.twist, self.orientations))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for cycle in self.cycles.iter() {
+            writeln!(f, "{cycle}")?;
+        }
+        for kind in self.untouched.iter() {
+            writeln!(f, "{} untouched", plural(kind))?;
+        }
+        write!(f, "Order: {}", self.order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analyse_3x3(alg: &str) -> Analysis {
+        analyse(&PuzzleType::from_name("3x3").unwrap(), alg).unwrap()
+    }
+
+    #[test]
+    fn an_a_perm_is_a_corner_three_cycle() {
+        let analysis = analyse_3x3("x R' U R' D2 R U' R' D2 R2 x'");
+        assert_eq!(analysis.cycles.len(), 1);
+        assert_eq!((analysis.cycles[0].kind, analysis.cycles[0].slots.len(), analysis.cycles[0].twist), ("corner", 3, 0));
+        assert_eq!(analysis.untouched, vec!["edge"]);
+        assert_eq!(analysis.order, 3);
+    }
+
+    #[test]
+    fn twists_are_cycles_of_one() {
+        let analysis = analyse_3x3("R' D' R D R' D' R D U D' R' D R D' R' D R U'");
+        assert_eq!(analysis.cycles.len(), 2);
+        assert!(analysis.cycles.iter().all(|x| x.kind == "corner" && x.slots.len() == 1 && x.twist != 0));
+        assert_ne!(analysis.cycles[0].twist, analysis.cycles[1].twist);
+        assert_eq!(analysis.order, 3);
+    }
+
+    #[test]
+    fn slices_wide_moves_and_rotations_are_not_face_turns() {
+        assert_ne!(analyse_3x3("r U r'").to_string(), analyse_3x3("R U R'").to_string());
+        assert_eq!(analyse_3x3("r U r'").to_string(), analyse_3x3("L F L'").to_string());
+
+        let analysis = analyse_3x3("M2 U M2 U2 M2 U M2");
+        assert_eq!(analysis.cycles.iter().map(|x| (x.kind, x.slots.len())).collect::<Vec<_>>(), vec![("edge", 2), ("edge", 2)]);
+        assert_eq!(analysis.order, 2);
+        assert_eq!(analyse_3x3("x").order, 4);
+        assert_eq!(analyse_3x3("E2 y2").order, 2);
+    }
+
+    #[test]
+    fn orders_solve_when_repeated() {
+        for (name, alg) in [("3x3", "R U"), ("3x3", "R U R' U'"), ("3x3", "r U M'"), ("2x2", "R U2 F'"), ("Skewb", "R L'"), ("Pyraminx", "R L'"), ("Megaminx", "R U'"), ("Ivy", "R L'")] {
+            let puzzle = PuzzleType::from_name(name).unwrap();
+            let order = analyse(&puzzle, alg).unwrap().order as usize;
+            let repeated = |times: usize| solved_after(&puzzle, &vec![alg; times].join(" ")).unwrap().is_solved();
+            assert!(repeated(order), "{name} {alg}");
+            assert!((1..order).filter(|&x| order.is_multiple_of(x)).all(|x| !repeated(x)), "{name} {alg}");
+        }
+    }
+}
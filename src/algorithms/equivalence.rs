@@ -0,0 +1,264 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::algorithms::{format, Move, Notation};
+use crate::algorithms::simplify::simplify;
+use crate::algorithms::transforms::{face_turns, geometry, rotate};
+use crate::history::History;
+use crate::models::{parser, PuzzleType};
+
+/// A sticker that ends up a different colour after each algorithm. Colours
+/// are written as the letter of the face they belong to.
+pub struct StickerDiff {
+    pub face: char,
+    pub index: usize,
+    pub first: char,
+    pub second: char
+}
+
+pub enum Equivalence {
+    Equal,
+    /// The second algorithm does the same as the first with `before` done
+    /// first and `after` done last, e.g. an AUF.
+    Adjusted { before: String, after: String },
+    /// The second algorithm is the first done from a different angle.
+    Rotated(String),
+    Different(Vec<StickerDiff>)
+}
+
+/// A solved cube with every sticker numbered instead of coloured, so that
+/// where each one goes can be read off after turning it.
+fn numbered(puzzle: &PuzzleType) -> PuzzleType {
+    fn number<const F: usize, const N: usize>(state: &mut [[u8; N]; F]) {
+        for (face, stickers) in state.iter_mut().enumerate() {
+            for (index, sticker) in stickers.iter_mut().enumerate() {
+                *sticker = (face * N + index) as u8;
+            }
+        }
+    }
+
+    let mut puzzle = puzzle.solved();
+    match &mut puzzle {
+        PuzzleType::RubiksCube(cube) => number(&mut cube.state),
+        PuzzleType::RubiksCube2x2(cube) => number(&mut cube.state),
+        _ => {}
+    }
+    puzzle
+}
+
+/// `stickers` as seen after turning the whole cube with `rotation` (x, y or
+/// z): the face it turns like, the opposite face the other way and the
+/// middle layer along with them. The middle layer has no stickers of its
+/// own in the model, so its stickers move with the rest of their face.
+fn rotated(puzzle: &PuzzleType, stickers: Vec<Vec<u8>>, rotation: &Move) -> Vec<Vec<u8>> {
+    let turns = match rotation.letter {
+        'x' => "R L'",
+        'y' => "U D'",
+        _ => "F B'"
+    };
+    let mut turned = numbered(puzzle);
+    turned.input_moves(turns);
+
+    let size = stickers[0].len();
+    let sources: Vec<Vec<usize>> = turned.stickers().iter().enumerate().map(|(face, from)| {
+        let from: Vec<usize> = from.iter().map(|&x| x as usize).collect();
+        let moved = (0..size).find(|&x| from[x] != face * size + x);
+        (0..size).map(|index| match moved {
+            Some(moved) if from[index] == face * size + index => {
+                from[moved] / size * size + (from[moved] + size + index - moved) % size
+            }
+            _ => from[index]
+        }).collect()
+    }).collect();
+
+    (0..rotation.amount.rem_euclid(4)).fold(stickers, |stickers, _| {
+        sources.iter().map(|face| face.iter().map(|&x| stickers[x / size][x % size]).collect()).collect()
+    })
+}
+
+/// A solved puzzle of the same kind as `puzzle` after `alg`. Cube
+/// algorithms are done as face turns, as the model only has those, and any
+/// rotation left over is then applied to the stickers, so `r` is not the
+/// same as `R`.
+pub fn solved_after(puzzle: &PuzzleType, alg: &str) -> Result<PuzzleType, String> {
+    let notation = Notation::of(puzzle);
+    let mut result = puzzle.solved();
+    if notation != Notation::Cube {
+        History::default().apply(&mut result, alg)?;
+        return Ok(result);
+    }
+
+    let (turns, held) = face_turns(&notation.parse(&parser::expand(alg, notation)?)?)?;
+    result.input_moves(&format(&simplify(&turns)));
+    let stickers = held.iter().fold(result.stickers(), |stickers, rotation| rotated(puzzle, stickers, rotation));
+    match &mut result {
+        PuzzleType::RubiksCube(cube) => cube.state.iter_mut().zip(stickers).for_each(|(face, x)| face.copy_from_slice(&x)),
+        PuzzleType::RubiksCube2x2(cube) => cube.state.iter_mut().zip(stickers).for_each(|(face, x)| face.copy_from_slice(&x)),
+        _ => {}
+    }
+    Ok(result)
+}
+
+/// What `alg` does to a solved puzzle, sticker by sticker.
+fn result_of(puzzle: &PuzzleType, alg: &str) -> Result<Vec<Vec<u8>>, String> {
+    Ok(solved_after(puzzle, alg)?.stickers())
+}
+
+/// Every way the whole puzzle can be turned, as the shortest sequence of
+/// rotations that gets there (starting with no rotation at all). Found by
+/// turning around two axes until nothing new turns up.
+fn rotations(notation: Notation) -> Vec<Vec<Move>> {
+    let Ok((faces, _)) = geometry(notation) else { return Vec::new() };
+    let axes = match notation {
+        Notation::Cube => "xy",
+        _ => &faces[..2]
+    };
+    let probe: Vec<Move> = faces.chars().filter_map(|x| notation.parse_move(&x.to_string()).ok()).collect();
+
+    let mut found = vec![Vec::new()];
+    let mut seen = HashSet::from([format(&probe)]);
+    let mut next = 0;
+    while next < found.len() {
+        for axis in axes.chars() {
+            let mut sequence = found[next].clone();
+            sequence.push(Move { layers: String::new(), letter: axis, wide: false, amount: 1, notation });
+            let sequence = simplify(&sequence);
+
+            if let Ok(rotated) = rotate(&probe, notation, &sequence) {
+                if seen.insert(format(&rotated)) {
+                    found.push(sequence);
+                }
+            }
+        }
+        next += 1;
+    }
+    found
+}
+
+/// Turns of U, for puzzles that have one.
+fn adjustments(puzzle: &PuzzleType) -> Vec<String> {
+    let notation = Notation::of(puzzle);
+    if !puzzle.move_set().0.contains('U') {
+        return vec![String::new()];
+    }
+    (0..notation.order()).map(|amount| {
+        let turn = Move { layers: String::new(), letter: 'U', wide: false, amount, notation };
+        if turn.is_identity() { String::new() } else { turn.to_string() }
+    }).collect()
+}
+
+/// Checks whether `first` and `second` do the same thing to a solved puzzle
+/// of the same kind as `puzzle`, allowing for a U turn before or after
+/// (preferring just one of them) or for the second to be the first done
+/// after turning the puzzle over.
+pub fn compare(puzzle: &PuzzleType, first: &str, second: &str) -> Result<Equivalence, String> {
+    let target = result_of(puzzle, second)?;
+    let expanded = parser::expand(first, Notation::of(puzzle))?;
+    let result = result_of(puzzle, &expanded)?;
+    if result == target {
+        return Ok(Equivalence::Equal);
+    }
+
+    let turns = adjustments(puzzle);
+    let mut pairs: Vec<(&String, &String)> = turns.iter().flat_map(|x| turns.iter().map(move |y| (x, y))).collect();
+    pairs.sort_by_key(|(before, after)| !before.is_empty() && !after.is_empty());
+
+    for (before, after) in pairs.into_iter().filter(|(x, y)| !x.is_empty() || !y.is_empty()) {
+        if result_of(puzzle, &format!("{before} {expanded} {after}"))? == target {
+            return Ok(Equivalence::Adjusted { before: before.clone(), after: after.clone() });
+        }
+    }
+
+    let notation = Notation::of(puzzle);
+    let moves = notation.parse(&expanded)?;
+    for rotation in rotations(notation).into_iter().skip(1) {
+        let Ok(rotated) = rotate(&moves, notation, &rotation) else { continue };
+        if result_of(puzzle, &format(&rotated)).is_ok_and(|x| x == target) {
+            return Ok(Equivalence::Rotated(format(&rotation)));
+        }
+    }
+
+    let letters = puzzle.face_letters();
+    let mut diff = Vec::new();
+    for (face, (a, b)) in result.iter().zip(target.iter()).enumerate() {
+        for (index, (&x, &y)) in a.iter().zip(b.iter()).enumerate() {
+            if x != y {
+                diff.push(StickerDiff { face: letters[face], index, first: letters[x as usize], second: letters[y as usize] });
+            }
+        }
+    }
+    Ok(Equivalence::Different(diff))
+}
+
+impl fmt::Display for Equivalence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Equivalence::Equal => write!(f, "Equal"),
+            Equivalence::Adjusted { before, after } => {
+                write!(f, "Equal up to")?;
+                if !before.is_empty() {
+                    write!(f, " {before} before")?;
+                }
+                if !before.is_empty() && !after.is_empty() {
+                    write!(f, " and")?;
+                }
+                if !after.is_empty() {
+                    write!(f, " {after} after")?;
+                }
+                Ok(())
+            }
+            Equivalence::Rotated(rotation) => write!(f, "Equal when done after rotating with {rotation}"),
+            Equivalence::Different(diff) => {
+                write!(f, "Different ({} stickers):", diff.len())?;
+                for sticker in diff {
+                    write!(f, "\n  {}{}: {} vs {}", sticker.face, sticker.index, sticker.first, sticker.second)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compare_3x3(first: &str, second: &str) -> Equivalence {
+        compare(&PuzzleType::from_name("3x3").unwrap(), first, second).unwrap()
+    }
+
+    #[test]
+    fn wide_moves_are_not_face_turns() {
+        assert!(matches!(compare_3x3("r", "R"), Equivalence::Different(diff) if !diff.is_empty()));
+        assert!(matches!(compare_3x3("r", "R M'"), Equivalence::Equal));
+        assert!(matches!(compare_3x3("R M'", "r"), Equivalence::Equal));
+        assert!(matches!(compare_3x3("M2 U M2 U2 M2 U M2", "M2 U M2 U2 M2 U M2"), Equivalence::Equal));
+    }
+
+    #[test]
+    fn rotations_are_kept() {
+        assert!(matches!(compare_3x3("x U x'", "F"), Equivalence::Equal));
+        assert!(matches!(compare_3x3("y R y'", "B"), Equivalence::Equal));
+        assert!(matches!(compare_3x3("z' U z", "R"), Equivalence::Equal));
+        assert!(matches!(compare_3x3("[x: R U]", "R F"), Equivalence::Equal));
+        assert!(matches!(compare_3x3("x", ""), Equivalence::Different(diff) if !diff.is_empty()));
+        assert!(matches!(compare_3x3("x y x'", "z"), Equivalence::Equal));
+    }
+
+    #[test]
+    fn auf_and_rotation() {
+        assert!(matches!(compare_3x3("U R U R' U R U2 R'", "R U R' U R U2 R'"), Equivalence::Adjusted { .. }));
+        assert!(matches!(compare_3x3("R U R'", "F U F'"), Equivalence::Rotated(_)));
+        assert!(matches!(compare_3x3("R U R' U'", "R U2 R'"), Equivalence::Different(_)));
+    }
+
+    #[test]
+    fn other_puzzles() {
+        let skewb = PuzzleType::from_name("Skewb").unwrap();
+        assert!(matches!(compare(&skewb, "R L R'", "R L R'").unwrap(), Equivalence::Equal));
+        let cube = PuzzleType::from_name("2x2").unwrap();
+        assert!(matches!(compare(&cube, "x U x'", "F").unwrap(), Equivalence::Equal));
+        // With no centres, turning both layers is the same as turning one.
+        assert!(matches!(compare(&cube, "r", "R").unwrap(), Equivalence::Equal));
+    }
+}
@@ -0,0 +1,225 @@
+use std::fmt;
+
+use crate::algorithms::{format, Notation};
+use crate::algorithms::simplify::simplify;
+use crate::algorithms::transforms::face_turns;
+use crate::models::rubiks_cube::RubiksCube;
+
+/// One algorithm for each OLL case, numbered as on the usual sheets.
+pub const OLL: [(&str, &str); 57] = [
+    ("OLL 1", "R U2 R2 F R F' U2 R' F R F'"),
+    ("OLL 2", "F R U R' U' F' f R U R' U' f'"),
+    ("OLL 3", "f R U R' U' f' U' F R U R' U' F'"),
+    ("OLL 4", "f R U R' U' f' U F R U R' U' F'"),
+    ("OLL 5", "r' U2 R U R' U r"),
+    ("OLL 6", "r U2 R' U' R U' r'"),
+    ("OLL 7", "r U R' U R U2 r'"),
+    ("OLL 8", "l' U' L U' L' U2 l"),
+    ("OLL 9", "R U R' U' R' F R2 U R' U' F'"),
+    ("OLL 10", "R U R' U R' F R F' R U2 R'"),
+    ("OLL 11", "r U R' U R' F R F' R U2 r'"),
+    ("OLL 12", "M' R' U' R U' R' U2 R U' M"),
+    ("OLL 13", "F U R U' R2 F' R U R U' R'"),
+    ("OLL 14", "R' F R U R' F' R F U' F'"),
+    ("OLL 15", "r' U' r R' U' R U r' U r"),
+    ("OLL 16", "r U r' R U R' U' r U' r'"),
+    ("OLL 17", "R U R' U R' F R F' U2 R' F R F'"),
+    ("OLL 18", "r U R' U R U2 r2 U' R U' R' U2 r"),
+    ("OLL 19", "r' R U R U R' U' M' R' F R F'"),
+    ("OLL 20", "r U R' U' M2 U R U' R' U' M'"),
+    ("OLL 21", "R U2 R' U' R U R' U' R U' R'"),
+    ("OLL 22", "R U2 R2 U' R2 U' R2 U2 R"),
+    ("OLL 23", "R2 D' R U2 R' D R U2 R"),
+    ("OLL 24", "r U R' U' r' F R F'"),
+    ("OLL 25", "F' r U R' U' r' F R"),
+    ("OLL 26", "R U2 R' U' R U' R'"),
+    ("OLL 27", "R U R' U R U2 R'"),
+    ("OLL 28", "r U R' U' M U R U' R'"),
+    ("OLL 29", "R U R' U' R U' R' F' U' F R U R'"),
+    ("OLL 30", "F R' F R2 U' R' U' R U R' F2"),
+    ("OLL 31", "R' U' F U R U' R' F' R"),
+    ("OLL 32", "L U F' U' L' U L F L'"),
+    ("OLL 33", "R U R' U' R' F R F'"),
+    ("OLL 34", "R U R2 U' R' F R U R U' F'"),
+    ("OLL 35", "R U2 R2 F R F' R U2 R'"),
+    ("OLL 36", "L' U' L U' L' U L U L F' L' F"),
+    ("OLL 37", "F R' F' R U R U' R'"),
+    ("OLL 38", "R U R' U R U' R' U' R' F R F'"),
+    ("OLL 39", "L F' L' U' L U F U' L'"),
+    ("OLL 40", "R' F R U R' U' F' U R"),
+    ("OLL 41", "R U R' U R U2 R' F R U R' U' F'"),
+    ("OLL 42", "R' U' R U' R' U2 R F R U R' U' F'"),
+    ("OLL 43", "F' U' L' U L F"),
+    ("OLL 44", "F U R U' R' F'"),
+    ("OLL 45", "F R U R' U' F'"),
+    ("OLL 46", "R' U' R' F R F' U R"),
+    ("OLL 47", "R' U' R' F R F' R' F R F' U R"),
+    ("OLL 48", "F R U R' U' R U R' U' F'"),
+    ("OLL 49", "r U' r2 U r2 U r2 U' r"),
+    ("OLL 50", "r' U r2 U' r2 U' r2 U r'"),
+    ("OLL 51", "F U R U' R' U R U' R' F'"),
+    ("OLL 52", "R U R' U R U' B U' B' R'"),
+    ("OLL 53", "l' U2 L U L' U' L U L' U l"),
+    ("OLL 54", "r U2 R' U' R U R' U' R U' r'"),
+    ("OLL 55", "R' F R U R U' R2 F' R2 U' R' U R U R'"),
+    ("OLL 56", "r' U' r U' R' U R U' R' U R r' U r"),
+    ("OLL 57", "R U R' U' M' U R U' r'")
+];
+
+pub const PLL: [(&str, &str); 21] = [
+    ("Aa", "x R' U R' D2 R U' R' D2 R2 x'"),
+    ("Ab", "x R2 D2 R U R' D2 R U' R x'"),
+    ("E", "x' R U' R' D R U R' D' R U R' D R U' R' D' x"),
+    ("F", "R' U' F' R U R' U' R' F R2 U' R' U' R U R' U R"),
+    ("Ga", "R2 U R' U R' U' R U' R2 U' D R' U R D'"),
+    ("Gb", "R' U' R U D' R2 U R' U R U' R U' R2 D"),
+    ("Gc", "R2 U' R U' R U R' U R2 U D' R U' R' D"),
+    ("Gd", "R U R' U' D R2 U' R U' R' U R' U R2 D'"),
+    ("H", "M2 U M2 U2 M2 U M2"),
+    ("Ja", "x R2 F R F' R U2 r' U r U2 x'"),
+    ("Jb", "R U R' F' R U R' U' R' F R2 U' R'"),
+    ("Na", "R U R' U R U R' F' R U R' U' R' F R2 U' R' U2 R U' R'"),
+    ("Nb", "R' U R U' R' F' U' F R U R' F R' F' R U' R"),
+    ("Ra", "R U' R' U' R U R D R' U' R D' R' U2 R'"),
+    ("Rb", "R2 F R U R U' R' F' R U2 R' U2 R"),
+    ("T", "R U R' U' R' F R2 U' R' U' R U R' F'"),
+    ("Ua", "M2 U M U2 M' U M2"),
+    ("Ub", "M2 U' M U2 M' U' M2"),
+    ("V", "R' U R' U' y R' F' R2 U' R' U R' F R F"),
+    ("Y", "F R U' R' U' R U R' F' R U R' U' R' F R F'"),
+    ("Z", "M' U M2 U M2 U M' U2 M2")
+];
+
+/// A recognised case: do `before`, then `alg`, then `after` (both AUFs, and
+/// either can be empty).
+pub struct Case {
+    pub name: &'static str,
+    pub before: String,
+    pub alg: &'static str,
+    pub after: String
+}
+
+pub enum LastLayer {
+    Oll(Case),
+    Pll(Case),
+    /// Oriented and permuted already, but maybe needing an AUF.
+    Solved(String)
+}
+
+/// U turns in the order they are tried, so that the shortest AUF wins.
+const AUFS: [&str; 4] = ["", "U", "U'", "U2"];
+
+/// Applies `alg` the only way the cube model can: as outer face turns.
+fn apply(cube: &RubiksCube, alg: &str) -> RubiksCube {
+    let mut cube = cube.clone();
+    if let Ok((turns, _)) = Notation::Cube.parse(alg).and_then(|x| face_turns(&x)) {
+        cube.input_moves(&format(&simplify(&turns)));
+    }
+    cube
+}
+
+fn oriented(cube: &RubiksCube) -> bool {
+    cube.state[0].iter().all(|&x| x == 0)
+}
+
+fn f2l_solved(cube: &RubiksCube) -> Result<bool, String> {
+    let pieces = cube.pieces()?;
+    let corners = (4..8).all(|i| pieces.corner_permutation[i] == i as u8 && pieces.corner_orientation[i] == 0);
+    let edges = (4..12).all(|i| pieces.edge_permutation[i] == i as u8 && pieces.edge_orientation[i] == 0);
+    Ok(corners && edges)
+}
+
+/// The AUF that solves `cube`, if it is one U turn (or none) from solved.
+fn final_auf(cube: &RubiksCube) -> Option<String> {
+    AUFS.iter().find(|x| apply(cube, x).state == RubiksCube::default().state).map(|x| x.to_string())
+}
+
+/// Names the last layer case of a cube with the first two layers solved:
+/// the OLL case if the top is not yet oriented, otherwise the PLL case.
+/// Cases are found by trying each stored algorithm after each AUF.
+pub fn recognise(cube: &RubiksCube) -> Result<LastLayer, String> {
+    if !f2l_solved(cube)? {
+        return Err("The first two layers are not solved".to_string());
+    }
+
+    if !oriented(cube) {
+        for before in AUFS {
+            let turned = apply(cube, before);
+            if let Some(&(name, alg)) = OLL.iter().find(|(_, alg)| oriented(&apply(&turned, alg))) {
+                return Ok(LastLayer::Oll(Case { name, before: before.to_string(), alg, after: String::new() }));
+            }
+        }
+        return Err("The last layer cannot be oriented".to_string());
+    }
+
+    if let Some(after) = final_auf(cube) {
+        return Ok(LastLayer::Solved(after));
+    }
+    for before in AUFS {
+        let turned = apply(cube, before);
+        for &(name, alg) in PLL.iter() {
+            if let Some(after) = final_auf(&apply(&turned, alg)) {
+                return Ok(LastLayer::Pll(Case { name, before: before.to_string(), alg, after }));
+            }
+        }
+    }
+    Err("The last layer cannot be permuted".to_string())
+}
+
+impl fmt::Display for Case {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let moves: Vec<&str> = [self.before.as_str(), self.alg, self.after.as_str()].into_iter().filter(|x| !x.is_empty()).collect();
+        write!(f, "{}: {}", self.name, moves.join(" "))
+    }
+}
+
+impl fmt::Display for LastLayer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LastLayer::Oll(case) | LastLayer::Pll(case) => write!(f, "{case}"),
+            LastLayer::Solved(after) if after.is_empty() => write!(f, "Solved"),
+            LastLayer::Solved(after) => write!(f, "Solved after {after}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::transforms::inverse_alg;
+    use crate::models::Puzzle;
+
+    /// The case `alg` solves, seen from each side.
+    fn cases(alg: &str) -> Vec<RubiksCube> {
+        let case = apply(&RubiksCube::default(), &inverse_alg(alg, Notation::Cube).unwrap());
+        AUFS.iter().map(|auf| apply(&case, auf)).collect()
+    }
+
+    #[test]
+    fn every_oll_is_recognised_as_itself() {
+        for (name, alg) in OLL {
+            for cube in cases(alg) {
+                let LastLayer::Oll(case) = recognise(&cube).unwrap() else { panic!("{name} is not an OLL case") };
+                assert_eq!(case.name, name);
+                assert!(oriented(&apply(&cube, &format!("{} {}", case.before, case.alg))), "{name}");
+            }
+        }
+    }
+
+    #[test]
+    fn every_pll_is_recognised_as_itself() {
+        for (name, alg) in PLL {
+            for cube in cases(alg) {
+                let LastLayer::Pll(case) = recognise(&cube).unwrap() else { panic!("{name} is not a PLL case") };
+                assert_eq!(case.name, name);
+                assert!(apply(&cube, &format!("{} {} {}", case.before, case.alg, case.after)).is_solved(), "{name}");
+            }
+        }
+    }
+
+    #[test]
+    fn solved_and_unsolved_f2l() {
+        assert!(matches!(recognise(&apply(&RubiksCube::default(), "U'")).unwrap(), LastLayer::Solved(after) if after == "U"));
+        assert!(recognise(&apply(&RubiksCube::default(), "R")).is_err());
+    }
+}
@@ -0,0 +1,152 @@
+use std::fmt;
+
+use crate::models::PuzzleType;
+
+pub mod cycles;
+pub mod equivalence;
+pub mod last_layer;
+pub mod simplify;
+pub mod transforms;
+
+/// Move notations understood by the algorithm tools. `Cube` covers the 2x2,
+/// 3x3 and bigger cubes (layer prefixes, wide moves, slices and rotations)
+/// and `Corner` the Skewb and Ivy, which share the letters U, F, R and L.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Notation {
+    Cube,
+    Megaminx,
+    Pyraminx,
+    Corner
+}
+
+impl Notation {
+    pub fn of(puzzle: &PuzzleType) -> Self {
+        match puzzle {
+            PuzzleType::RubiksCube(_) | PuzzleType::RubiksCube2x2(_) => Notation::Cube,
+            PuzzleType::Megaminx(_) => Notation::Megaminx,
+            PuzzleType::Pyraminx(_) => Notation::Pyraminx,
+            PuzzleType::Skewb(_) | PuzzleType::Ivy(_) => Notation::Corner
+        }
+    }
+
+    fn letters(&self) -> &'static str {
+        match self {
+            Notation::Cube => "UFRBLDMESxyzufrbld",
+            Notation::Megaminx => "UFRBVLPGOSJD",
+            Notation::Pyraminx => "FRLDTEKB",
+            Notation::Corner => "UFRL"
+        }
+    }
+
+    /// Turns of any layer that bring it back to where it started.
+    pub fn order(&self) -> i32 {
+        match self {
+            Notation::Cube => 4,
+            Notation::Megaminx => 5,
+            Notation::Pyraminx | Notation::Corner => 3
+        }
+    }
+
+    /// Parses one move such as `R`, `U2'`, `3Rw2`, `x'` or `T'`. Pochmann
+    /// Megaminx moves (`R++`, `D--`) turn all but one face of the puzzle,
+    /// which the model cannot do, so they are refused rather than read as
+    /// face turns.
+    pub fn parse_move(&self, token: &str) -> Result<Move, String> {
+        let invalid = || format!("'{token}' is not a move");
+        // Only the cube tells upper and lower case apart (`r` is `Rw`).
+        let cube = *self == Notation::Cube;
+
+        let start = token.find(|x: char| !x.is_ascii_digit() && x != '-').ok_or_else(invalid)?;
+        let (layers, rest) = token.split_at(start);
+        if !layers.is_empty() && !cube {
+            return Err(invalid());
+        }
+
+        let mut chars = rest.chars();
+        let letter = chars.next().map(|x| if cube { x } else { x.to_ascii_uppercase() });
+        let letter = letter.filter(|&x| self.letters().contains(x)).ok_or_else(invalid)?;
+        let mut suffix = chars.as_str();
+
+        let wide = cube && suffix.starts_with('w');
+        if wide {
+            suffix = &suffix[1..];
+        }
+
+        if *self == Notation::Megaminx && (suffix == "++" || suffix == "--") {
+            return Err(format!("'{token}' is a Pochmann move, which turns the whole Megaminx but one face; only face turns can be done"));
+        }
+        let (number, prime) = match suffix.strip_suffix('\'') {
+            Some(number) => (number, true),
+            None => (suffix, false)
+        };
+        let number: i32 = if number.is_empty() { 1 } else { number.parse().map_err(|_| invalid())? };
+        let amount = if prime { -number } else { number };
+
+        Ok(Move { layers: layers.to_string(), letter, wide, amount, notation: *self })
+    }
+
+    pub fn parse(&self, alg: &str) -> Result<Vec<Move>, String> {
+        alg.split_whitespace().map(|x| self.parse_move(x)).collect()
+    }
+}
+
+/// A single turn. `amount` counts quarter (or fifth, or third) turns
+/// clockwise and is negative for anticlockwise turns.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Move {
+    /// Layer prefix on big cubes, e.g. the `3` in `3Rw`.
+    pub layers: String,
+    pub letter: char,
+    pub wide: bool,
+    pub amount: i32,
+    pub notation: Notation
+}
+
+impl Move {
+    pub fn inverse(&self) -> Self {
+        Self { amount: -self.amount, ..self.clone() }
+    }
+
+    /// Whether the move does nothing, e.g. `R4`.
+    pub fn is_identity(&self) -> bool {
+        self.amount.rem_euclid(self.notation.order()) == 0
+    }
+
+    /// Whether both moves turn the same layers, so they can be merged.
+    pub fn same_layer(&self, other: &Move) -> bool {
+        self.letter == other.letter && self.layers == other.layers && self.wide == other.wide
+    }
+}
+
+/// Writes the move the way the models read it: `R`, `R'`, and otherwise the
+/// number of clockwise turns (`R2`, and `R3` on the Megaminx).
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let order = self.notation.order();
+        let wide = if self.wide { "w" } else { "" };
+        let amount = match self.amount.rem_euclid(order) {
+            1 => String::new(),
+            x if x == order - 1 => "'".to_string(),
+            x => x.to_string()
+        };
+        write!(f, "{}{}{}{}", self.layers, self.letter, wide, amount)
+    }
+}
+
+pub fn format(moves: &[Move]) -> String {
+    moves.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pochmann_moves_are_refused() {
+        for token in ["R++", "D--"] {
+            assert!(Notation::Megaminx.parse_move(token).is_err_and(|x| x.contains("Pochmann")), "{token}");
+        }
+        assert_eq!(Notation::Megaminx.parse_move("R2'").unwrap().amount, -2);
+        assert!(Notation::Cube.parse_move("R++").is_err());
+    }
+}
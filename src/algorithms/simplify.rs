@@ -0,0 +1,181 @@
+use std::fmt;
+
+use crate::algorithms::{format, Move, Notation};
+use crate::algorithms::transforms::geometry;
+use crate::models::parser;
+
+/// Cube moves on the same axis commute: U, D, E, y and the wide u, d.
+fn cube_axis(letter: char) -> usize {
+    match letter.to_ascii_uppercase() {
+        'U' | 'D' | 'E' | 'Y' => 0,
+        'R' | 'L' | 'M' | 'X' => 1,
+        _ => 2
+    }
+}
+
+/// Whether doing `a` then `b` is the same as `b` then `a`.
+pub fn commute(a: &Move, b: &Move) -> bool {
+    if a.letter == b.letter {
+        return true;
+    }
+
+    match a.notation {
+        Notation::Cube => cube_axis(a.letter) == cube_axis(b.letter),
+        // Faces that do not touch share no pieces.
+        Notation::Megaminx => match geometry(Notation::Megaminx) {
+            Ok((faces, neighbours)) => match (faces.find(a.letter), faces.find(b.letter)) {
+                (Some(x), Some(y)) => !neighbours[x].contains(&y),
+                _ => false
+            },
+            Err(_) => false
+        },
+        // Tips only touch the three faces they sit on.
+        Notation::Pyraminx => {
+            let tip = |x: char| "TEKB".find(x);
+            let face = |x: char| "FRLD".find(x);
+            match (tip(a.letter), tip(b.letter), face(a.letter), face(b.letter)) {
+                (Some(_), Some(_), _, _) => true,
+                (Some(x), _, _, Some(y)) | (_, Some(x), Some(y), _) => x + y == 3,
+                _ => false
+            }
+        }
+        Notation::Corner => false
+    }
+}
+
+/// Merges turns of the same layer and cancels turns that undo each other,
+/// looking past moves that commute, e.g. `R L R'` becomes `L`.
+pub fn simplify(moves: &[Move]) -> Vec<Move> {
+    let mut simplified: Vec<Move> = Vec::new();
+
+    for turn in moves.iter().filter(|x| !x.is_identity()) {
+        let merge = simplified.iter().rposition(|x| x.same_layer(turn) || !commute(x, turn));
+
+        match merge {
+            Some(i) if simplified[i].same_layer(turn) => {
+                simplified[i].amount += turn.amount;
+                if simplified[i].is_identity() {
+                    simplified.remove(i);
+                }
+            }
+            _ => simplified.push(turn.clone())
+        }
+    }
+
+    for turn in simplified.iter_mut() {
+        let order = turn.notation.order();
+        turn.amount = turn.amount.rem_euclid(order);
+        if turn.amount > order / 2 {
+            turn.amount -= order;
+        }
+    }
+    simplified
+}
+
+/// Move counts in the usual metrics. HTM counts any turn of one outer block
+/// as one move, QTM counts quarter turns, STM also counts slices as one move
+/// and ETM counts every move including rotations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Metrics {
+    pub htm: usize,
+    pub qtm: usize,
+    pub stm: usize,
+    pub etm: usize
+}
+
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} HTM, {} QTM, {} STM, {} ETM", self.htm, self.qtm, self.stm, self.etm)
+    }
+}
+
+pub fn metrics(moves: &[Move]) -> Metrics {
+    let mut metrics = Metrics::default();
+
+    for turn in moves {
+        let order = turn.notation.order();
+        let amount = turn.amount.rem_euclid(order);
+        let quarters = amount.min(order - amount) as usize;
+
+        let rotation = "xyz".contains(turn.letter);
+        let slice = "MES".contains(turn.letter) || (!turn.wide && !turn.layers.is_empty() && turn.layers != "1");
+        let (layers, counted) = match (rotation, slice) {
+            (true, _) => (0, 0),
+            (_, true) => (2, 1),
+            _ => (1, 1)
+        };
+
+        metrics.htm += layers;
+        metrics.qtm += quarters * layers;
+        metrics.stm += counted;
+        metrics.etm += 1;
+    }
+    metrics
+}
+
+/// Expands, simplifies and writes out `alg`, with its move counts.
+pub fn simplify_alg(alg: &str, notation: Notation) -> Result<(String, Metrics), String> {
+    let moves = simplify(&parser::parse(alg, notation)?);
+    Ok((format(&moves), metrics(&moves)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use crate::models::PuzzleType;
+
+    fn simplified(alg: &str, notation: Notation) -> String {
+        simplify_alg(alg, notation).unwrap().0
+    }
+
+    #[test]
+    fn cancels_and_merges() {
+        assert_eq!(simplified("R L R'", Notation::Cube), "L");
+        assert_eq!(simplified("U D U", Notation::Cube), "U2 D");
+        assert_eq!(simplified("R U U' R'", Notation::Cube), "");
+        assert_eq!(simplified("M x M'", Notation::Cube), "x");
+        assert_eq!(simplified("R U R", Notation::Cube), "R U R");
+        assert_eq!(simplified("T E T'", Notation::Pyraminx), "E");
+        assert_eq!(simplified("R R R", Notation::Pyraminx), "");
+        assert_eq!(simplified("R F R", Notation::Corner), "R F R");
+        assert_eq!(simplified("R R2 R'", Notation::Megaminx), "R2");
+    }
+
+    #[test]
+    fn keeps_what_each_puzzle_does() {
+        let mut rng = rand::thread_rng();
+        for name in ["2x2", "3x3", "Skewb", "Pyraminx", "Megaminx", "Ivy"] {
+            let puzzle = PuzzleType::from_name(name).unwrap();
+            let (letters, _, order) = puzzle.move_set();
+            let letters: Vec<char> = letters.chars().collect();
+            let notation = Notation::of(&puzzle);
+
+            for _ in 0..50 {
+                // Few letters, so that plenty of moves cancel.
+                let alg: Vec<Move> = (0..30).map(|_| Move {
+                    layers: String::new(),
+                    letter: letters[rng.gen_range(0..3)],
+                    wide: false,
+                    amount: rng.gen_range(1..order as i32),
+                    notation
+                }).collect();
+                let simple = simplify(&alg);
+                assert!(simple.len() <= alg.len());
+
+                let (mut before, mut after) = (puzzle.clone(), puzzle.clone());
+                before.input_moves(&format(&alg));
+                after.input_moves(&format(&simple));
+                assert_eq!(before.stickers(), after.stickers(), "{name}: {} vs {}", format(&alg), format(&simple));
+            }
+        }
+    }
+
+    #[test]
+    fn counts_metrics() {
+        let count = |alg| metrics(&parser::parse(alg, Notation::Cube).unwrap());
+        assert_eq!(count("R U R' U'"), Metrics { htm: 4, qtm: 4, stm: 4, etm: 4 });
+        assert_eq!(count("M2 U M2 U2"), Metrics { htm: 6, qtm: 11, stm: 4, etm: 4 });
+        assert_eq!(count("x R2 r"), Metrics { htm: 2, qtm: 3, stm: 2, etm: 3 });
+    }
+}
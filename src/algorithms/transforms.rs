@@ -0,0 +1,289 @@
+use crate::algorithms::{format, Move, Notation};
+
+/// Faces in the same order as each model's `Faces`, and the faces around
+/// each one in the order its turn cycles them (same as the `adjacent` lists
+/// in the models' `rotate`). Mirrors and rotations are worked out from these.
+const CUBE_FACES: &str = "UFRBLD";
+const CUBE_NEIGHBOURS: [&[usize]; 6] = [&[3, 4, 1, 2], &[0, 4, 5, 2], &[0, 1, 5, 3], &[0, 2, 5, 4], &[0, 3, 5, 1], &[1, 4, 3, 2]];
+
+const MEGAMINX_FACES: &str = "UFRBVLGOSJPD";
+const MEGAMINX_NEIGHBOURS: [&[usize]; 12] = [
+    &[1, 2, 3, 4, 5], &[10, 6, 2, 0, 5], &[6, 7, 3, 0, 1], &[7, 8, 4, 0, 2], &[8, 9, 5, 0, 3], &[9, 10, 1, 0, 4],
+    &[11, 7, 2, 1, 10], &[11, 8, 3, 2, 6], &[11, 9, 4, 3, 7], &[11, 10, 5, 4, 8], &[11, 6, 1, 5, 9], &[10, 9, 8, 7, 6]
+];
+
+const PYRAMINX_FACES: &str = "FRLD";
+const PYRAMINX_NEIGHBOURS: [&[usize]; 4] = [&[2, 3, 1], &[0, 3, 2], &[1, 3, 0], &[0, 2, 1]];
+/// Each tip and the face opposite it.
+const PYRAMINX_TIPS: [(char, usize); 4] = [('T', 3), ('E', 2), ('K', 1), ('B', 0)];
+
+/// Slices and rotations turn the same way as one face of their axis: M as L,
+/// E as D and S as F; x as R, y as U and z as F.
+const SLICES: [(char, usize); 3] = [('M', 4), ('E', 5), ('S', 1)];
+const ROTATIONS: [(char, usize); 3] = [('x', 2), ('y', 0), ('z', 1)];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mirror {
+    /// Across the plane between R and L, e.g. for left-handed algorithms.
+    LeftRight,
+    /// Across the plane between F and B.
+    FrontBack
+}
+
+/// A symmetry of the puzzle as the face each face is sent to. Reflections
+/// also turn every move the other way.
+struct Symmetry {
+    faces: Vec<usize>,
+    reflection: bool
+}
+
+/// Face letters and the neighbour lists above for `notation`.
+pub fn geometry(notation: Notation) -> Result<(&'static str, &'static [&'static [usize]]), String> {
+    match notation {
+        Notation::Cube => Ok((CUBE_FACES, &CUBE_NEIGHBOURS)),
+        Notation::Megaminx => Ok((MEGAMINX_FACES, &MEGAMINX_NEIGHBOURS)),
+        Notation::Pyraminx => Ok((PYRAMINX_FACES, &PYRAMINX_NEIGHBOURS)),
+        Notation::Corner => Err("Mirrors and rotations are not available for the Skewb and Ivy".to_string())
+    }
+}
+
+fn face_index(faces: &str, letter: char) -> Option<usize> {
+    faces.find(letter.to_ascii_uppercase())
+}
+
+impl Symmetry {
+    /// The symmetry sending `a` to `a_image` and its neighbour `b` to
+    /// `b_image`, if there is one. Knowing where a face and one neighbour go
+    /// fixes where the rest of its neighbours go, so this spreads outwards.
+    fn find(neighbours: &[&[usize]], (a, a_image): (usize, usize), (b, b_image): (usize, usize), reflection: bool) -> Option<Self> {
+        let mut faces: Vec<Option<usize>> = vec![None; neighbours.len()];
+        faces[a] = Some(a_image);
+        faces[b] = Some(b_image);
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for face in 0..neighbours.len() {
+                let Some(image) = faces[face] else { continue };
+                let around = neighbours[face];
+                let Some(i) = around.iter().position(|&x| faces[x].is_some()) else { continue };
+                let j = neighbours[image].iter().position(|&x| Some(x) == faces[around[i]])?;
+
+                for k in 0..around.len() {
+                    let step = if reflection { around.len() - k } else { k };
+                    let target = neighbours[image][(j + step) % around.len()];
+                    match faces[around[(i + k) % around.len()]] {
+                        Some(x) if x != target => return None,
+                        Some(_) => {}
+                        None => {
+                            faces[around[(i + k) % around.len()]] = Some(target);
+                            changed = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        Some(Self { faces: faces.into_iter().collect::<Option<Vec<usize>>>()?, reflection })
+    }
+
+    fn mirror(notation: Notation, mirror: Mirror) -> Result<Self, String> {
+        let (faces, neighbours) = geometry(notation)?;
+        let fixed = match (notation, mirror) {
+            (Notation::Cube, Mirror::LeftRight) | (Notation::Megaminx, Mirror::LeftRight) => ('U', 'F'),
+            (Notation::Cube, Mirror::FrontBack) => ('U', 'R'),
+            (Notation::Pyraminx, Mirror::LeftRight) => ('D', 'F'),
+            _ => return Err(format!("{notation:?} has no {mirror:?} mirror"))
+        };
+
+        let (a, b) = (face_index(faces, fixed.0).unwrap(), face_index(faces, fixed.1).unwrap());
+        Self::find(neighbours, (a, a), (b, b), true).ok_or("The puzzle has no such mirror".to_string())
+    }
+
+    /// Turning the whole puzzle one step the same way as `face` turns.
+    fn rotation(notation: Notation, face: usize) -> Result<Self, String> {
+        let (_, neighbours) = geometry(notation)?;
+        let around = neighbours[face];
+        Self::find(neighbours, (face, face), (around[0], around[1]), false).ok_or("The puzzle has no such rotation".to_string())
+    }
+
+    /// The face opposite `face` on the cube.
+    fn opposite(neighbours: &[&[usize]], face: usize) -> Option<usize> {
+        (0..neighbours.len()).find(|&x| x != face && !neighbours[face].contains(&x))
+    }
+
+    /// The move that does to the transformed puzzle what `turn` does to the
+    /// original one.
+    fn apply(&self, turn: &Move) -> Result<Move, String> {
+        let (faces, neighbours) = geometry(turn.notation)?;
+        let mut amount = if self.reflection { -turn.amount } else { turn.amount };
+
+        let letter = if let Some(face) = face_index(faces, turn.letter) {
+            let letter = faces.as_bytes()[self.faces[face]] as char;
+            if turn.letter.is_lowercase() { letter.to_ascii_lowercase() } else { letter }
+        } else if let Some(&(_, opposite)) = PYRAMINX_TIPS.iter().find(|x| x.0 == turn.letter && turn.notation == Notation::Pyraminx) {
+            PYRAMINX_TIPS.iter().find(|x| x.1 == self.faces[opposite]).unwrap().0
+        } else {
+            let group = if SLICES.iter().any(|x| x.0 == turn.letter) { &SLICES } else { &ROTATIONS };
+            let &(_, like) = group.iter().find(|x| x.0 == turn.letter).ok_or(format!("Cannot transform {turn}"))?;
+            let image = self.faces[like];
+
+            match group.iter().find(|x| x.1 == image) {
+                Some(x) => x.0,
+                None => {
+                    amount = -amount;
+                    let opposite = Self::opposite(neighbours, image).ok_or(format!("Cannot transform {turn}"))?;
+                    group.iter().find(|x| x.1 == opposite).ok_or(format!("Cannot transform {turn}"))?.0
+                }
+            }
+        };
+
+        Ok(Move { letter, amount, ..turn.clone() })
+    }
+}
+
+/// The moves in reverse order, each turned the other way.
+pub fn inverse(moves: &[Move]) -> Vec<Move> {
+    moves.iter().rev().map(|x| x.inverse()).collect()
+}
+
+pub fn mirror(moves: &[Move], notation: Notation, mirror: Mirror) -> Result<Vec<Move>, String> {
+    let symmetry = Symmetry::mirror(notation, mirror)?;
+    moves.iter().map(|x| symmetry.apply(x)).collect()
+}
+
+/// Rewrites `moves` as if the puzzle had been turned by `rotations` first, so
+/// that the result does the same as `rotations moves rotations'`. Cubes are
+/// turned with x, y and z; other puzzles with the letter of the face whose
+/// axis they turn around (e.g. `U2` on the Megaminx).
+pub fn rotate(moves: &[Move], notation: Notation, rotations: &[Move]) -> Result<Vec<Move>, String> {
+    let (faces, _) = geometry(notation)?;
+    let mut moves = moves.to_vec();
+
+    for rotation in rotations.iter().rev() {
+        let like = match notation {
+            Notation::Cube => ROTATIONS.iter().find(|x| x.0 == rotation.letter).map(|x| x.1),
+            _ => face_index(faces, rotation.letter)
+        };
+        let like = like.ok_or(format!("{rotation} is not a rotation"))?;
+        let symmetry = Symmetry::rotation(notation, like)?;
+
+        for _ in 0..rotation.amount.rem_euclid(notation.order()) {
+            moves = moves.iter().map(|x| symmetry.apply(x)).collect::<Result<_, _>>()?;
+        }
+    }
+    Ok(moves)
+}
+
+/// How a slice or a wide move is made of outer turns and a rotation on the
+/// 3x3: M is R L' x', E is U D' y', S is F' B z, and e.g. r is L x. Each
+/// entry is the move, the outer turns with their direction, and the rotation.
+type Turn = (char, i32);
+const COMPOSITES: [(char, &[Turn], Turn); 9] = [
+    ('M', &[('R', 1), ('L', -1)], ('x', -1)),
+    ('E', &[('U', 1), ('D', -1)], ('y', -1)),
+    ('S', &[('F', -1), ('B', 1)], ('z', 1)),
+    ('r', &[('L', 1)], ('x', 1)),
+    ('l', &[('R', 1)], ('x', -1)),
+    ('u', &[('D', 1)], ('y', 1)),
+    ('d', &[('U', 1)], ('y', -1)),
+    ('f', &[('B', 1)], ('z', 1)),
+    ('b', &[('F', 1)], ('z', -1))
+];
+
+/// Rewrites a 3x3 algorithm with slices, wide moves and rotations as turns
+/// of the six outer faces, which is all the cube model can do. Turning the
+/// whole cube does not change the puzzle, so the rotations are moved to the
+/// end and returned separately: they say how the cube is being held after.
+pub fn face_turns(moves: &[Move]) -> Result<(Vec<Move>, Vec<Move>), String> {
+    let mut turns = Vec::new();
+    let mut held: Vec<Move> = Vec::new();
+
+    for turn in moves {
+        if turn.notation != Notation::Cube || !(turn.layers.is_empty() || turn.layers == "1") {
+            return Err(format!("{turn} is not a 3x3 move"));
+        }
+
+        let letter = if turn.wide { turn.letter.to_ascii_lowercase() } else { turn.letter };
+        let (outer, rotation) = match COMPOSITES.iter().find(|x| x.0 == letter) {
+            Some(&(_, outer, (axis, direction))) => (outer.to_vec(), Some((axis, direction))),
+            None if ROTATIONS.iter().any(|x| x.0 == letter) => (Vec::new(), Some((letter, 1))),
+            None => (vec![(letter, 1)], None)
+        };
+
+        let outer: Vec<Move> = outer.into_iter()
+            .map(|(letter, direction)| Move { layers: String::new(), letter, wide: false, amount: turn.amount * direction, notation: Notation::Cube })
+            .collect();
+        turns.extend(rotate(&outer, Notation::Cube, &held)?);
+
+        if let Some((axis, direction)) = rotation {
+            held.push(Move { layers: String::new(), letter: axis, wide: false, amount: turn.amount * direction, notation: Notation::Cube });
+        }
+    }
+    Ok((turns, held))
+}
+
+/// `inverse` on move text, e.g. to undo a scramble.
+pub fn inverse_alg(alg: &str, notation: Notation) -> Result<String, String> {
+    Ok(format(&inverse(&notation.parse(alg)?)))
+}
+
+pub fn mirror_alg(alg: &str, notation: Notation, direction: Mirror) -> Result<String, String> {
+    Ok(format(&mirror(&notation.parse(alg)?, notation, direction)?))
+}
+
+pub fn rotate_alg(alg: &str, notation: Notation, rotations: &str) -> Result<String, String> {
+    Ok(format(&rotate(&notation.parse(alg)?, notation, &notation.parse(rotations)?)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::equivalence::solved_after;
+    use crate::models::PuzzleType;
+
+    #[test]
+    fn inverses() {
+        assert_eq!(inverse_alg("R U R' U'", Notation::Cube).unwrap(), "U R U' R'");
+        assert_eq!(inverse_alg("R U2 r' x", Notation::Cube).unwrap(), "x' r U2 R'");
+        assert_eq!(inverse_alg("R U2' D3", Notation::Megaminx).unwrap(), "D2 U2 R'");
+        assert_eq!(inverse_alg("R L' T", Notation::Pyraminx).unwrap(), "T' L R'");
+    }
+
+    #[test]
+    fn mirrors() {
+        assert_eq!(mirror_alg("R U R' U'", Notation::Cube, Mirror::LeftRight).unwrap(), "L' U' L U");
+        assert_eq!(mirror_alg("R U R' F'", Notation::Cube, Mirror::FrontBack).unwrap(), "R' U' R B");
+        assert_eq!(mirror_alg("M E S r x y z", Notation::Cube, Mirror::LeftRight).unwrap(), "M E' S' l' x y' z'");
+        assert_eq!(mirror_alg("R U R' F", Notation::Megaminx, Mirror::LeftRight).unwrap(), "L' U' L F'");
+        assert_eq!(mirror_alg("R L' E T", Notation::Pyraminx, Mirror::LeftRight).unwrap(), "L' R K' T'");
+        assert!(mirror_alg("R U", Notation::Corner, Mirror::LeftRight).is_err());
+        assert!(mirror_alg("R U", Notation::Megaminx, Mirror::FrontBack).is_err());
+    }
+
+    #[test]
+    fn rotations() {
+        assert_eq!(rotate_alg("R U F", Notation::Cube, "y").unwrap(), "B U R");
+        assert_eq!(rotate_alg("U", Notation::Cube, "x").unwrap(), "F");
+        assert_eq!(rotate_alg("M E S r", Notation::Cube, "y").unwrap(), "S E M' b");
+        assert_eq!(rotate_alg("R U", Notation::Cube, "y2").unwrap(), "L U");
+        assert_eq!(rotate_alg("F R U", Notation::Megaminx, "U").unwrap(), "R B U");
+        assert_eq!(rotate_alg("R L' E T", Notation::Pyraminx, "D").unwrap(), "F R' K T");
+        assert!(rotate_alg("R", Notation::Cube, "R").is_err());
+    }
+
+    /// Rotating an algorithm does the same as turning the cube, doing it and
+    /// turning the cube back.
+    #[test]
+    fn rotations_agree_with_the_model() {
+        let puzzle = PuzzleType::from_name("3x3").unwrap();
+        for alg in ["R U R' F2 D", "r U M' E2 S", "L' B D2 f"] {
+            for rotation in ["x", "y", "z", "x' y2", "z y"] {
+                let rotated = rotate_alg(alg, Notation::Cube, rotation).unwrap();
+                let inverse = inverse_alg(rotation, Notation::Cube).unwrap();
+                let expected = solved_after(&puzzle, &format!("{rotation} {alg} {inverse}")).unwrap();
+                assert_eq!(solved_after(&puzzle, &rotated).unwrap().stickers(), expected.stickers(), "{rotation}: {alg}");
+            }
+        }
+    }
+}
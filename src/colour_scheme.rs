@@ -0,0 +1,200 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use colored::ColoredString;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+lazy_static! {
+    static ref CURRENT_SCHEME: Mutex<ColourScheme> = Mutex::new(ColourScheme::western());
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Colour {
+    pub name: String,
+    pub letter: char,
+    /// SGR parameters used to draw the sticker, e.g. `31` or `38;5;208`.
+    pub ansi: String
+}
+
+impl Colour {
+    pub fn new(name: &str, letter: char, ansi: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            letter,
+            ansi: ansi.to_string()
+        }
+    }
+
+    pub fn paint(&self) -> ColoredString {
+        ColoredString::from(format!("\x1b[{}m{}\x1b[0m", self.ansi, self.letter))
+    }
+}
+
+/// Colours indexed by the sticker values stored in each model's `state`:
+/// `cube` for the 2x2, 3x3, Skewb and Ivy (same order as `models::Faces`),
+/// `pyraminx` for `pyraminx::Faces` and `megaminx` for `megaminx::Faces`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ColourScheme {
+    pub name: String,
+    pub cube: Vec<Colour>,
+    #[serde(default = "default_pyraminx")]
+    pub pyraminx: Vec<Colour>,
+    #[serde(default = "default_megaminx")]
+    pub megaminx: Vec<Colour>
+}
+
+fn default_pyraminx() -> Vec<Colour> {
+    ColourScheme::western().pyraminx
+}
+
+fn default_megaminx() -> Vec<Colour> {
+    ColourScheme::western().megaminx
+}
+
+impl ColourScheme {
+    pub fn western() -> Self {
+        Self {
+            name: "Western".to_string(),
+            cube: vec![
+                Colour::new("White", 'W', "37"),
+                Colour::new("Blue", 'B', "34"),
+                Colour::new("Orange", 'O', "38;5;208"),
+                Colour::new("Green", 'G', "32"),
+                Colour::new("Red", 'R', "31"),
+                Colour::new("Yellow", 'Y', "33"),
+            ],
+            pyraminx: vec![
+                Colour::new("Blue", 'B', "34"),
+                Colour::new("Red", 'R', "31"),
+                Colour::new("Green", 'G', "32"),
+                Colour::new("Yellow", 'Y', "33"),
+            ],
+            megaminx: vec![
+                Colour::new("White", 'W', "37"),
+                Colour::new("Blue", 'B', "34"),
+                Colour::new("Yellow", 'Y', "33"),
+                Colour::new("Purple", 'P', "38;5;91"),
+                Colour::new("Green", 'G', "32"),
+                Colour::new("Red", 'R', "31"),
+                Colour::new("Lime", 'L', "38;5;10"),
+                Colour::new("Orange", 'O', "38;5;208"),
+                Colour::new("Sky", 'S', "38;5;81"),
+                Colour::new("Beige", 'E', "38;5;230"),
+                Colour::new("Pink", 'K', "38;5;200"),
+                Colour::new("Gray", 'A', "38;5;15"),
+            ]
+        }
+    }
+
+    /// Blue opposite white and yellow opposite green, with the cube held the
+    /// same way as the western scheme (white on top, orange on the right).
+    pub fn japanese() -> Self {
+        let western = Self::western();
+        let colour = |i: usize| western.cube[i].clone();
+
+        Self {
+            name: "Japanese".to_string(),
+            cube: vec![colour(0), colour(5), colour(2), colour(3), colour(4), colour(1)],
+            ..western
+        }
+    }
+
+    pub fn built_in(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "western" => Some(Self::western()),
+            "japanese" => Some(Self::japanese()),
+            _ => None
+        }
+    }
+
+    /// Loads a scheme from a `.toml` or `.json` file. Lists that are left out
+    /// fall back to the western colours.
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+
+        let scheme: Self = match path.extension().and_then(|x| x.to_str()) {
+            Some("json") => serde_json::from_str(&contents).map_err(|e| e.to_string())?,
+            Some("toml") => toml::from_str(&contents).map_err(|e| e.to_string())?,
+            _ => return Err(format!("{} is not a .toml or .json file", path.display()))
+        };
+
+        scheme.validate()?;
+        Ok(scheme)
+    }
+
+    /// Checks every puzzle has the right number of colours, and that no two
+    /// of them share a letter, as colours are entered by letter.
+    pub fn validate(&self) -> Result<(), String> {
+        for (puzzle, colours, expected) in [("cube", &self.cube, 6), ("pyraminx", &self.pyraminx, 4), ("megaminx", &self.megaminx, 12)] {
+            if colours.len() != expected {
+                return Err(format!("{puzzle} needs {expected} colours, found {}", colours.len()));
+            }
+            for (i, colour) in colours.iter().enumerate() {
+                if let Some(other) = colours[..i].iter().find(|x| x.letter.eq_ignore_ascii_case(&colour.letter)) {
+                    return Err(format!("{} and {} in {puzzle} both use the letter '{}'", other.name, colour.name, colour.letter));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the sticker value for a colour letter, in either case.
+    pub fn value_of(colours: &[Colour], letter: char) -> Option<u8> {
+        colours.iter().position(|colour| colour.letter.eq_ignore_ascii_case(&letter)).map(|i| i as u8)
+    }
+}
+
+pub fn current() -> ColourScheme {
+    CURRENT_SCHEME.lock().unwrap().clone()
+}
+
+pub fn set_current(scheme: ColourScheme) {
+    *CURRENT_SCHEME.lock().unwrap() = scheme;
+}
+
+/// Paints a sticker value, leaving out-of-range values blank.
+pub fn paint(colours: &[Colour], value: u8) -> ColoredString {
+    match colours.get(value as usize) {
+        Some(colour) => colour.paint(),
+        None => ColoredString::from(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PuzzleType;
+
+    #[test]
+    fn built_in_schemes_are_valid() {
+        assert!(ColourScheme::western().validate().is_ok());
+        assert!(ColourScheme::japanese().validate().is_ok());
+    }
+
+    #[test]
+    fn shared_letters_are_rejected() {
+        let mut scheme = ColourScheme::western();
+        scheme.megaminx[9].letter = 'b';
+        assert!(scheme.validate().is_err());
+    }
+
+    #[test]
+    fn every_puzzle_takes_colours() {
+        let scheme = ColourScheme::western();
+        for name in ["2x2", "3x3", "Skewb", "Pyraminx", "Megaminx", "Ivy"] {
+            let mut puzzle = PuzzleType::from_name(name).unwrap();
+            puzzle.input_moves("R U");
+            let palette = match puzzle {
+                PuzzleType::Pyraminx(_) => &scheme.pyraminx,
+                PuzzleType::Megaminx(_) => &scheme.megaminx,
+                _ => &scheme.cube
+            };
+            let colours: String = puzzle.stickers().concat().iter().map(|&x| palette[x as usize].letter).collect();
+
+            let mut entered = puzzle.solved();
+            entered.input_colours(&colours).unwrap();
+            assert_eq!(entered.stickers(), puzzle.stickers(), "{name}");
+        }
+    }
+}
@@ -36,17 +36,17 @@ fn process_moves(initial_state: u128) -> Vec<u128> {
             value >>= 3;
         }
     }
-    let mut cube = RubiksCube2x2::default();
-    cube.state = state;
+    let mut cube = RubiksCube2x2 { state };
+    debug_assert!(cube.validate().is_ok(), "Decoded an unreachable state");
 
-    for i in 0..6 {
-        for j in 0..3 {
-            cube.input_moves(POSSIBLE_MOVES[i][j][0]);
+    for face in POSSIBLE_MOVES {
+        for [turn, undo] in face {
+            cube.input_moves(turn);
             let current_state = cube.return_state();
             if !state_known(current_state) {
                 local_depth.push(current_state);
             }
-            cube.input_moves(POSSIBLE_MOVES[i][j][1]);
+            cube.input_moves(undo);
         }
     }
     local_depth
@@ -55,8 +55,7 @@ fn process_moves(initial_state: u128) -> Vec<u128> {
 fn bfs(mut depth: u8, mut current_depth: Vec<u128>, mut states_processed: u32) {
     let mut new_depth = Vec::new();
 
-    while !current_depth.is_empty() {
-        let state = current_depth.pop().unwrap();  // Safe to unwrap as it's ensured to not be empty
+    while let Some(state) = current_depth.pop() {
         let states = process_moves(state);
 
         for state in states {
@@ -1,89 +1,86 @@
-use std::collections::HashMap;
+use std::path::Path;
 use lazy_static::lazy_static;
+use crate::db_generators::pattern_database::PatternDatabase;
 use crate::models::ivy::Ivy;
-use crate::models::Puzzle;
+use crate::models::PuzzleType;
+use crate::solvers::cubie::{rank_even, unrank_even};
+use crate::solvers::optimal::Indexed;
+use crate::solvers::pieces::{turn_pieces, Orbit};
 
-const POSSIBLE_MOVES: [[[&str; 2]; 3]; 4] = [
-    [["U", "U'"], ["U'", "U"], ["U2", "U2"]],
-    [["R", "R'"], ["R'", "R"], ["R2", "R2"]],
-    [["L", "L'"], ["L'", "L"], ["L2", "L2"]],
-    [["F", "F'"], ["F'", "F"], ["F2", "F2"]],
-];
+pub const PATH: &str = "ivy.pdb";
 
-lazy_static! {
-    static ref KNOWN_STATES: std::sync::Mutex<HashMap<u128, u8>> = std::sync::Mutex::new(HashMap::new());
-}
+/// The turns searched, each with the one that undoes it. `2` is the same as
+/// `'` on the Ivy.
+pub const MOVES: [&str; 8] = ["U", "U'", "R", "R'", "L", "L'", "F", "F'"];
 
-fn add_known_state(key: u128, value: u8) {
-    let mut states = KNOWN_STATES.lock().unwrap();
-    states.insert(key, value);
+/// Even orders of the centres (360) and the twists of the corners (3^4).
+/// The corners never leave their slots.
+pub const STATES: usize = 360 * 81;
+
+/// The Ivy as its pieces, numbered from 0 to `STATES` - 1.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct IvyState {
+    pub corners: Orbit<4>,
+    pub centres: Orbit<6>
 }
 
-fn state_known(key: u128) -> bool {
-    let states = KNOWN_STATES.lock().unwrap();
-    states.contains_key(&key)
+lazy_static! {
+    static ref TURNS: Vec<IvyState> = turn_pieces(&PuzzleType::Ivy(Ivy::default()), &MOVES).iter()
+        .map(|pieces| IvyState { corners: Orbit::of(&pieces[0]), centres: Orbit::of(&pieces[1]) })
+        .collect();
 }
 
-fn process_moves(initial_state: u128) -> Vec<u128> {
-    let mut state = [[0u8; 3]; 6];
-    let mut local_depth = Vec::new();
-    let mut value = initial_state;
-    for face in state.iter_mut().rev() {
-        for piece in face.iter_mut().rev() {
-            *piece = (value & 0b111) as u8;
-            value >>= 3;
-        }
+impl IvyState {
+    pub fn of(ivy: &Ivy) -> Result<Self, String> {
+        let pieces = PuzzleType::Ivy(ivy.clone()).pieces()?;
+        Ok(Self { corners: Orbit::of(&pieces[0]), centres: Orbit::of(&pieces[1]) })
     }
-    let mut cube = Ivy::default();
-    cube.state = state;
 
-    for i in 0..4 {
-        for j in 0..3 {
-            cube.input_moves(POSSIBLE_MOVES[i][j][0]);
-            let current_state = cube.return_state();
-            if !state_known(current_state) {
-                local_depth.push(current_state);
-            }
-            cube.input_moves(POSSIBLE_MOVES[i][j][1]);
+    pub fn from_index(mut index: usize) -> Self {
+        let mut state = Self { corners: Orbit::solved(), centres: Orbit::solved() };
+        for twist in state.corners.orientation.iter_mut().rev() {
+            *twist = (index % 3) as u8;
+            index /= 3;
         }
+        state.centres.permutation.copy_from_slice(&unrank_even(index, 6));
+        state
     }
-    local_depth
 }
 
-fn bfs(mut depth: u8, mut current_depth: Vec<u128>, mut states_processed: u32) {
-    let mut new_depth = Vec::new();
-
-    while !current_depth.is_empty() {
-        let state = current_depth.pop().unwrap();  // Safe to unwrap as it's ensured to not be empty
-        let states = process_moves(state);
-
-        for state in states {
-            if !state_known(state) {
-                new_depth.push(state);
-                add_known_state(state, depth);
-                states_processed += 1;
-            }
-        }
+impl Indexed for IvyState {
+    fn index(&self) -> usize {
+        let index = rank_even(&self.centres.permutation);
+        self.corners.orientation.iter().fold(index, |result, &x| result * 3 + x as usize)
+    }
 
-        if current_depth.is_empty() {
-            println!("Depth: {}, States processed: {}", depth, states_processed);
-            depth += 1;  // Move to the next depth
-            current_depth = new_depth.clone();  // Copy new states to the next level
-            new_depth.clear();  // Clear new_depth for the next round of processing
+    fn apply(&self, turn: usize) -> Self {
+        Self {
+            corners: self.corners.multiply(&TURNS[turn].corners, 3),
+            centres: self.centres.multiply(&TURNS[turn].centres, 1)
         }
     }
 }
 
-pub fn generate_db() {
-    let mut current_depth = Vec::new();
-    let depth = 1;
-    let states_processed = 1;
+fn neighbours(index: usize, next: &mut Vec<usize>) {
+    let state = IvyState::from_index(index);
+    next.extend((0..MOVES.len()).map(|turn| state.apply(turn).index()));
+}
+
+fn solved() -> usize {
+    IvyState::of(&Ivy::default()).unwrap().index()
+}
 
-    let cube = Ivy::default();
-    let state = cube.return_state();
-    add_known_state(state, 0);  // Initial state has depth 0
-    println!("Depth: 0, States processed: {}", states_processed);
-    current_depth.push(state);
+/// The number of moves from solved of every Ivy state, read from `PATH` or
+/// generated and saved there.
+pub fn load() -> Result<PatternDatabase, String> {
+    PatternDatabase::load_or_generate(Path::new(PATH), STATES, &[solved()], neighbours)
+}
 
-    bfs(depth, current_depth, states_processed);
+pub fn generate_db() {
+    let database = PatternDatabase::generate(STATES, &[solved()], neighbours, |depth, states| {
+        println!("Depth: {depth}, States processed: {states}");
+    });
+    if let Err(e) = database.save(Path::new(PATH)) {
+        println!("{e}");
+    }
 }
@@ -2,3 +2,4 @@ pub mod corner_database_generator;
 pub mod ivy_database_generator;
 pub mod skewb_database_generator;
 pub mod pyaminx_database_generator;
+pub mod pattern_database;
@@ -0,0 +1,138 @@
+use std::fs;
+use std::path::Path;
+
+/// Moves from solved for every state of part of a puzzle, for states that
+/// can be numbered 0..size (e.g. a corner permutation and twist). Unlike the
+/// `HashMap` generators this stores four bits a state, so tables with tens of
+/// millions of states fit in memory and can be saved to disk.
+pub struct PatternDatabase {
+    size: usize,
+    entries: Vec<u8>
+}
+
+/// What `get` gives for a state the search never reached, which includes
+/// states further than the table can hold.
+pub const UNKNOWN: u8 = 0xF;
+const MAGIC: &[u8; 4] = b"PDB1";
+
+impl PatternDatabase {
+    fn new(size: usize) -> Self {
+        Self { size, entries: vec![0xFF; size.div_ceil(2)] }
+    }
+
+    pub fn get(&self, index: usize) -> u8 {
+        (self.entries[index / 2] >> (4 * (index % 2))) & 0xF
+    }
+
+    fn set(&mut self, index: usize, value: u8) {
+        let shift = 4 * (index % 2);
+        self.entries[index / 2] = (self.entries[index / 2] & !(0xF << shift)) | (value << shift);
+    }
+
+    /// Breadth first search from the `solved` states. `neighbours` adds the
+    /// index of every state one move away from the given one. Each depth is
+    /// found by sweeping over the whole table rather than keeping a queue,
+    /// which would take far more memory than the table itself. `report` is
+    /// told the number of states found so far after each depth.
+    pub fn generate(size: usize, solved: &[usize], neighbours: impl Fn(usize, &mut Vec<usize>), mut report: impl FnMut(u8, usize)) -> Self {
+        let mut database = Self::new(size);
+        for &index in solved {
+            database.set(index, 0);
+        }
+
+        let mut states_processed = solved.len();
+        report(0, states_processed);
+
+        let mut next = Vec::new();
+        for depth in 0..UNKNOWN - 1 {
+            let mut found = 0;
+            for index in 0..size {
+                if database.get(index) != depth {
+                    continue;
+                }
+                next.clear();
+                neighbours(index, &mut next);
+                for &neighbour in next.iter() {
+                    if database.get(neighbour) == UNKNOWN {
+                        database.set(neighbour, depth + 1);
+                        found += 1;
+                    }
+                }
+            }
+
+            if found == 0 {
+                break;
+            }
+            states_processed += found;
+            report(depth + 1, states_processed);
+        }
+        database
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let mut contents = MAGIC.to_vec();
+        contents.extend((self.size as u64).to_le_bytes());
+        contents.extend(&self.entries);
+        fs::write(path, contents).map_err(|e| format!("Could not write {}: {e}", path.display()))
+    }
+
+    pub fn load(path: &Path, size: usize) -> Result<Self, String> {
+        let contents = fs::read(path).map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+        let header = MAGIC.len() + 8;
+
+        if contents.len() < header || &contents[..4] != MAGIC {
+            return Err(format!("{} is not a pattern database", path.display()));
+        }
+        if u64::from_le_bytes(contents[4..header].try_into().unwrap()) != size as u64 || contents.len() != header + size.div_ceil(2) {
+            return Err(format!("{} is for a different table", path.display()));
+        }
+        Ok(Self { size, entries: contents[header..].to_vec() })
+    }
+
+    /// Reads the table from `path`, or generates it and saves it there if the
+    /// file is missing or out of date.
+    pub fn load_or_generate(path: &Path, size: usize, solved: &[usize], neighbours: impl Fn(usize, &mut Vec<usize>)) -> Result<Self, String> {
+        if let Ok(database) = Self::load(path, size) {
+            return Ok(database);
+        }
+
+        println!("Generating {}", path.display());
+        let database = Self::generate(size, solved, neighbours, |depth, states| {
+            println!("Depth: {depth}, States processed: {states}");
+        });
+        database.save(path)?;
+        Ok(database)
+    }
+}
+
+/// Breadth first search from the `solved` states for tables that need more
+/// moves than a `PatternDatabase` can hold, so keep a byte a state. States
+/// are anything `index` numbers 0..size, and `neighbours` adds every state
+/// one move away from the given one. States it never reaches are `u8::MAX`.
+pub fn byte_table<S: Copy>(size: usize, solved: &[S], index: impl Fn(&S) -> usize, neighbours: impl Fn(&S, &mut Vec<S>)) -> Vec<u8> {
+    let mut distances = vec![u8::MAX; size];
+    for state in solved {
+        distances[index(state)] = 0;
+    }
+
+    let mut layer = solved.to_vec();
+    let mut next = Vec::new();
+    let mut depth = 0;
+    while !layer.is_empty() {
+        depth += 1;
+        let mut found = Vec::new();
+        for state in layer.iter() {
+            next.clear();
+            neighbours(state, &mut next);
+            for neighbour in next.iter() {
+                let index = index(neighbour);
+                if distances[index] == u8::MAX {
+                    distances[index] = depth;
+                    found.push(*neighbour);
+                }
+            }
+        }
+        layer = found;
+    }
+    distances
+}
@@ -1,93 +1,121 @@
-use std::collections::HashMap;
+use std::path::Path;
 use lazy_static::lazy_static;
+use crate::db_generators::pattern_database::PatternDatabase;
 use crate::models::pyraminx::Pyraminx;
-use crate::models::Puzzle;
-
-const POSSIBLE_MOVES: [[[&str; 2]; 3]; 8] = [
-    [["D", "D'"], ["D'", "D"], ["D2", "D2"]],
-    [["R", "R'"], ["R'", "R"], ["R2", "R2"]],
-    [["L", "L'"], ["L'", "L"], ["L2", "L2"]],
-    [["F", "F'"], ["F'", "F"], ["F2", "F2"]],
-    [["T", "T'"], ["T'", "T"], ["T2", "T2"]],
-    [["E", "E'"], ["E'", "E"], ["E2", "E2"]],
-    [["K", "K'"], ["K'", "K"], ["K2", "K2"]],
-    [["B", "B'"], ["B'", "B"], ["B2", "B2"]],
-];
+use crate::models::{permutation_parity, Puzzle, PuzzleType};
+use crate::solvers::cubie::{rank_even, unrank_even, unrank_permutation};
+use crate::solvers::optimal::Indexed;
+use crate::solvers::pieces::{turn_pieces, Orbit};
 
-lazy_static! {
-    static ref KNOWN_STATES: std::sync::Mutex<HashMap<u128, u8>> = std::sync::Mutex::new(HashMap::new());
-}
+pub const PATH: &str = "pyraminx.pdb";
+
+/// The face turns searched, each followed by the one that undoes it. The
+/// tips are left out: each one can be solved on its own in a single move,
+/// and searching them as well would make the table 81 times bigger.
+pub const MOVES: [&str; 8] = ["F", "F'", "R", "R'", "L", "L'", "D", "D'"];
 
-fn add_known_state(key: u128, value: u8) {
-    let mut states = KNOWN_STATES.lock().unwrap();
-    states.insert(key, value);
+/// Even orders of the axial centres (12) and their twists (27), even orders
+/// of the edges (360) and their flips (32). The last axial centre's twist
+/// and the last edge's flip follow from the others.
+pub const STATES: usize = 12 * 27 * 360 * 32;
+
+/// The Pyraminx without its tips.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct PyraminxCore {
+    pub axials: Orbit<4>,
+    pub edges: Orbit<6>
 }
 
-fn state_known(key: u128) -> bool {
-    let states = KNOWN_STATES.lock().unwrap();
-    states.contains_key(&key)
+lazy_static! {
+    static ref TURNS: Vec<PyraminxCore> = turn_pieces(&PuzzleType::Pyraminx(Pyraminx::default()), &MOVES).iter()
+        .map(|pieces| PyraminxCore { axials: Orbit::of(&pieces[1]), edges: Orbit::of(&pieces[2]) })
+        .collect();
 }
 
-fn process_moves(initial_state: u128) -> Vec<u128> {
-    let mut state = [[0u8; 9]; 4];
-    let mut local_depth = Vec::new();
-    let mut value = initial_state;
-    for face in state.iter_mut().rev() {
-        for piece in face.iter_mut().rev() {
-            *piece = (value & 0b111) as u8;
-            value >>= 3;
-        }
+impl PyraminxCore {
+    pub fn of(pyraminx: &Pyraminx) -> Result<Self, String> {
+        let pieces = PuzzleType::Pyraminx(pyraminx.clone()).pieces()?;
+        Ok(Self { axials: Orbit::of(&pieces[1]), edges: Orbit::of(&pieces[2]) })
     }
-    let mut cube = Pyraminx::default();
-    cube.state = state;
-
-    for i in 0..8 {
-        for j in 0..3 {
-            cube.input_moves(POSSIBLE_MOVES[i][j][0]);
-            let current_state = cube.return_state();
-            if !state_known(current_state) {
-                local_depth.push(current_state);
+
+    /// The cores that count as solved. Face turns also turn the puzzle as a
+    /// whole, so the core can end up solved but held another way: any of the
+    /// turns of the whole puzzle that face turns can reach.
+    pub fn solved() -> Vec<Self> {
+        let mut solved = Vec::new();
+        for colours in (0..24).map(|x| unrank_permutation(x, 4)) {
+            let mut pyraminx = Pyraminx::default();
+            for (face, &colour) in pyraminx.state.iter_mut().zip(&colours) {
+                *face = [colour; 9];
+            }
+            if permutation_parity(&colours) == 0 && pyraminx.validate().is_ok() {
+                solved.push(Self::of(&pyraminx).unwrap());
             }
-            cube.input_moves(POSSIBLE_MOVES[i][j][1]);
         }
+        solved
     }
-    local_depth
-}
 
-fn bfs(mut depth: u8, mut current_depth: Vec<u128>, mut states_processed: u32) {
-    let mut new_depth = Vec::new();
+    pub fn from_index(mut index: usize) -> Self {
+        let mut core = Self { axials: Orbit::solved(), edges: Orbit::solved() };
 
-    while !current_depth.is_empty() {
-        let state = current_depth.pop().unwrap();  // Safe to unwrap as it's ensured to not be empty
-        let states = process_moves(state);
+        let mut flips = 0;
+        for flip in core.edges.orientation[..5].iter_mut().rev() {
+            *flip = (index % 2) as u8;
+            flips += *flip;
+            index /= 2;
+        }
+        core.edges.orientation[5] = flips % 2;
+        core.edges.permutation.copy_from_slice(&unrank_even(index % 360, 6));
+        index /= 360;
 
-        for state in states {
-            if !state_known(state) {
-                new_depth.push(state);
-                add_known_state(state, depth);
-                states_processed += 1;
-            }
+        let mut twists = 0;
+        for twist in core.axials.orientation[..3].iter_mut().rev() {
+            *twist = (index % 3) as u8;
+            twists += *twist;
+            index /= 3;
         }
+        core.axials.orientation[3] = (3 - twists % 3) % 3;
+        core.axials.permutation.copy_from_slice(&unrank_even(index, 4));
+        core
+    }
+}
+
+impl Indexed for PyraminxCore {
+    fn index(&self) -> usize {
+        let mut index = rank_even(&self.axials.permutation);
+        index = self.axials.orientation[..3].iter().fold(index, |result, &x| result * 3 + x as usize);
+        index = index * 360 + rank_even(&self.edges.permutation);
+        self.edges.orientation[..5].iter().fold(index, |result, &x| result * 2 + x as usize)
+    }
 
-        if current_depth.is_empty() {
-            println!("Depth: {}, States processed: {}", depth, states_processed);
-            depth += 1;  // Move to the next depth
-            current_depth = new_depth.clone();  // Copy new states to the next level
-            new_depth.clear();  // Clear new_depth for the next round of processing
+    fn apply(&self, turn: usize) -> Self {
+        Self {
+            axials: self.axials.multiply(&TURNS[turn].axials, 3),
+            edges: self.edges.multiply(&TURNS[turn].edges, 2)
         }
     }
 }
 
-pub fn generate_db() {
-    let mut current_depth = Vec::new();
-    let depth = 1;
-    let states_processed = 1;
+fn neighbours(index: usize, next: &mut Vec<usize>) {
+    let core = PyraminxCore::from_index(index);
+    next.extend((0..MOVES.len()).map(|turn| core.apply(turn).index()));
+}
 
-    let cube = Pyraminx::default();
-    let state = cube.return_state();
-    add_known_state(state, 0);  // Initial state has depth 0
-    println!("Depth: 0, States processed: {}", states_processed);
-    current_depth.push(state);
+fn solved() -> Vec<usize> {
+    PyraminxCore::solved().iter().map(|x| x.index()).collect()
+}
+
+/// The number of face turns from solved of every Pyraminx core, read from
+/// `PATH` or generated and saved there.
+pub fn load() -> Result<PatternDatabase, String> {
+    PatternDatabase::load_or_generate(Path::new(PATH), STATES, &solved(), neighbours)
+}
 
-    bfs(depth, current_depth, states_processed);
+pub fn generate_db() {
+    let database = PatternDatabase::generate(STATES, &solved(), neighbours, |depth, states| {
+        println!("Depth: {depth}, States processed: {states}");
+    });
+    if let Err(e) = database.save(Path::new(PATH)) {
+        println!("{e}");
+    }
 }
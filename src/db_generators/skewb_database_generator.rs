@@ -1,89 +1,120 @@
-use std::collections::HashMap;
+use std::path::Path;
 use lazy_static::lazy_static;
-use crate::models::skewb::Skewb;
-use crate::models::Puzzle;
+use crate::db_generators::pattern_database::PatternDatabase;
+use crate::models::skewb::{tetrad_turn, Skewb, TETRADS};
+use crate::models::PuzzleType;
+use crate::solvers::cubie::{rank_even, unrank_even};
+use crate::solvers::optimal::Indexed;
+use crate::solvers::pieces::{turn_pieces, Orbit};
 
-const POSSIBLE_MOVES: [[[&str; 2]; 3]; 4] = [
-    [["U", "U'"], ["U'", "U"], ["U2", "U2"]],
-    [["R", "R'"], ["R'", "R"], ["R2", "R2"]],
-    [["L", "L'"], ["L'", "L"], ["L2", "L2"]],
-    [["F", "F'"], ["F'", "F"], ["F2", "F2"]],
-];
+pub const PATH: &str = "skewb.pdb";
 
-lazy_static! {
-    static ref KNOWN_STATES: std::sync::Mutex<HashMap<u128, u8>> = std::sync::Mutex::new(HashMap::new());
-}
+/// The turns searched, each with the one that undoes it. `2` is the same as
+/// `'` on the Skewb.
+pub const MOVES: [&str; 8] = ["U", "U'", "R", "R'", "L", "L'", "F", "F'"];
+
+/// Even orders of the five centres that move (60), even orders of each
+/// tetrad (12 * 12), and the twists of three corners of each tetrad (3^6).
+/// The last corner of each tetrad is twisted however the rest require.
+pub const STATES: usize = 60 * 12 * 12 * 729;
 
-fn add_known_state(key: u128, value: u8) {
-    let mut states = KNOWN_STATES.lock().unwrap();
-    states.insert(key, value);
+/// The Skewb as its pieces, numbered so that every reachable state has an
+/// index below `STATES`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SkewbState {
+    pub corners: Orbit<8>,
+    pub centres: Orbit<6>
 }
 
-fn state_known(key: u128) -> bool {
-    let states = KNOWN_STATES.lock().unwrap();
-    states.contains_key(&key)
+lazy_static! {
+    static ref TURNS: Vec<SkewbState> = turn_pieces(&PuzzleType::Skewb(Skewb::default()), &MOVES).iter()
+        .map(|pieces| SkewbState { corners: Orbit::of(&pieces[0]), centres: Orbit::of(&pieces[1]) })
+        .collect();
 }
 
-fn process_moves(initial_state: u128) -> Vec<u128> {
-    let mut state = [[0u8; 5]; 6];
-    let mut local_depth = Vec::new();
-    let mut value = initial_state;
-    for face in state.iter_mut().rev() {
-        for piece in face.iter_mut().rev() {
-            *piece = (value & 0b111) as u8;
-            value >>= 3;
-        }
+impl SkewbState {
+    pub fn of(skewb: &Skewb) -> Result<Self, String> {
+        let pieces = PuzzleType::Skewb(skewb.clone()).pieces()?;
+        Ok(Self { corners: Orbit::of(&pieces[0]), centres: Orbit::of(&pieces[1]) })
+    }
+
+    /// Where the pieces of each tetrad are, as places within the tetrad.
+    fn tetrad_order(&self, tetrad: usize) -> [u8; 4] {
+        let slots = TETRADS[tetrad];
+        slots.map(|slot| slots.iter().position(|&x| x == self.corners.permutation[slot] as usize).unwrap() as u8)
     }
-    let mut cube = Skewb::default();
-    cube.state = state;
-
-    for i in 0..4 {
-        for j in 0..3 {
-            cube.input_moves(POSSIBLE_MOVES[i][j][0]);
-            let current_state = cube.return_state();
-            if !state_known(current_state) {
-                local_depth.push(current_state);
+
+    pub fn from_index(mut index: usize) -> Self {
+        let mut state = Self { corners: Orbit::solved(), centres: Orbit::solved() };
+
+        for slots in TETRADS.iter().rev() {
+            for &slot in slots[..3].iter().rev() {
+                state.corners.orientation[slot] = (index % 3) as u8;
+                index /= 3;
             }
-            cube.input_moves(POSSIBLE_MOVES[i][j][1]);
         }
+        for slots in TETRADS.iter().rev() {
+            for (&slot, piece) in slots.iter().zip(unrank_even(index % 12, 4)) {
+                state.corners.permutation[slot] = slots[piece as usize] as u8;
+            }
+            index /= 12;
+        }
+        state.centres.permutation[..5].copy_from_slice(&unrank_even(index, 5));
+
+        // The twist of each tetrad is fixed by how far the other one is
+        // turned (see `Skewb::validate`).
+        let turns = [0, 1].map(|tetrad| tetrad_turn(state.tetrad_order(tetrad)) as u8);
+        let totals = [turns[1], (3 - turns[0]) % 3];
+        for (slots, total) in TETRADS.iter().zip(totals) {
+            let twist: u8 = slots[..3].iter().map(|&slot| state.corners.orientation[slot]).sum();
+            state.corners.orientation[slots[3]] = (total + 6 - twist % 3) % 3;
+        }
+        state
     }
-    local_depth
 }
 
-fn bfs(mut depth: u8, mut current_depth: Vec<u128>, mut states_processed: u32) {
-    let mut new_depth = Vec::new();
-
-    while !current_depth.is_empty() {
-        let state = current_depth.pop().unwrap();  // Safe to unwrap as it's ensured to not be empty
-        let states = process_moves(state);
-
-        for state in states {
-            if !state_known(state) {
-                new_depth.push(state);
-                add_known_state(state, depth);
-                states_processed += 1;
+impl Indexed for SkewbState {
+    fn index(&self) -> usize {
+        let mut index = rank_even(&self.centres.permutation[..5]);
+        for tetrad in 0..2 {
+            index = index * 12 + rank_even(&self.tetrad_order(tetrad));
+        }
+        for slots in TETRADS {
+            for slot in &slots[..3] {
+                index = index * 3 + self.corners.orientation[*slot] as usize;
             }
         }
+        index
+    }
 
-        if current_depth.is_empty() {
-            println!("Depth: {}, States processed: {}", depth, states_processed);
-            depth += 1;  // Move to the next depth
-            current_depth = new_depth.clone();  // Copy new states to the next level
-            new_depth.clear();  // Clear new_depth for the next round of processing
+    fn apply(&self, turn: usize) -> Self {
+        Self {
+            corners: self.corners.multiply(&TURNS[turn].corners, 3),
+            centres: self.centres.multiply(&TURNS[turn].centres, 1)
         }
     }
 }
 
-pub fn generate_db() {
-    let mut current_depth = Vec::new();
-    let depth = 1;
-    let states_processed = 1;
+fn neighbours(index: usize, next: &mut Vec<usize>) {
+    let state = SkewbState::from_index(index);
+    next.extend((0..MOVES.len()).map(|turn| state.apply(turn).index()));
+}
+
+fn solved() -> usize {
+    SkewbState::of(&Skewb::default()).unwrap().index()
+}
 
-    let cube = Skewb::default();
-    let state = cube.return_state();
-    add_known_state(state, 0);  // Initial state has depth 0
-    println!("Depth: 0, States processed: {}", states_processed);
-    current_depth.push(state);
+/// The number of moves from solved of every Skewb state, read from `PATH` or
+/// generated (in a few seconds) and saved there.
+pub fn load() -> Result<PatternDatabase, String> {
+    PatternDatabase::load_or_generate(Path::new(PATH), STATES, &[solved()], neighbours)
+}
 
-    bfs(depth, current_depth, states_processed);
+pub fn generate_db() {
+    let database = PatternDatabase::generate(STATES, &[solved()], neighbours, |depth, states| {
+        println!("Depth: {depth}, States processed: {states}");
+    });
+    if let Err(e) = database.save(Path::new(PATH)) {
+        println!("{e}");
+    }
 }
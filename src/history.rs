@@ -0,0 +1,146 @@
+use crate::algorithms::Notation;
+use crate::models::{parser, PuzzleType};
+
+/// Every move applied to the puzzle since it was last solved, split into the
+/// scramble and the moves entered afterwards, plus a stack of undone moves.
+#[derive(Default)]
+pub struct History {
+    pub scramble: Vec<String>,
+    pub moves: Vec<String>,
+    undone: Vec<String>
+}
+
+impl History {
+    pub fn new(scramble: &str) -> Self {
+        Self {
+            scramble: scramble.split_whitespace().map(|x| x.to_uppercase()).collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Expands and applies a sequence of moves and records them. Nothing is
+    /// applied if any of them is not a move on this puzzle.
+    pub fn apply(&mut self, puzzle: &mut PuzzleType, moves: &str) -> Result<(), String> {
+        let moves = parser::expand(moves, Notation::of(puzzle))?;
+        let turns: Vec<String> = moves.split_whitespace().map(|x| x.to_uppercase()).collect();
+        if let Some(turn) = turns.iter().find(|x| puzzle.inverse_move(x).is_none()) {
+            return Err(format!("'{turn}' is not a {} move", puzzle.name()));
+        }
+
+        for turn in turns {
+            puzzle.input_moves(&turn);
+            self.moves.push(turn);
+        }
+        if !moves.trim().is_empty() {
+            self.undone.clear();
+        }
+        Ok(())
+    }
+
+    /// Records moves that have already been applied to the puzzle elsewhere,
+    /// e.g. in the TUI.
+    pub fn record(&mut self, turns: Vec<String>) {
+        if !turns.is_empty() {
+            self.undone.clear();
+        }
+        self.moves.extend(turns);
+    }
+
+    /// Takes back the last move entered. The scramble cannot be undone, and
+    /// a move the puzzle does not know is left in the log.
+    pub fn undo(&mut self, puzzle: &mut PuzzleType) -> Result<String, String> {
+        let turn = self.moves.last().ok_or("Nothing to undo".to_string())?;
+        let inverse = puzzle.inverse_move(turn).ok_or(format!("'{turn}' is not a {} move, so it cannot be undone", puzzle.name()))?;
+        puzzle.input_moves(&inverse);
+        let turn = self.moves.pop().unwrap();
+        self.undone.push(turn.clone());
+        Ok(turn)
+    }
+
+    pub fn redo(&mut self, puzzle: &mut PuzzleType) -> Option<String> {
+        let turn = self.undone.pop()?;
+        puzzle.input_moves(&turn);
+        self.moves.push(turn.clone());
+        Some(turn)
+    }
+
+    /// Undoes the whole log, scramble included, and clears it.
+    pub fn reset(&mut self, puzzle: &mut PuzzleType) {
+        let inverse = self.inverse(puzzle);
+        puzzle.input_moves(&inverse);
+        *self = Self::default();
+    }
+
+    /// The scramble followed by the moves entered since.
+    pub fn log(&self) -> Vec<String> {
+        self.scramble.iter().chain(self.moves.iter()).cloned().collect()
+    }
+
+    /// The moves that take the puzzle back to the start of the log.
+    pub fn inverse(&self, puzzle: &PuzzleType) -> String {
+        self.log().iter().rev().filter_map(|x| puzzle.inverse_move(x)).collect::<Vec<_>>().join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scrambled(moves: &str) -> PuzzleType {
+        let mut puzzle = PuzzleType::from_name("3x3").unwrap();
+        puzzle.input_moves(moves);
+        puzzle
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut puzzle = scrambled("");
+        let mut history = History::default();
+        history.apply(&mut puzzle, "R U").unwrap();
+
+        assert_eq!(history.undo(&mut puzzle).unwrap(), "U");
+        assert_eq!(puzzle.return_state(), scrambled("R").return_state());
+        assert_eq!(history.redo(&mut puzzle).unwrap(), "U");
+        assert_eq!(puzzle.return_state(), scrambled("R U").return_state());
+        assert!(history.redo(&mut puzzle).is_none());
+
+        history.undo(&mut puzzle).unwrap();
+        history.apply(&mut puzzle, "F").unwrap();
+        assert!(history.redo(&mut puzzle).is_none());
+        assert_eq!(history.moves, vec!["R", "F"]);
+    }
+
+    #[test]
+    fn the_scramble_cannot_be_undone() {
+        let mut puzzle = scrambled("R U");
+        let mut history = History::new("R U");
+        history.apply(&mut puzzle, "F").unwrap();
+        history.undo(&mut puzzle).unwrap();
+        assert_eq!(history.undo(&mut puzzle).unwrap_err(), "Nothing to undo");
+        assert_eq!(puzzle.return_state(), scrambled("R U").return_state());
+    }
+
+    #[test]
+    fn unknown_moves_are_kept() {
+        let mut puzzle = scrambled("");
+        let mut history = History::default();
+        assert!(history.apply(&mut puzzle, "R Q").is_err());
+        assert!(history.moves.is_empty() && puzzle.is_solved());
+
+        history.record(vec!["R".to_string(), "Q".to_string()]);
+        assert!(history.undo(&mut puzzle).unwrap_err().contains("'Q'"));
+        assert_eq!(history.moves, vec!["R", "Q"]);
+    }
+
+    #[test]
+    fn reset_and_inverse() {
+        let mut puzzle = scrambled("R U");
+        let mut history = History::new("r u");
+        history.apply(&mut puzzle, "[F: D2]").unwrap();
+        assert_eq!(history.inverse(&puzzle), "F D2 F' U' R'");
+
+        history.reset(&mut puzzle);
+        assert!(puzzle.is_solved());
+        assert!(history.log().is_empty());
+    }
+}
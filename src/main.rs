@@ -1,12 +1,19 @@
 use std::io::{self, stdin, stdout, Write};
+use std::path::Path;
 use termion::event::Key;
 use termion::input::TermRead;
 use termion::raw::IntoRawMode;
 use termion::{clear, cursor};
 
+mod algorithms;
+mod colour_scheme;
+mod history;
 mod models;
 mod db_generators;
 mod scramble_generator;
+mod solvers;
+mod timer;
+mod tui;
 
 fn select_option() -> &'static str {
     let menu: &'static [&str] = &["2x2", "3x3", "Skewb", "Pyraminx", "Megaminx", "Ivy", "gcdb", "gsdb", "gidb", "gpdb"];
@@ -67,16 +74,28 @@ fn select_option() -> &'static str {
     menu[selected]
 }
 
+fn load_colour_scheme(name: &str) -> Result<colour_scheme::ColourScheme, String> {
+    match colour_scheme::ColourScheme::built_in(name) {
+        Some(scheme) => Ok(scheme),
+        None => colour_scheme::ColourScheme::from_file(Path::new(name))
+    }
+}
+
 fn main() {
+    for path in ["colour_scheme.toml", "colour_scheme.json"] {
+        if Path::new(path).exists() {
+            match load_colour_scheme(path) {
+                Ok(scheme) => colour_scheme::set_current(scheme),
+                Err(e) => println!("{e}")
+            }
+        }
+    }
+
     let option = select_option();
 
-    let mut cube = match option {
-        "2x2" => models::PuzzleType::RubiksCube2x2(models::rubiks_cube_2x2::RubiksCube2x2::default()),
-        "3x3" => models::PuzzleType::RubiksCube(models::rubiks_cube::RubiksCube::default()),
-        "Skewb" => models::PuzzleType::Skewb(models::skewb::Skewb::default()),
-        "Pyraminx" => models::PuzzleType::Pyraminx(models::pyraminx::Pyraminx::default()),
-        "Ivy" => models::PuzzleType::Ivy(models::ivy::Ivy::default()),
-        _ => models::PuzzleType::Megaminx(models::megaminx::Megaminx::default())
+    let mut cube = match models::PuzzleType::from_name(option) {
+        Some(puzzle) => puzzle,
+        None => models::PuzzleType::Megaminx(models::megaminx::Megaminx::default())
     };
 
     match option {
@@ -92,6 +111,17 @@ fn main() {
     cube.input_moves(scramble.as_str());
     cube.print();
 
+    let mut history = history::History::new(&scramble);
+    let mut session = timer::DEFAULT_SESSION.to_string();
+    let mut database = match timer::database::Database::open(Path::new(timer::database::DEFAULT_PATH)) {
+        Ok(database) => database,
+        Err(e) => {
+            println!("{e}");
+            println!("Solves will not be saved this time");
+            timer::database::Database::unsaved()
+        }
+    };
+
     loop {
         let mut moves = String::new();
         
@@ -104,10 +134,341 @@ fn main() {
             println!("{}", cube.is_solved())
         } else if moves == "rs" {
             println!("{:?}", cube.return_state());
+        } else if moves == "v" {
+            match cube.validate() {
+                Ok(()) => println!("Valid"),
+                Err(e) => println!("{e}")
+            }
+        } else if let Some(name) = moves.strip_prefix("scheme ") {
+            match load_colour_scheme(name.trim()) {
+                Ok(scheme) => colour_scheme::set_current(scheme),
+                Err(e) => println!("{e}")
+            }
+            cube.print();
+        } else if moves == "facelets" {
+            match &cube {
+                models::PuzzleType::RubiksCube(rubiks_cube) => println!("{}", rubiks_cube.to_facelets()),
+                _ => println!("Facelet strings are only available for the 3x3")
+            }
+        } else if let Some(facelets) = moves.strip_prefix("facelets ") {
+            match models::rubiks_cube::RubiksCube::from_facelets(facelets) {
+                Ok(rubiks_cube) => {
+                    cube = models::PuzzleType::RubiksCube(rubiks_cube);
+                    history = history::History::default();
+                    cube.print();
+                }
+                Err(e) => println!("{e}")
+            }
+        } else if moves == "ll" {
+            match &cube {
+                models::PuzzleType::RubiksCube(rubiks_cube) => match algorithms::last_layer::recognise(rubiks_cube) {
+                    Ok(case) => println!("{case}"),
+                    Err(e) => println!("{e}")
+                },
+                _ => println!("Last layer recognition is only available for the 3x3")
+            }
+        } else if moves == "tui" {
+            match tui::key_map(&cube) {
+                Ok(key_map) => {
+                    let turns = tui::run(&mut cube, &key_map);
+                    println!("{}", turns.join(" "));
+                    history.record(turns);
+                    cube.print();
+                }
+                Err(e) => println!("{e}")
+            }
+        } else if moves == "timer" || moves == "timer noinspection" {
+            let timed = timer::run(&cube, &session, moves == "timer");
+            for solve in timed.iter() {
+                println!("{solve}   {}", solve.scramble);
+            }
+            if let Err(e) = database.add(timed) {
+                println!("{e}");
+            }
+        } else if moves == "stats" {
+            let session_solves = database.solves(&timer::database::Query { session: Some(session.clone()), ..Default::default() });
+            println!("{} in session {session}", cube.name());
+            for row in timer::stats::Statistics::for_puzzle(&session_solves, &cube).render() {
+                println!("{row}");
+            }
+            for row in timer::stats::render_histogram(&timer::stats::of_puzzle(&session_solves, &cube), 10) {
+                println!("{row}");
+            }
+        } else if moves == "stats all" {
+            for (puzzle, statistics) in timer::stats::Statistics::by_puzzle(&database.solves(&Default::default())) {
+                println!("{puzzle}");
+                for row in statistics.render() {
+                    println!("  {row}");
+                }
+            }
+        } else if moves == "session" {
+            println!("Current session: {session}");
+            for (name, puzzle, count) in database.sessions() {
+                println!("  {name} ({puzzle}): {count} solves");
+            }
+        } else if let Some(name) = moves.strip_prefix("session ") {
+            session = name.trim().to_string();
+        } else if let Some(name) = moves.strip_prefix("rename ") {
+            match database.rename_session(&session, name.trim()) {
+                Ok(count) => {
+                    println!("Moved {count} solves to {}", name.trim());
+                    session = name.trim().to_string();
+                }
+                Err(e) => println!("{e}")
+            }
+        } else if moves == "solves" || moves.starts_with("solves ") {
+            let last = moves.strip_prefix("solves ").and_then(|x| x.trim().parse().ok()).unwrap_or(12);
+            let query = timer::database::Query {
+                puzzle: Some(cube.name().to_string()),
+                session: Some(session.clone()),
+                last: Some(last),
+                ..Default::default()
+            };
+            for record in database.query(&query) {
+                println!("{:>6}  {:<12} {}   {}", record.id, record.solve.to_string(), record.solve.scramble, record.solve.comment);
+            }
+        } else if let Some(arguments) = moves.strip_prefix("penalty ") {
+            let penalty = match arguments.split_whitespace().nth(1) {
+                Some("ok") => Some(timer::Penalty::None),
+                Some("+2") => Some(timer::Penalty::PlusTwo),
+                Some("dnf") => Some(timer::Penalty::Dnf),
+                _ => None
+            };
+            match (arguments.split_whitespace().next().and_then(|x| x.parse().ok()), penalty) {
+                (Some(id), Some(penalty)) => if let Err(e) = database.set_penalty(id, penalty) {
+                    println!("{e}");
+                },
+                _ => println!("Usage: penalty <id> <ok|+2|dnf>")
+            }
+        } else if let Some(arguments) = moves.strip_prefix("comment ") {
+            let (id, comment) = arguments.split_once(' ').unwrap_or((arguments, ""));
+            match id.parse() {
+                Ok(id) => if let Err(e) = database.set_comment(id, comment.trim()) {
+                    println!("{e}");
+                },
+                Err(_) => println!("Usage: comment <id> <text>")
+            }
+        } else if let Some(id) = moves.strip_prefix("delete ") {
+            match id.trim().parse().map_err(|_| "Usage: delete <id>".to_string()).and_then(|id| database.remove(id)) {
+                Ok(solve) => println!("Deleted {solve}"),
+                Err(e) => println!("{e}")
+            }
+        } else if let Some(path) = moves.strip_prefix("import ") {
+            match timer::cstimer::import(Path::new(path.trim())) {
+                Ok(import) => {
+                    println!("Imported {} solves", import.solves.len());
+                    if !import.skipped.is_empty() {
+                        println!("Skipped: {}", import.skipped.join(", "));
+                    }
+                    if let Err(e) = database.add(import.solves) {
+                        println!("{e}");
+                    }
+                }
+                Err(e) => println!("{e}")
+            }
+        } else if let Some(path) = moves.strip_prefix("export ") {
+            let solves = database.solves(&Default::default());
+            match timer::cstimer::export(Path::new(path.trim()), &solves) {
+                Ok(()) => println!("Exported {} solves", solves.len()),
+                Err(e) => println!("{e}")
+            }
+        } else if let Some(alg) = moves.strip_prefix("simplify ") {
+            match algorithms::simplify::simplify_alg(alg, algorithms::Notation::of(&cube)) {
+                Ok((simplified, metrics)) => println!("{simplified}\n{metrics}"),
+                Err(e) => println!("{e}")
+            }
+        } else if let Some(alg) = moves.strip_prefix("inverse ") {
+            match algorithms::transforms::inverse_alg(alg, algorithms::Notation::of(&cube)) {
+                Ok(inverse) => println!("{inverse}"),
+                Err(e) => println!("{e}")
+            }
+        } else if let Some(alg) = moves.strip_prefix("mirror ") {
+            let (direction, alg) = match alg.strip_prefix("fb ") {
+                Some(alg) => (algorithms::transforms::Mirror::FrontBack, alg),
+                None => (algorithms::transforms::Mirror::LeftRight, alg)
+            };
+            match algorithms::transforms::mirror_alg(alg, algorithms::Notation::of(&cube), direction) {
+                Ok(mirrored) => println!("{mirrored}"),
+                Err(e) => println!("{e}")
+            }
+        } else if let Some(arguments) = moves.strip_prefix("rotate ") {
+            let (rotations, alg) = arguments.split_once(':').unwrap_or((arguments, ""));
+            match algorithms::transforms::rotate_alg(alg, algorithms::Notation::of(&cube), rotations) {
+                Ok(rotated) => println!("{rotated}"),
+                Err(e) => println!("{e}")
+            }
+        } else if let Some(alg) = moves.strip_prefix("cycles ") {
+            match algorithms::cycles::analyse(&cube, alg) {
+                Ok(analysis) => println!("{analysis}"),
+                Err(e) => println!("{e}")
+            }
+        } else if let Some(arguments) = moves.strip_prefix("compare ") {
+            let (first, second) = arguments.split_once('|').unwrap_or((arguments, ""));
+            match algorithms::equivalence::compare(&cube, first, second) {
+                Ok(equivalence) => println!("{equivalence}"),
+                Err(e) => println!("{e}")
+            }
+        } else if let Some(colours) = moves.strip_prefix("colours ") {
+            match cube.input_colours(colours) {
+                Ok(()) => {
+                    history = history::History::default();
+                    cube.print();
+                }
+                Err(e) => println!("{e}")
+            }
+        } else if moves == "undo" {
+            match history.undo(&mut cube) {
+                Ok(turn) => println!("Undid {turn}"),
+                Err(e) => println!("{e}")
+            }
+            cube.print();
+        } else if moves == "redo" {
+            match history.redo(&mut cube) {
+                Some(turn) => println!("Redid {turn}"),
+                None => println!("Nothing to redo")
+            }
+            cube.print();
+        } else if moves == "reset" {
+            history.reset(&mut cube);
+            cube.print();
+        } else if moves == "history" {
+            println!("Scramble: {}", history.scramble.join(" "));
+            println!("Moves ({}): {}", history.moves.len(), history.moves.join(" "));
+            if let Ok((log, metrics)) = algorithms::simplify::simplify_alg(&history.log().join(" "), algorithms::Notation::of(&cube)) {
+                println!("Simplified: {log} ({metrics})");
+            }
+        } else if moves == "solve" {
+            match &cube {
+                models::PuzzleType::Megaminx(megaminx) => match megaminx.solve() {
+                    Ok(solution) => println!("{solution}"),
+                    Err(e) => println!("{e}")
+                },
+                _ => match cube.solve() {
+                    Ok(solution) => println!("{} ({})", algorithms::format(&solution), algorithms::simplify::metrics(&solution)),
+                    Err(e) => println!("{e}")
+                }
+            }
+        } else if moves == "solve all" {
+            match cube.solve_all() {
+                Ok(solutions) => {
+                    for solution in &solutions {
+                        println!("{}", algorithms::format(solution));
+                    }
+                    let plural = if solutions.len() == 1 { "" } else { "s" };
+                    println!("{} optimal solution{plural} of {} moves", solutions.len(), solutions[0].len());
+                }
+                Err(e) => println!("{e}")
+            }
+        } else if moves == "optimal" || moves == "optimal resume" {
+            let checkpoint = Path::new(solvers::corner_solver::PROGRESS_PATH);
+            match &cube {
+                models::PuzzleType::RubiksCube(rubiks_cube) => {
+                    let report = |depth, nodes| println!("Depth {depth}: {nodes} nodes");
+                    match solvers::corner_solver::solve(rubiks_cube, Some(checkpoint), moves == "optimal resume", report) {
+                        Ok(solution) => {
+                            let nodes: u64 = solution.nodes.iter().sum();
+                            println!("{} ({} HTM, {nodes} nodes)", algorithms::format(&solution.moves), solution.moves.len());
+                        }
+                        Err(e) => println!("{e}")
+                    }
+                }
+                _ => println!("The optimal solver is only available for the 3x3")
+            }
+        } else if moves == "l4e" {
+            match &cube {
+                models::PuzzleType::Pyraminx(pyraminx) => match pyraminx.solve_l4e() {
+                    Ok(solutions) => {
+                        for solution in &solutions {
+                            println!("{}", algorithms::format(solution));
+                        }
+                    }
+                    Err(e) => println!("{e}")
+                },
+                _ => println!("L4E solutions are only available for the Pyraminx")
+            }
+        } else if moves == "distance" {
+            match &cube {
+                models::PuzzleType::Ivy(ivy) => match ivy.distance() {
+                    Ok(distance) => println!("{distance} moves from solved"),
+                    Err(e) => println!("{e}")
+                },
+                _ => println!("The distance from solved is only available for the Ivy")
+            }
+        } else if moves == "thistlethwaite" {
+            match &cube {
+                models::PuzzleType::RubiksCube(rubiks_cube) => match solvers::thistlethwaite::solve(rubiks_cube) {
+                    Ok(solution) => println!("{solution}"),
+                    Err(e) => println!("{e}")
+                },
+                _ => println!("The Thistlethwaite solver is only available for the 3x3")
+            }
+        } else if moves == "lbl" {
+            match &cube {
+                models::PuzzleType::RubiksCube(rubiks_cube) => match solvers::beginner::solve(rubiks_cube) {
+                    Ok(solution) => solution.print(&cube),
+                    Err(e) => println!("{e}")
+                },
+                _ => println!("The layer by layer solver is only available for the 3x3")
+            }
+        } else if ["cross", "xcross", "pair"].contains(&moves.split_whitespace().next().unwrap_or("")) {
+            let (step, faces) = moves.split_once(' ').unwrap_or((&moves, ""));
+            match &cube {
+                models::PuzzleType::RubiksCube(rubiks_cube) => {
+                    let steps = solvers::steps::move_set(faces).and_then(|turns| match step {
+                        "cross" => solvers::cfop::crosses(rubiks_cube, &turns),
+                        "xcross" => solvers::cfop::xcrosses(rubiks_cube, &turns),
+                        _ => solvers::cfop::pairs(rubiks_cube, &turns)
+                    });
+                    match steps {
+                        Ok(steps) if steps.is_empty() => println!("Nothing within {} moves", solvers::steps::MAX_DEPTH),
+                        Ok(steps) => {
+                            for step in &steps {
+                                println!("{step}");
+                            }
+                        }
+                        Err(e) => println!("{e}")
+                    }
+                }
+                _ => println!("CFOP steps are only available for the 3x3")
+            }
+        } else if moves == "roux" {
+            match &cube {
+                models::PuzzleType::RubiksCube(rubiks_cube) => match solvers::roux::solve(rubiks_cube) {
+                    Ok(solution) => println!("{solution}"),
+                    Err(e) => println!("{e}")
+                },
+                _ => println!("The Roux solver is only available for the 3x3")
+            }
+        } else if ["eoline", "eocross"].contains(&moves.split_whitespace().next().unwrap_or("")) {
+            let (step, layers) = moves.split_once(' ').unwrap_or((&moves, ""));
+            match &cube {
+                models::PuzzleType::RubiksCube(rubiks_cube) => {
+                    let solutions = solvers::steps::move_set(layers).and_then(|turns| match step {
+                        "eoline" => solvers::zz::eoline(rubiks_cube, &turns),
+                        _ => solvers::zz::eocross(rubiks_cube, &turns)
+                    });
+                    match solutions {
+                        Ok(solutions) if solutions.is_empty() => println!("Nothing within {} moves", solvers::steps::MAX_DEPTH),
+                        Ok(solutions) => {
+                            for solution in &solutions {
+                                println!("{}", algorithms::format(solution));
+                            }
+                            let plural = if solutions.len() == 1 { "" } else { "s" };
+                            println!("{} optimal solution{plural} of {} STM", solutions.len(), algorithms::simplify::metrics(&solutions[0]).stm);
+                        }
+                        Err(e) => println!("{e}")
+                    }
+                }
+                _ => println!("ZZ steps are only available for the 3x3")
+            }
+        } else if moves == "invert" {
+            println!("{}", history.inverse(&cube));
         // } else if moves == "p" {
         //     cube.print();
         } else {
-            cube.input_moves(&moves);
+            if let Err(e) = history.apply(&mut cube, &moves) {
+                println!("{e}");
+            }
             cube.print();
             println!();
         }
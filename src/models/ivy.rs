@@ -1,8 +1,12 @@
 use std::collections::HashMap;
 use colored::{Colorize, ColoredString};
 
-use crate::models::{Puzzle, Faces};
+use crate::colour_scheme;
+use crate::algorithms::{Move, Notation};
+use crate::solvers::ivy as solver;
+use crate::models::{parser, Puzzle, Faces, find_pieces, permutation_parity, slot_name};
 
+#[derive(Clone)]
 pub struct Ivy {
     pub state: [[u8;3];6]
 }
@@ -15,6 +19,16 @@ impl Default for Ivy {
     }
 }
 
+/// Sticker positions of the four corners (URB, UFL, DFR, DBL), which only
+/// ever twist in place.
+pub const CORNERS: [[(usize, usize); 3]; 4] = [
+    [(0, 2), (3, 2), (2, 2)], [(0, 0), (1, 2), (4, 2)], [(5, 2), (1, 0), (2, 0)], [(5, 0), (3, 0), (4, 0)]
+];
+
+pub const CENTRES: [[(usize, usize); 1]; 6] = [[(0, 1)], [(1, 1)], [(2, 1)], [(3, 1)], [(4, 1)], [(5, 1)]];
+
+pub const FACE_LETTERS: [char; 6] = ['U', 'F', 'R', 'B', 'L', 'D'];
+
 impl Ivy {
     fn rotate(&mut self, face: Faces, magnitude: u32) {
         let rotation = (magnitude % 8) as usize;
@@ -47,7 +61,7 @@ impl Ivy {
     }
 
     pub fn input_moves(&mut self, moves: &str) {
-        let moves = moves.to_uppercase();
+        let moves = parser::flatten(moves, Notation::Corner);
         let moves_list: Vec<&str> = moves.split_whitespace().collect();
 
         for x in moves_list {
@@ -73,6 +87,21 @@ impl Ivy {
             }
         }
     }
+
+    /// A solution in as few moves as possible.
+    pub fn solve(&self) -> Result<Vec<Move>, String> {
+        solver::solve(self)
+    }
+
+    /// Every solution in as few moves as possible.
+    pub fn solve_all(&self) -> Result<Vec<Vec<Move>>, String> {
+        solver::solve_all(self)
+    }
+
+    /// How many moves an optimal solution takes.
+    pub fn distance(&self) -> Result<u8, String> {
+        solver::distance(self)
+    }
 }
 
 impl Puzzle for Ivy {
@@ -91,7 +120,21 @@ impl Puzzle for Ivy {
         self.state == [[0u8;3], [1u8;3], [2u8;3], [3u8;3], [4u8;3], [5u8;3]]
     }
 
-    fn print(&self) {
+    fn validate(&self) -> Result<(), String> {
+        let (corners, _) = find_pieces(&self.state, &CORNERS, "corner", &FACE_LETTERS)?;
+        let (centres, _) = find_pieces(&self.state, &CENTRES, "centre", &FACE_LETTERS)?;
+
+        if let Some(slot) = (0..corners.len()).find(|&slot| corners[slot] != slot as u8) {
+            return Err(format!("The corner at {} can only twist in place", slot_name(&CORNERS[slot], &FACE_LETTERS)));
+        }
+        if permutation_parity(&centres) != 0 {
+            return Err("Parity: the centres are in an odd permutation".to_string());
+        }
+        Ok(())
+    }
+
+    fn render(&self) -> Vec<String> {
+        let scheme = colour_scheme::current();
         let mut print_state: Vec<Vec<ColoredString>> = vec![vec![" ".white(); 12]; 9];
 
         let print_map: HashMap<_, _> = vec![((0, 0), (2, 3)), ((0, 1), (1, 4)), ((0, 2), (0, 5)), ((1, 0), (5, 5)), ((1, 1), (4, 4)), ((1, 2), (3, 3)), ((2, 0), (5, 6)), ((2, 1), (4, 7)), ((2, 2), (3, 8)), ((3, 0), (5, 11)), ((3, 1), (4, 10)), ((3, 2), (3, 9)), ((4, 0), (5, 0)), ((4, 1), (4, 1)), ((4, 2), (3, 2)), ((5, 0), (8, 3)), ((5, 1), (7, 4)), ((5, 2), (6, 5))].into_iter().collect();
@@ -99,16 +142,30 @@ impl Puzzle for Ivy {
         for i in 0..6 {
             for j in 0..3 {
                 let (x, y) = print_map[&(i, j)];
-                print_state[x][y] = match self.state[i][j] {0 => "W".white(), 1 => "B".blue(), 2 => ColoredString::from(format!("\x1b[38;5;208m{}\x1b[0m", "O")), 3 => "G".green(), 4 => "R".red(), 5 => "Y".yellow(), _ => " ".white()}
+                print_state[x][y] = colour_scheme::paint(&scheme.cube, self.state[i][j])
             }
         }
 
                 
-        for row in print_state {
-            for cell in row {
-                print!("{} ", cell);
-            }
-            println!();
-        }
+        print_state.iter().map(|row| row.iter().map(|cell| format!("{} ", cell)).collect()).collect()
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::tests::{assert_invalid, copy, swap, twist};
+
+    #[test]
+    fn unreachable_states_are_rejected() {
+        let broken = |change: fn(&mut [[u8; 3]; 6])| {
+            let mut ivy = Ivy::default();
+            change(&mut ivy.state);
+            ivy
+        };
+        assert_invalid(&broken(|x| swap(x, &CORNERS[0], &CORNERS[1])), "The corner at");
+        assert_invalid(&broken(|x| swap(x, &CENTRES[0], &CENTRES[1])), "Parity: the centres");
+        assert_invalid(&broken(|x| copy(x, &CENTRES[0], &CENTRES[1])), "The centre F is missing");
+        // Corners twist freely.
+        assert!(broken(|x| twist(x, &CORNERS[0])).validate().is_ok());
+    }
+}
@@ -1,7 +1,11 @@
 use std::collections::HashMap;
 use colored::{Colorize, ColoredString};
 
-use crate::models::Puzzle;
+use crate::colour_scheme;
+use crate::algorithms::Notation;
+use crate::models::{parser, Puzzle, find_pieces, orientation_sum, permutation_parity};
+use crate::solvers::megaminx as solver;
+use crate::solvers::stages::Solution;
 
 pub enum Faces {
     White,
@@ -37,6 +41,28 @@ impl Faces {
     }
 }
 
+/// Sticker positions of each corner slot, going clockwise.
+pub const CORNERS: [[(usize, usize); 3]; 20] = [
+    [(0, 0), (5, 6), (1, 2)], [(0, 2), (4, 8), (5, 4)], [(0, 4), (3, 0), (4, 6)], [(0, 6), (2, 2), (3, 8)],
+    [(0, 8), (1, 4), (2, 0)], [(1, 0), (5, 8), (10, 0)], [(1, 6), (6, 2), (2, 8)], [(1, 8), (10, 2), (6, 0)],
+    [(2, 4), (7, 4), (3, 6)], [(2, 6), (6, 4), (7, 2)], [(3, 2), (8, 6), (4, 4)], [(3, 4), (7, 6), (8, 4)],
+    [(4, 0), (9, 8), (5, 2)], [(4, 2), (8, 8), (9, 6)], [(5, 0), (9, 0), (10, 8)], [(6, 6), (11, 4), (7, 0)],
+    [(6, 8), (10, 4), (11, 2)], [(7, 8), (11, 6), (8, 2)], [(8, 0), (11, 8), (9, 4)], [(9, 2), (11, 0), (10, 6)]
+];
+
+pub const EDGES: [[(usize, usize); 2]; 30] = [
+    [(0, 1), (5, 5)], [(0, 3), (4, 7)], [(0, 5), (3, 9)], [(0, 7), (2, 1)], [(0, 9), (1, 3)],
+    [(1, 1), (5, 7)], [(1, 5), (2, 9)], [(1, 7), (6, 1)], [(1, 9), (10, 1)], [(2, 3), (3, 7)],
+    [(2, 5), (7, 3)], [(2, 7), (6, 3)], [(3, 1), (4, 5)], [(3, 3), (8, 5)], [(3, 5), (7, 5)],
+    [(4, 1), (9, 7)], [(4, 3), (8, 7)], [(4, 9), (5, 3)], [(5, 1), (9, 9)], [(5, 9), (10, 9)],
+    [(6, 5), (7, 1)], [(6, 7), (11, 3)], [(6, 9), (10, 3)], [(7, 7), (8, 3)], [(7, 9), (11, 5)],
+    [(8, 1), (11, 7)], [(8, 9), (9, 5)], [(9, 1), (10, 7)], [(9, 3), (11, 9)], [(10, 5), (11, 1)]
+];
+
+/// The letter used to turn each face in `input_moves`.
+pub const FACE_LETTERS: [char; 12] = ['U', 'F', 'R', 'B', 'V', 'L', 'G', 'O', 'S', 'J', 'P', 'D'];
+
+#[derive(Clone)]
 pub struct Megaminx {
     pub state: [[u8; 10]; 12]
 }
@@ -90,7 +116,7 @@ impl Megaminx {
     }
 
     pub fn input_moves(&mut self, moves: &str) {
-        let moves = moves.to_uppercase();
+        let moves = parser::flatten(moves, Notation::Megaminx);
         let moves_list: Vec<&str> = moves.split_whitespace().collect();
 
         for x in moves_list {
@@ -126,6 +152,11 @@ impl Megaminx {
             }
         }
     }
+
+    /// A solution in stages, from the star on D to the last layer on U.
+    pub fn solve(&self) -> Result<Solution, String> {
+        solver::solve(self)
+    }
 }
 
 impl Puzzle for Megaminx {
@@ -144,20 +175,32 @@ impl Puzzle for Megaminx {
         self.state == [[0u8; 10], [1u8; 10], [2u8; 10], [3u8; 10], [4u8; 10], [5u8; 10], [6u8; 10], [7u8; 10], [8u8; 10], [9u8; 10], [10u8; 10], [11u8; 10]]
     }
 
-    fn print(&self) {
+    fn validate(&self) -> Result<(), String> {
+        let (corner_permutation, corner_orientation) = find_pieces(&self.state, &CORNERS, "corner", &FACE_LETTERS)?;
+        let (edge_permutation, edge_orientation) = find_pieces(&self.state, &EDGES, "edge", &FACE_LETTERS)?;
+
+        if !orientation_sum(&corner_orientation).is_multiple_of(3) {
+            return Err("Corner twist: the corner orientations do not sum to a multiple of 3".to_string());
+        }
+        if !orientation_sum(&edge_orientation).is_multiple_of(2) {
+            return Err("Edge flip: an odd number of edges are flipped".to_string());
+        }
+        if permutation_parity(&corner_permutation) != 0 {
+            return Err("Parity: the corners are in an odd permutation".to_string());
+        }
+        if permutation_parity(&edge_permutation) != 0 {
+            return Err("Parity: the edges are in an odd permutation".to_string());
+        }
+        Ok(())
+    }
+
+    fn render(&self) -> Vec<String> {
+        let scheme = colour_scheme::current();
         let mut print_state: Vec<Vec<ColoredString>> = vec![vec![" ".white(); 56]; 13];
-        print_state[7][12] = "W".white();
-        print_state[10][17] = "B".blue();
-        print_state[5][20] = "Y".yellow();
-        print_state[2][12] = ColoredString::from(format!("\x1b[38;5;91m{}\x1b[0m", "P"));
-        print_state[5][4] = "G".green();
-        print_state[10][7] = "R".red();
-        print_state[5][34] = ColoredString::from(format!("\x1b[38;5;10m{}\x1b[0m", "L"));
-        print_state[2][42] = ColoredString::from(format!("\x1b[38;5;208m{}\x1b[0m", "O"));
-        print_state[5][50] = ColoredString::from(format!("\x1b[38;5;81m{}\x1b[0m", "S"));
-        print_state[10][37] = ColoredString::from(format!("\x1b[38;5;200m{}\x1b[0m", "P"));
-        print_state[10][47] = ColoredString::from(format!("\x1b[38;5;230m{}\x1b[0m", "B"));
-        print_state[7][42] = ColoredString::from(format!("\x1b[38;5;15m{}\x1b[0m", "G"));
+        let centres = [(7, 12), (10, 17), (5, 20), (2, 12), (5, 4), (10, 7), (5, 34), (2, 42), (5, 50), (10, 47), (10, 37), (7, 42)];
+        for (face, (x, y)) in centres.into_iter().enumerate() {
+            print_state[x][y] = colour_scheme::paint(&scheme.megaminx, face as u8);
+        }
 
         let print_map: HashMap<_, _> = vec![
             ((0, 0), (9, 12)), ((0, 1), (8, 10)), ((0, 2), (7, 8)), ((0, 3), (6, 9)), ((0, 4), (5, 10)), ((0, 5), (5, 12)), ((0, 6), (5, 14)), ((0, 7), (6, 15)), ((0, 8), (7, 16)), ((0, 9), (8, 14)), 
@@ -177,30 +220,30 @@ impl Puzzle for Megaminx {
         for i in 0..12 {
             for j in 0..10 {
                 let (x, y) = print_map[&(i, j)];
-                print_state[x][y] = match self.state[i][j] {
-                    0 => "W".white(),
-                    1 => "B".blue(),
-                    2 => "Y".yellow(),
-                    3 => ColoredString::from(format!("\x1b[38;5;91m{}\x1b[0m", "P")),
-                    4 => "G".green(),
-                    5 => "R".red(),
-                    6 => ColoredString::from(format!("\x1b[38;5;10m{}\x1b[0m", "L")),
-                    7 => ColoredString::from(format!("\x1b[38;5;208m{}\x1b[0m", "O")),
-                    8 => ColoredString::from(format!("\x1b[38;5;81m{}\x1b[0m", "S")),
-                    9 => ColoredString::from(format!("\x1b[38;5;230m{}\x1b[0m", "B")),
-                    10 => ColoredString::from(format!("\x1b[38;5;200m{}\x1b[0m", "P")),
-                    11 => ColoredString::from(format!("\x1b[38;5;15m{}\x1b[0m", "G")),
-                    _ => " ".white()
-                }
+                print_state[x][y] = colour_scheme::paint(&scheme.megaminx, self.state[i][j])
             }
         }
 
                 
-        for row in print_state {
-            for cell in row {
-                print!("{}", cell);
-            }
-            println!();
-        }
+        print_state.iter().map(|row| row.iter().map(|cell| format!("{}", cell)).collect()).collect()
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::tests::{assert_invalid, copy, swap, twist};
+
+    #[test]
+    fn unreachable_states_are_rejected() {
+        let broken = |change: fn(&mut [[u8; 10]; 12])| {
+            let mut megaminx = Megaminx::default();
+            change(&mut megaminx.state);
+            megaminx
+        };
+        assert_invalid(&broken(|x| twist(x, &CORNERS[0])), "Corner twist");
+        assert_invalid(&broken(|x| twist(x, &EDGES[0])), "Edge flip");
+        assert_invalid(&broken(|x| swap(x, &CORNERS[0], &CORNERS[1])), "Parity: the corners");
+        assert_invalid(&broken(|x| swap(x, &EDGES[0], &EDGES[1])), "Parity: the edges");
+        assert_invalid(&broken(|x| copy(x, &EDGES[0], &EDGES[1])), "The edge");
+    }
+}
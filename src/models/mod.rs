@@ -4,6 +4,10 @@ pub mod rubiks_cube;
 pub mod megaminx;
 pub mod skewb;
 pub mod ivy;
+pub mod parser;
+
+use crate::algorithms::Move;
+use crate::colour_scheme::{self, ColourScheme};
 
 pub enum Faces {
     White,
@@ -29,11 +33,107 @@ impl Faces {
 
 pub trait Puzzle {
     fn is_solved(&self) -> bool;
-    fn print(&self);
+    /// Draws the net as coloured rows of text.
+    fn render(&self) -> Vec<String>;
     fn return_state(&self) -> u128;
+    fn validate(&self) -> Result<(), String>;
+
+    fn print(&self) {
+        for row in self.render() {
+            println!("{row}");
+        }
+    }
 }
 
+/// Names a piece slot after the faces its stickers sit on, e.g. `URF`.
+pub fn slot_name(slot: &[(usize, usize)], face_letters: &[char]) -> String {
+    slot.iter().map(|&(face, _)| face_letters[face]).collect()
+}
+
+/// Works out which piece sits in each slot by comparing the stickers with
+/// the colours every slot has when solved (each sticker takes the colour of
+/// its face). Returns the piece in each slot and how many stickers it is
+/// turned by, checking that every piece appears exactly once.
+pub fn find_pieces<const F: usize, const S: usize, const N: usize>(
+    state: &[[u8; S]; F],
+    slots: &[[(usize, usize); N]],
+    kind: &str,
+    face_letters: &[char]
+) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let mut permutation = Vec::new();
+    let mut orientation = Vec::new();
+
+    for slot in slots {
+        let colours = slot.map(|(face, index)| state[face][index] as usize);
+
+        let found = (0..N).find_map(|turn| {
+            slots.iter()
+                .position(|piece| (0..N).all(|i| piece[i].0 == colours[(i + turn) % N]))
+                .map(|piece| (piece, turn))
+        });
+
+        match found {
+            Some((piece, turn)) => {
+                permutation.push(piece as u8);
+                orientation.push(turn as u8);
+            }
+            None => return Err(format!("The {kind} at {} has colours that do not form a {kind}", slot_name(slot, face_letters)))
+        }
+    }
+
+    for (piece, slot) in slots.iter().enumerate() {
+        if !permutation.contains(&(piece as u8)) {
+            return Err(format!("The {kind} {} is missing", slot_name(slot, face_letters)));
+        }
+    }
+
+    Ok((permutation, orientation))
+}
+
+/// One kind of piece (e.g. the edges) as found by `find_pieces`, with the
+/// names of the slots and how many ways each piece can be turned.
+pub struct PieceSet {
+    pub kind: &'static str,
+    pub names: Vec<String>,
+    pub permutation: Vec<u8>,
+    pub orientation: Vec<u8>,
+    pub orientations: u8
+}
+
+pub fn piece_set<const F: usize, const S: usize, const N: usize>(
+    state: &[[u8; S]; F],
+    slots: &[[(usize, usize); N]],
+    kind: &'static str,
+    face_letters: &[char]
+) -> Result<PieceSet, String> {
+    let (permutation, orientation) = find_pieces(state, slots, kind, face_letters)?;
+
+    Ok(PieceSet {
+        kind,
+        names: slots.iter().map(|slot| slot_name(slot, face_letters)).collect(),
+        permutation,
+        orientation,
+        orientations: N as u8
+    })
+}
+
+pub fn permutation_parity(permutation: &[u8]) -> usize {
+    let mut inversions = 0;
+    for i in 0..permutation.len() {
+        for j in i + 1..permutation.len() {
+            if permutation[i] > permutation[j] {
+                inversions += 1;
+            }
+        }
+    }
+    inversions % 2
+}
+
+pub fn orientation_sum(orientation: &[u8]) -> u32 {
+    orientation.iter().map(|&x| x as u32).sum()
+}
 
+#[derive(Clone)]
 pub enum PuzzleType {
     RubiksCube(rubiks_cube::RubiksCube),
     RubiksCube2x2(rubiks_cube_2x2::RubiksCube2x2),
@@ -44,6 +144,69 @@ pub enum PuzzleType {
 }
 
 impl PuzzleType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PuzzleType::RubiksCube(_) => "3x3",
+            PuzzleType::RubiksCube2x2(_) => "2x2",
+            PuzzleType::Skewb(_) => "Skewb",
+            PuzzleType::Pyraminx(_) => "Pyraminx",
+            PuzzleType::Megaminx(_) => "Megaminx",
+            PuzzleType::Ivy(_) => "Ivy"
+        }
+    }
+
+    /// A solved puzzle from its `name()`.
+    pub fn from_name(name: &str) -> Option<PuzzleType> {
+        match name {
+            "2x2" => Some(PuzzleType::RubiksCube2x2(rubiks_cube_2x2::RubiksCube2x2::default())),
+            "3x3" => Some(PuzzleType::RubiksCube(rubiks_cube::RubiksCube::default())),
+            "Skewb" => Some(PuzzleType::Skewb(skewb::Skewb::default())),
+            "Pyraminx" => Some(PuzzleType::Pyraminx(pyraminx::Pyraminx::default())),
+            "Megaminx" => Some(PuzzleType::Megaminx(megaminx::Megaminx::default())),
+            "Ivy" => Some(PuzzleType::Ivy(ivy::Ivy::default())),
+            _ => None
+        }
+    }
+
+    /// A solved puzzle of the same kind.
+    pub fn solved(&self) -> PuzzleType {
+        Self::from_name(self.name()).unwrap()
+    }
+
+    /// Letters and multiples accepted by `input_moves`, and how many turns of
+    /// a face bring it back to where it started.
+    pub fn move_set(&self) -> (&'static str, &'static str, u32) {
+        match self {
+            PuzzleType::RubiksCube(_) | PuzzleType::RubiksCube2x2(_) => ("UFRBLD", "2", 4),
+            PuzzleType::Skewb(_) | PuzzleType::Ivy(_) => ("UFRL", "2", 3),
+            PuzzleType::Pyraminx(_) => ("FRLDTEKB", "2", 3),
+            PuzzleType::Megaminx(_) => ("UFRBVLPGOSJD", "234", 5)
+        }
+    }
+
+    /// Returns the move that undoes `turn`, or `None` if `turn` is not a move
+    /// on this puzzle. Multiples are written as a number (`R2`, and `R3` on the
+    /// Megaminx) and the last multiple as a prime.
+    pub fn inverse_move(&self, turn: &str) -> Option<String> {
+        let (letters, multiples, order) = self.move_set();
+        let turn = turn.to_uppercase();
+        let mut chars = turn.chars();
+
+        let face = chars.next().filter(|&x| letters.contains(x))?;
+        let magnitude = match (chars.next(), chars.next()) {
+            (None, _) => 1,
+            (Some('\''), None) => order - 1,
+            (Some(x), None) if multiples.contains(x) => x.to_digit(10)?,
+            _ => return None
+        };
+
+        Some(match order - magnitude {
+            1 => face.to_string(),
+            x if x == order - 1 => format!("{face}'"),
+            x => format!("{face}{x}")
+        })
+    }
+
     pub fn is_solved(&self) -> bool {
         match self {
             PuzzleType::RubiksCube(cube) => cube.is_solved(),
@@ -66,6 +229,39 @@ impl PuzzleType {
         }
     }
 
+    pub fn render(&self) -> Vec<String> {
+        match self {
+            PuzzleType::RubiksCube(cube) => cube.render(),
+            PuzzleType::RubiksCube2x2(cube) => cube.render(),
+            PuzzleType::Skewb(cube) => cube.render(),
+            PuzzleType::Pyraminx(pyraminx) => pyraminx.render(),
+            PuzzleType::Megaminx(megaminx) => megaminx.render(),
+            PuzzleType::Ivy(cube) => cube.render()
+        }
+    }
+
+    /// The colour of every sticker, face by face in the order of `face_letters`.
+    pub fn stickers(&self) -> Vec<Vec<u8>> {
+        match self {
+            PuzzleType::RubiksCube(cube) => cube.state.iter().map(|x| x.to_vec()).collect(),
+            PuzzleType::RubiksCube2x2(cube) => cube.state.iter().map(|x| x.to_vec()).collect(),
+            PuzzleType::Skewb(cube) => cube.state.iter().map(|x| x.to_vec()).collect(),
+            PuzzleType::Pyraminx(pyraminx) => pyraminx.state.iter().map(|x| x.to_vec()).collect(),
+            PuzzleType::Megaminx(megaminx) => megaminx.state.iter().map(|x| x.to_vec()).collect(),
+            PuzzleType::Ivy(cube) => cube.state.iter().map(|x| x.to_vec()).collect()
+        }
+    }
+
+    pub fn face_letters(&self) -> &'static [char] {
+        match self {
+            PuzzleType::RubiksCube(_) => &rubiks_cube::FACE_LETTERS,
+            PuzzleType::RubiksCube2x2(_) | PuzzleType::Skewb(_) => &rubiks_cube_2x2::FACE_LETTERS,
+            PuzzleType::Pyraminx(_) => &pyraminx::FACE_LETTERS,
+            PuzzleType::Megaminx(_) => &megaminx::FACE_LETTERS,
+            PuzzleType::Ivy(_) => &ivy::FACE_LETTERS
+        }
+    }
+
     pub fn return_state(&self) -> u128 {
         match self {
             PuzzleType::RubiksCube(cube) => cube.return_state(),
@@ -77,6 +273,69 @@ impl PuzzleType {
         }
     }
 
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            PuzzleType::RubiksCube(cube) => cube.validate(),
+            PuzzleType::RubiksCube2x2(cube) => cube.validate(),
+            PuzzleType::Skewb(cube) => cube.validate(),
+            PuzzleType::Pyraminx(pyraminx) => pyraminx.validate(),
+            PuzzleType::Megaminx(megaminx) => megaminx.validate(),
+            PuzzleType::Ivy(cube) => cube.validate()
+        }
+    }
+
+    /// Moves that solve the puzzle from its current state.
+    pub fn solve(&self) -> Result<Vec<Move>, String> {
+        match self {
+            PuzzleType::RubiksCube(cube) => cube.solve(),
+            PuzzleType::Skewb(cube) => cube.solve(),
+            PuzzleType::Pyraminx(pyraminx) => pyraminx.solve(),
+            PuzzleType::Megaminx(megaminx) => megaminx.solve().map(|x| x.moves()),
+            PuzzleType::Ivy(cube) => cube.solve(),
+            _ => Err(format!("There is no solver for the {} yet", self.name()))
+        }
+    }
+
+    /// Every shortest solution, for the puzzles small enough to have a table
+    /// of every state.
+    pub fn solve_all(&self) -> Result<Vec<Vec<Move>>, String> {
+        match self {
+            PuzzleType::Skewb(cube) => cube.solve_all(),
+            PuzzleType::Ivy(cube) => cube.solve_all(),
+            _ => Err(format!("Listing every optimal solution is not available for the {}", self.name()))
+        }
+    }
+
+    /// Every kind of moving piece on the puzzle and where each one is.
+    pub fn pieces(&self) -> Result<Vec<PieceSet>, String> {
+        match self {
+            PuzzleType::RubiksCube(cube) => Ok(vec![
+                piece_set(&cube.state, &rubiks_cube::CORNERS, "corner", &rubiks_cube::FACE_LETTERS)?,
+                piece_set(&cube.state, &rubiks_cube::EDGES, "edge", &rubiks_cube::FACE_LETTERS)?
+            ]),
+            PuzzleType::RubiksCube2x2(cube) => Ok(vec![
+                piece_set(&cube.state, &rubiks_cube_2x2::CORNERS, "corner", &rubiks_cube_2x2::FACE_LETTERS)?
+            ]),
+            PuzzleType::Skewb(cube) => Ok(vec![
+                piece_set(&cube.state, &rubiks_cube_2x2::CORNERS, "corner", &rubiks_cube_2x2::FACE_LETTERS)?,
+                piece_set(&cube.state, &skewb::CENTRES, "centre", &rubiks_cube_2x2::FACE_LETTERS)?
+            ]),
+            PuzzleType::Pyraminx(pyraminx) => Ok(vec![
+                piece_set(&pyraminx.state, &pyraminx::TIPS, "tip", &pyraminx::FACE_LETTERS)?,
+                piece_set(&pyraminx.state, &pyraminx::AXIALS, "axial centre", &pyraminx::FACE_LETTERS)?,
+                piece_set(&pyraminx.state, &pyraminx::EDGES, "edge", &pyraminx::FACE_LETTERS)?
+            ]),
+            PuzzleType::Megaminx(megaminx) => Ok(vec![
+                piece_set(&megaminx.state, &megaminx::CORNERS, "corner", &megaminx::FACE_LETTERS)?,
+                piece_set(&megaminx.state, &megaminx::EDGES, "edge", &megaminx::FACE_LETTERS)?
+            ]),
+            PuzzleType::Ivy(cube) => Ok(vec![
+                piece_set(&cube.state, &ivy::CORNERS, "corner", &ivy::FACE_LETTERS)?,
+                piece_set(&cube.state, &ivy::CENTRES, "centre", &ivy::FACE_LETTERS)?
+            ])
+        }
+    }
+
     pub fn input_moves(&mut self, moves: &str) {
         match self {
             PuzzleType::RubiksCube(cube) => cube.input_moves(moves),
@@ -87,4 +346,133 @@ impl PuzzleType {
             PuzzleType::Ivy(cube) => cube.input_moves(moves)
         }
     }
-}
\ No newline at end of file
+
+    /// Sets every sticker from a string of colour letters in the current
+    /// scheme, listed face by face in the same order as `state`. The puzzle is
+    /// left unchanged if the colours do not describe a reachable state.
+    pub fn input_colours(&mut self, colours: &str) -> Result<(), String> {
+        let scheme = colour_scheme::current();
+        let palette = match self {
+            PuzzleType::Pyraminx(_) => &scheme.pyraminx,
+            PuzzleType::Megaminx(_) => &scheme.megaminx,
+            _ => &scheme.cube
+        };
+
+        let mut values = Vec::new();
+        for letter in colours.chars().filter(|c| !c.is_whitespace()) {
+            match ColourScheme::value_of(palette, letter) {
+                Some(value) => values.push(value),
+                None => return Err(format!("'{letter}' is not a colour in the {} scheme", scheme.name))
+            }
+        }
+
+        let mut puzzle = self.clone();
+        match &mut puzzle {
+            PuzzleType::RubiksCube(cube) => fill_state(&mut cube.state, &values)?,
+            PuzzleType::RubiksCube2x2(cube) => fill_state(&mut cube.state, &values)?,
+            PuzzleType::Skewb(cube) => fill_state(&mut cube.state, &values)?,
+            PuzzleType::Pyraminx(pyraminx) => fill_state(&mut pyraminx.state, &values)?,
+            PuzzleType::Megaminx(megaminx) => fill_state(&mut megaminx.state, &values)?,
+            PuzzleType::Ivy(cube) => fill_state(&mut cube.state, &values)?
+        }
+
+        puzzle.validate()?;
+        *self = puzzle;
+        Ok(())
+    }
+}
+
+fn fill_state<const F: usize, const N: usize>(state: &mut [[u8; N]; F], values: &[u8]) -> Result<(), String> {
+    if values.len() != F * N {
+        return Err(format!("Expected {} stickers, found {}", F * N, values.len()));
+    }
+
+    for (i, face) in state.iter_mut().enumerate() {
+        face.copy_from_slice(&values[i * N..(i + 1) * N]);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use rand::Rng;
+
+    pub const NAMES: [&str; 6] = ["3x3", "2x2", "Skewb", "Pyraminx", "Megaminx", "Ivy"];
+
+    /// Turns the piece in `slot` in place by moving its stickers round one.
+    pub fn twist<const F: usize, const S: usize, const N: usize>(state: &mut [[u8; S]; F], slot: &[(usize, usize); N]) {
+        let colours = slot.map(|(face, index)| state[face][index]);
+        for (i, &(face, index)) in slot.iter().enumerate() {
+            state[face][index] = colours[(i + 1) % N];
+        }
+    }
+
+    /// Swaps the pieces in two slots without turning either.
+    pub fn swap<const F: usize, const S: usize, const N: usize>(state: &mut [[u8; S]; F], a: &[(usize, usize); N], b: &[(usize, usize); N]) {
+        for (&(x, i), &(y, j)) in a.iter().zip(b) {
+            let colour = state[x][i];
+            state[x][i] = state[y][j];
+            state[y][j] = colour;
+        }
+    }
+
+    /// Puts a copy of the piece in `from` into `to`, so the piece that was
+    /// in `to` is missing.
+    pub fn copy<const F: usize, const S: usize, const N: usize>(state: &mut [[u8; S]; F], from: &[(usize, usize); N], to: &[(usize, usize); N]) {
+        for (&(x, i), &(y, j)) in from.iter().zip(to) {
+            state[y][j] = state[x][i];
+        }
+    }
+
+    pub fn assert_invalid(puzzle: &impl Puzzle, message: &str) {
+        match puzzle.validate() {
+            Ok(()) => panic!("expected an error starting '{message}'"),
+            Err(e) => assert!(e.starts_with(message), "'{e}' does not start with '{message}'")
+        }
+    }
+
+    /// Every turn comes back to where it started after the puzzle's order of
+    /// turns, is undone by its inverse and keeps the puzzle solvable. The
+    /// Skewb's F and the Pyraminx's L once moved the wrong stickers.
+    #[test]
+    fn turns_have_the_right_order() {
+        for name in NAMES {
+            let puzzle = PuzzleType::from_name(name).unwrap();
+            let (letters, _, order) = puzzle.move_set();
+            for letter in letters.chars() {
+                let turn = letter.to_string();
+                let mut turned = puzzle.solved();
+                for count in 1..=order {
+                    turned.input_moves(&turn);
+                    assert_eq!(turned.is_solved(), count == order, "{name} {turn} {count}");
+                    assert!(turned.validate().is_ok(), "{name} {turn} {count}");
+                }
+
+                // From a scrambled state, so that a turn that only moves
+                // stickers of one colour around cannot pass.
+                let setup: Vec<String> = letters.chars().map(|x| x.to_string()).collect();
+                let mut expected = puzzle.solved();
+                expected.input_moves(&setup.join(" "));
+                let mut undone = expected.clone();
+                undone.input_moves(&turn);
+                assert_ne!(undone.stickers(), expected.stickers(), "{name} {turn}");
+                assert!(undone.validate().is_ok(), "{name} {turn}");
+                undone.input_moves(&puzzle.inverse_move(&turn).unwrap());
+                assert_eq!(undone.stickers(), expected.stickers(), "{name} {turn}");
+            }
+        }
+    }
+
+    #[test]
+    fn scrambles_stay_solvable() {
+        let mut rng = rand::thread_rng();
+        for name in NAMES {
+            let mut puzzle = PuzzleType::from_name(name).unwrap();
+            let letters: Vec<char> = puzzle.move_set().0.chars().collect();
+            let moves: Vec<String> = (0..40).map(|_| letters[rng.gen_range(0..letters.len())].to_string()).collect();
+            puzzle.input_moves(&moves.join(" "));
+            assert!(puzzle.validate().is_ok(), "{name}: {}", moves.join(" "));
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use crate::algorithms::{format, Move, Notation};
+use crate::algorithms::transforms::inverse;
+
+/// Reads algorithm sheet notation on top of plain moves:
+///
+/// - `[A, B]` is the commutator `A B A' B'`
+/// - `[A: B]` is the conjugate `A B A'`
+/// - `(A)3` repeats `A` three times, and a `'` after a bracket inverts it
+/// - `//` starts a comment that runs to the end of the line
+///
+/// Brackets can be nested, e.g. `[U: [R U R', D]]2`.
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+    notation: Notation
+}
+
+const DELIMITERS: &str = "()[],:";
+
+/// The most moves a repeat may expand to, so that a typo like `(R U)99999`
+/// is an error rather than running out of memory.
+const MAX_MOVES: usize = 10000;
+
+impl Parser {
+    fn peek(&mut self) -> Option<char> {
+        while self.chars.get(self.position).is_some_and(|x| x.is_whitespace()) {
+            self.position += 1;
+        }
+        self.chars.get(self.position).copied()
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.peek() {
+            Some(x) if x == expected => {
+                self.position += 1;
+                Ok(())
+            }
+            Some(x) => Err(format!("Expected '{expected}' but found '{x}'")),
+            None => Err(format!("Missing '{expected}'"))
+        }
+    }
+
+    /// Moves up to (but not including) one of `closing`, or to the end.
+    fn sequence(&mut self, closing: &str) -> Result<Vec<Move>, String> {
+        let mut moves = Vec::new();
+
+        loop {
+            match self.peek() {
+                None if closing.is_empty() => return Ok(moves),
+                None => return Err(format!("Missing '{}'", closing.chars().last().unwrap())),
+                Some(x) if closing.contains(x) => return Ok(moves),
+                Some('(') => {
+                    self.position += 1;
+                    let group = self.sequence(")")?;
+                    self.expect(')')?;
+                    moves.extend(self.repeat(group)?);
+                }
+                Some('[') => {
+                    self.position += 1;
+                    let a = self.sequence(",:")?;
+                    let separator = self.peek();
+                    if separator != Some(',') && separator != Some(':') {
+                        return Err("Expected ',' or ':' inside '['".to_string());
+                    }
+                    self.position += 1;
+                    let b = self.sequence("]")?;
+                    self.expect(']')?;
+
+                    let mut group = [a.clone(), b.clone(), inverse(&a)].concat();
+                    if separator == Some(',') {
+                        group.extend(inverse(&b));
+                    }
+                    moves.extend(self.repeat(group)?);
+                }
+                Some(x) if DELIMITERS.contains(x) => return Err(format!("Unexpected '{x}'")),
+                Some(_) => {
+                    let start = self.position;
+                    while self.chars.get(self.position).is_some_and(|x| !x.is_whitespace() && !DELIMITERS.contains(*x)) {
+                        self.position += 1;
+                    }
+                    let token: String = self.chars[start..self.position].iter().collect();
+                    moves.push(self.notation.parse_move(&token)?);
+                }
+            }
+        }
+    }
+
+    /// Applies a repeat count and `'` written straight after a bracket.
+    fn repeat(&mut self, group: Vec<Move>) -> Result<Vec<Move>, String> {
+        let start = self.position;
+        while self.chars.get(self.position).is_some_and(|x| x.is_ascii_digit()) {
+            self.position += 1;
+        }
+        let count: String = self.chars[start..self.position].iter().collect();
+        let count: usize = if count.is_empty() { 1 } else { count.parse().map_err(|_| format!("{count} is too many repeats"))? };
+        if count > MAX_MOVES || group.len() * count > MAX_MOVES {
+            return Err(format!("{count} is too many repeats: at most {MAX_MOVES} moves can be written out"));
+        }
+
+        let group = if self.chars.get(self.position) == Some(&'\'') {
+            self.position += 1;
+            inverse(&group)
+        } else {
+            group
+        };
+        Ok(vec![group; count].concat())
+    }
+}
+
+/// Expands `alg` into the plain moves it stands for.
+pub fn parse(alg: &str, notation: Notation) -> Result<Vec<Move>, String> {
+    let without_comments: Vec<&str> = alg.lines().map(|x| x.split("//").next().unwrap_or_default()).collect();
+    let mut parser = Parser { chars: without_comments.join("\n").chars().collect(), position: 0, notation };
+    parser.sequence("")
+}
+
+/// `parse`, written back out as space separated moves for `input_moves`.
+pub fn expand(alg: &str, notation: Notation) -> Result<String, String> {
+    Ok(format(&parse(alg, notation)?))
+}
+
+/// What the models' `input_moves` actually turn. Plain moves are passed
+/// through untouched so that the database generators stay fast, and text
+/// that does not parse is left for `input_moves` to stop at as before.
+pub fn flatten(alg: &str, notation: Notation) -> String {
+    if !alg.contains(|x| DELIMITERS.contains(x)) && !alg.contains("//") {
+        return alg.to_uppercase();
+    }
+    expand(alg, notation).unwrap_or(alg.to_string()).to_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube(alg: &str) -> Result<String, String> {
+        expand(alg, Notation::Cube)
+    }
+
+    #[test]
+    fn brackets_expand() {
+        assert_eq!(cube("[R, U]").unwrap(), "R U R' U'");
+        assert_eq!(cube("[R: U]").unwrap(), "R U R'");
+        assert_eq!(cube("[U: [R U R', D]]").unwrap(), "U R U R' D R U' R' D' U'");
+        assert_eq!(cube("[R, [U: D]]").unwrap(), "R U D U' R' U D' U'");
+        assert_eq!(cube("(R U)3").unwrap(), "R U R U R U");
+        assert_eq!(cube("(R U)3'").unwrap(), "U' R' U' R' U' R'");
+        assert_eq!(cube("(R U)'").unwrap(), "U' R'");
+        assert_eq!(cube("[R, U]2").unwrap(), "R U R' U' R U R' U'");
+    }
+
+    #[test]
+    fn comments_run_to_the_end_of_the_line() {
+        assert_eq!(cube("R U // sexy\nR' U' // move").unwrap(), "R U R' U'");
+        assert_eq!(cube("// nothing").unwrap(), "");
+    }
+
+    #[test]
+    fn malformed_brackets_are_errors() {
+        for alg in ["(R U", "R U)", "[R U]", "[R, U", "R, U", "[R: U)", "]", "[R, U: F]"] {
+            assert!(cube(alg).is_err(), "{alg}");
+        }
+        assert!(cube("(R Q)").is_err());
+    }
+
+    #[test]
+    fn repeats_are_limited() {
+        assert!(cube("(R U)99999999999").is_err());
+        assert!(cube("(R U)99999999999999999999999").is_err());
+        assert!(cube("(((R U)100)100)100").is_err());
+        assert_eq!(parse("(R)10000", Notation::Cube).unwrap().len(), 10000);
+    }
+
+    #[test]
+    fn flatten_upper_cases_plain_moves() {
+        assert_eq!(flatten("r u", Notation::Cube), "R U");
+        assert_eq!(flatten("[r, u]", Notation::Cube), "R U R' U'");
+        assert_eq!(flatten("(R", Notation::Cube), "(R");
+    }
+}
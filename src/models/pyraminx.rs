@@ -1,7 +1,10 @@
 use std::collections::HashMap;
 use colored::{Colorize, ColoredString};
 
-use crate::models::Puzzle;
+use crate::colour_scheme;
+use crate::algorithms::{Move, Notation};
+use crate::solvers::pyraminx as solver;
+use crate::models::{parser, Puzzle, find_pieces, orientation_sum, permutation_parity, slot_name};
 
 pub enum Faces {
     Blue,
@@ -28,6 +31,23 @@ impl Faces {
     }
 }
 
+/// Sticker positions of the tips and the axial centres under them, in the
+/// same order as `Tips` (top, right, left, back).
+pub const TIPS: [[(usize, usize); 3]; 4] = [
+    [(0, 4), (1, 4), (2, 4)], [(0, 7), (3, 4), (1, 0)], [(0, 0), (2, 7), (3, 0)], [(1, 7), (3, 7), (2, 0)]
+];
+
+pub const AXIALS: [[(usize, usize); 3]; 4] = [
+    [(0, 3), (1, 3), (2, 3)], [(0, 6), (3, 3), (1, 1)], [(0, 1), (2, 6), (3, 1)], [(1, 6), (3, 6), (2, 1)]
+];
+
+pub const EDGES: [[(usize, usize); 2]; 6] = [
+    [(0, 5), (1, 2)], [(0, 2), (2, 5)], [(0, 8), (3, 2)], [(1, 5), (2, 2)], [(1, 8), (3, 5)], [(2, 8), (3, 8)]
+];
+
+pub const FACE_LETTERS: [char; 4] = ['F', 'R', 'L', 'D'];
+
+#[derive(Clone)]
 pub struct Pyraminx {
     pub state: [[u8; 9]; 4]
 }
@@ -72,7 +92,7 @@ impl Pyraminx {
         let adjacent = match face {
             Faces::Blue => [(2, [4, 3, 5, 6, 7]), (3, [0, 1, 2, 3, 4]), (1, [0, 1, 2, 3, 4])],
             Faces::Red => [(0, [4, 3, 5, 6, 7]), (3, [4, 3, 5, 6, 7]), (2, [0, 1, 2, 3, 4])],
-            Faces::Green => [(1, [7, 6, 5, 3, 4]), (3, [1, 0, 8, 6, 7]), (0, [4, 3, 2, 1, 0])],
+            Faces::Green => [(1, [7, 6, 5, 3, 4]), (3, [0, 1, 8, 6, 7]), (0, [4, 3, 2, 1, 0])],
             Faces::Yellow => [(0, [7, 6, 8, 0, 1]), (2, [7, 6, 8, 0, 1]), (1, [7, 6, 8, 0, 1])]
         };
 
@@ -119,7 +139,7 @@ impl Pyraminx {
     }
 
     pub fn input_moves(&mut self, moves: &str) {
-        let moves = moves.to_uppercase();
+        let moves = parser::flatten(moves, Notation::Pyraminx);
         let moves_list: Vec<&str> = moves.split_whitespace().collect();
 
         for x in moves_list {
@@ -156,6 +176,16 @@ impl Pyraminx {
             }
         }
     }
+
+    /// A solution in WCA notation, optimal apart from the tips.
+    pub fn solve(&self) -> Result<Vec<Move>, String> {
+        solver::solve(self)
+    }
+
+    /// Every optimal solution of an L4E case, in WCA notation.
+    pub fn solve_l4e(&self) -> Result<Vec<Vec<Move>>, String> {
+        solver::solve_l4e(self)
+    }
 }
 
 impl Puzzle for Pyraminx {
@@ -174,7 +204,31 @@ impl Puzzle for Pyraminx {
         self.state == [[0u8; 9], [1u8; 9], [2u8; 9], [3u8; 9]]
     }
 
-    fn print(&self) {
+    fn validate(&self) -> Result<(), String> {
+        let (tips, _) = find_pieces(&self.state, &TIPS, "tip", &FACE_LETTERS)?;
+        let (axials, axial_orientation) = find_pieces(&self.state, &AXIALS, "axial centre", &FACE_LETTERS)?;
+        let (edge_permutation, edge_orientation) = find_pieces(&self.state, &EDGES, "edge", &FACE_LETTERS)?;
+
+        if let Some(slot) = (0..tips.len()).find(|&slot| tips[slot] != axials[slot]) {
+            return Err(format!("The tip at {} does not match the axial centre under it", slot_name(&TIPS[slot], &FACE_LETTERS)));
+        }
+        if permutation_parity(&axials) != 0 {
+            return Err("Parity: the axial centres are in an odd permutation".to_string());
+        }
+        if permutation_parity(&edge_permutation) != 0 {
+            return Err("Parity: the edges are in an odd permutation".to_string());
+        }
+        if !orientation_sum(&axial_orientation).is_multiple_of(3) {
+            return Err("Axial twist: the axial centre orientations do not sum to a multiple of 3".to_string());
+        }
+        if !orientation_sum(&edge_orientation).is_multiple_of(2) {
+            return Err("Edge flip: an odd number of edges are flipped".to_string());
+        }
+        Ok(())
+    }
+
+    fn render(&self) -> Vec<String> {
+        let scheme = colour_scheme::current();
         let mut print_state: Vec<Vec<ColoredString>> = vec![vec![" ".white(); 32]; 11];
         let print_map: HashMap<_, _> = vec![
             ((0, 0),(4, 11)), ((0, 1),(3, 14)), ((0, 2),(2, 13)), ((0, 3),(2, 15)), ((0, 4),(0, 15)), ((0, 5),(2, 17)), ((0, 6),(3, 16)), ((0, 7),(4, 19)), ((0, 8),(4, 15)), 
@@ -185,15 +239,32 @@ impl Puzzle for Pyraminx {
         for i in 0..4 {
             for j in 0..9 {
                 let (x, y) = print_map[&(i, j)];
-                print_state[x][y] = match self.state[i][j] {0 => "B".blue(), 1 => "R".red(), 2 => "G".green(), 3=> "Y".yellow(), _ => " ".white()}
+                print_state[x][y] = colour_scheme::paint(&scheme.pyraminx, self.state[i][j])
             }
         }
 
-        for row in print_state {
-            for cell in row {
-                print!("{}", cell);
-            }
-            println!();
-        }
+        print_state.iter().map(|row| row.iter().map(|cell| format!("{}", cell)).collect()).collect()
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::tests::{assert_invalid, copy, swap, twist};
+
+    #[test]
+    fn unreachable_states_are_rejected() {
+        let broken = |change: fn(&mut [[u8; 9]; 4])| {
+            let mut pyraminx = Pyraminx::default();
+            change(&mut pyraminx.state);
+            pyraminx
+        };
+        assert_invalid(&broken(|x| swap(x, &TIPS[0], &TIPS[1])), "The tip at");
+        assert_invalid(&broken(|x| { twist(x, &TIPS[0]); twist(x, &AXIALS[0]) }), "Axial twist");
+        assert_invalid(&broken(|x| { swap(x, &TIPS[0], &TIPS[1]); swap(x, &AXIALS[0], &AXIALS[1]) }), "Parity: the axial centres");
+        assert_invalid(&broken(|x| swap(x, &EDGES[0], &EDGES[1])), "Parity: the edges");
+        assert_invalid(&broken(|x| twist(x, &EDGES[0])), "Edge flip");
+        assert_invalid(&broken(|x| copy(x, &EDGES[0], &EDGES[1])), "The edge");
+        // Tips turn on their own.
+        assert!(broken(|x| twist(x, &TIPS[0])).validate().is_ok());
+    }
+}
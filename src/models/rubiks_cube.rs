@@ -1,8 +1,12 @@
 use std::collections::HashMap;
 use colored::{Colorize, ColoredString};
 
-use crate::models::{Puzzle, Faces};
+use crate::colour_scheme;
+use crate::algorithms::{Move, Notation};
+use crate::solvers::kociemba;
+use crate::models::{parser, Puzzle, Faces, find_pieces, orientation_sum, permutation_parity};
 
+#[derive(Clone)]
 pub struct RubiksCube {
     pub state: [[u8; 8]; 6]
 }
@@ -15,7 +19,106 @@ impl Default for RubiksCube {
     }
 }
 
+/// Sticker positions of each corner slot in the order URF, UFL, ULB, UBR,
+/// DFR, DLF, DBL, DRB, starting with the U/D sticker and going clockwise.
+pub const CORNERS: [[(usize, usize); 3]; 8] = [
+    [(0, 4), (2, 0), (1, 2)], [(0, 6), (1, 0), (4, 2)], [(0, 0), (4, 0), (3, 2)], [(0, 2), (3, 0), (2, 2)],
+    [(5, 2), (1, 4), (2, 6)], [(5, 0), (4, 4), (1, 6)], [(5, 6), (3, 4), (4, 6)], [(5, 4), (2, 4), (3, 6)]
+];
+
+/// Sticker positions of each edge slot in the order UR, UF, UL, UB, DR, DF,
+/// DL, DB, FR, FL, BL, BR, starting with the U/D (or F/B) sticker.
+pub const EDGES: [[(usize, usize); 2]; 12] = [
+    [(0, 3), (2, 1)], [(0, 5), (1, 1)], [(0, 7), (4, 1)], [(0, 1), (3, 1)],
+    [(5, 3), (2, 5)], [(5, 1), (1, 5)], [(5, 7), (4, 5)], [(5, 5), (3, 5)],
+    [(1, 3), (2, 7)], [(1, 7), (4, 3)], [(3, 3), (4, 7)], [(3, 7), (2, 3)]
+];
+
+/// Faces in facelet string order (U, R, F, D, L, B) and, for each of the nine
+/// facelets read row by row, the sticker index on that face (`None` for the centre).
+const FACELET_FACES: [usize; 6] = [0, 2, 1, 5, 4, 3];
+const FACELET_INDEXES: [Option<usize>; 9] = [Some(0), Some(1), Some(2), Some(7), None, Some(3), Some(6), Some(5), Some(4)];
+pub const FACE_LETTERS: [char; 6] = ['U', 'F', 'R', 'B', 'L', 'D'];
+
+/// Which piece occupies each slot and how it is twisted or flipped.
+pub struct Pieces {
+    pub corner_permutation: [u8; 8],
+    pub corner_orientation: [u8; 8],
+    pub edge_permutation: [u8; 12],
+    pub edge_orientation: [u8; 12]
+}
+
 impl RubiksCube {
+    /// Parses a 54 character facelet string in Kociemba's format (faces in
+    /// the order U, R, F, D, L, B, each read row by row).
+    pub fn from_facelets(facelets: &str) -> Result<Self, String> {
+        let letters: Vec<char> = facelets.trim().to_uppercase().chars().collect();
+        if letters.len() != 54 {
+            return Err(format!("Facelet string must be 54 characters long, found {}", letters.len()));
+        }
+
+        let mut cube = Self::default();
+        let mut counts = [0; 6];
+
+        for (i, &face) in FACELET_FACES.iter().enumerate() {
+            for (j, &index) in FACELET_INDEXES.iter().enumerate() {
+                let letter = letters[i * 9 + j];
+                let value = match FACE_LETTERS.iter().position(|&x| x == letter) {
+                    Some(value) => value,
+                    None => return Err(format!("'{letter}' is not one of U, R, F, D, L, B"))
+                };
+                counts[value] += 1;
+
+                match index {
+                    Some(index) => cube.state[face][index] = value as u8,
+                    None if value != face => return Err(format!("Centre of the {} face is {letter}", FACE_LETTERS[face])),
+                    None => {}
+                }
+            }
+        }
+
+        for (value, &count) in counts.iter().enumerate() {
+            if count != 9 {
+                return Err(format!("Expected 9 {} facelets, found {count}", FACE_LETTERS[value]));
+            }
+        }
+
+        cube.validate()?;
+        Ok(cube)
+    }
+
+    pub fn to_facelets(&self) -> String {
+        let mut facelets = String::new();
+
+        for &face in FACELET_FACES.iter() {
+            for index in FACELET_INDEXES {
+                let value = match index {
+                    Some(index) => self.state[face][index] as usize,
+                    None => face
+                };
+                facelets.push(FACE_LETTERS.get(value).copied().unwrap_or('?'));
+            }
+        }
+        facelets
+    }
+
+    /// A solution of at most 21 moves, found with the two-phase solver.
+    pub fn solve(&self) -> Result<Vec<Move>, String> {
+        kociemba::solve(self)
+    }
+
+    pub fn pieces(&self) -> Result<Pieces, String> {
+        let (corner_permutation, corner_orientation) = find_pieces(&self.state, &CORNERS, "corner", &FACE_LETTERS)?;
+        let (edge_permutation, edge_orientation) = find_pieces(&self.state, &EDGES, "edge", &FACE_LETTERS)?;
+
+        Ok(Pieces {
+            corner_permutation: corner_permutation.try_into().unwrap(),
+            corner_orientation: corner_orientation.try_into().unwrap(),
+            edge_permutation: edge_permutation.try_into().unwrap(),
+            edge_orientation: edge_orientation.try_into().unwrap()
+        })
+    }
+
     fn rotate(&mut self, face: Faces, magnitude: u32) {
         let tiles = &mut self.state[face.to_number()];
         let rotation = (magnitude % 8) as usize;
@@ -50,7 +153,7 @@ impl RubiksCube {
     }
 
     pub fn input_moves(&mut self, moves: &str) {
-        let moves = moves.to_uppercase();
+        let moves = parser::flatten(moves, Notation::Cube);
         let moves_list: Vec<&str> = moves.split_whitespace().collect();
 
         for x in moves_list {
@@ -96,30 +199,98 @@ impl Puzzle for RubiksCube {
         self.state == [[0u8; 8], [1u8; 8], [2u8; 8], [3u8; 8], [4u8; 8], [5u8; 8]]
     }
 
-    fn print(&self) {
+    /// Checks that the stickers describe a cube that can be reached from
+    /// solved with face turns.
+    fn validate(&self) -> Result<(), String> {
+        let pieces = self.pieces()?;
+
+        if !orientation_sum(&pieces.corner_orientation).is_multiple_of(3) {
+            return Err("Corner twist: the corner orientations do not sum to a multiple of 3".to_string());
+        }
+        if !orientation_sum(&pieces.edge_orientation).is_multiple_of(2) {
+            return Err("Edge flip: an odd number of edges are flipped".to_string());
+        }
+        if permutation_parity(&pieces.corner_permutation) != permutation_parity(&pieces.edge_permutation) {
+            return Err("Parity: corner and edge permutations have different parity".to_string());
+        }
+
+        Ok(())
+    }
+
+    fn render(&self) -> Vec<String> {
+        let scheme = colour_scheme::current();
         let mut print_state: Vec<Vec<ColoredString>> = vec![vec![" ".white(); 12]; 9];
-        print_state[1][4] = "W".white();
-        print_state[4][4] = "B".blue();
-        print_state[4][7] = ColoredString::from(format!("\x1b[38;5;208m{}\x1b[0m", "O"));
-        print_state[4][10] = "G".green();
-        print_state[4][1] = "R".red();
-        print_state[7][4] = "Y".yellow();
+        for (face, (x, y)) in [(1, 4), (4, 4), (4, 7), (4, 10), (4, 1), (7, 4)].into_iter().enumerate() {
+            print_state[x][y] = colour_scheme::paint(&scheme.cube, face as u8);
+        }
 
         let print_map: HashMap<_, _> = vec![((0, 0), (0, 3)),((0, 1), (0, 4)),((0, 2), (0, 5)),((0, 3), (1, 5)),((0, 4), (2, 5)),((0, 5), (2, 4)),((0, 6), (2, 3)),((0, 7), (1, 3)),((1, 0), (3, 3)),((1, 1), (3, 4)),((1, 2), (3, 5)),((1, 3), (4, 5)),((1, 4), (5, 5)),((1, 5), (5, 4)),((1, 6), (5, 3)),((1, 7), (4, 3)),((2, 0), (3, 6)),((2, 1), (3, 7)),((2, 2), (3, 8)),((2, 3), (4, 8)),((2, 4), (5,8)),((2, 5), (5,7)),((2, 6), (5,6)),((2, 7), (4, 6)),((3, 0), (3,9)),((3, 1), (3,10)),((3, 2), (3,11)),((3, 3), (4,11)),((3, 4), (5,11)),((3, 5), (5,10)),((3, 6), (5,9)),((3, 7), (4, 9)),((4, 0), (3,0)),((4, 1), (3,1)),((4, 2), (3,2)),((4, 3), (4,2)),((4, 4), (5,2)),((4, 5), (5,1)),((4, 6), (5,0)),((4, 7), (4, 0)),((5, 0), (6,3)),((5, 1), (6,4)),((5, 2), (6,5)),((5, 3), (7, 5)),((5, 4), (8,5)),((5, 5), (8,4)),((5, 6), (8,3)),((5, 7), (7,3))].into_iter().collect();
 
         for i in 0..6 {
             for j in 0..8 {
                 let (x, y) = print_map[&(i, j)];
-                print_state[x][y] = match self.state[i][j] {0 => "W".white(), 1 => "B".blue(), 2 => ColoredString::from(format!("\x1b[38;5;208m{}\x1b[0m", "O")), 3 => "G".green(), 4 => "R".red(), 5 => "Y".yellow(), _ => " ".white()}
+                print_state[x][y] = colour_scheme::paint(&scheme.cube, self.state[i][j])
             }
         }
 
                 
-        for row in print_state {
-            for cell in row {
-                print!("{} ", cell);
-            }
-            println!();
+        print_state.iter().map(|row| row.iter().map(|cell| format!("{} ", cell)).collect()).collect()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use crate::models::tests::{assert_invalid, copy, swap, twist};
+
+    #[test]
+    fn facelets_of_r() {
+        let cube = RubiksCube::from_facelets("UUFUUFUUFRRRRRRRRRFFDFFDFFDDDBDDBDDBLLLLLLLLLUBBUBBUBB").unwrap();
+        let mut turned = RubiksCube::default();
+        turned.input_moves("R");
+        assert_eq!(turned.state, cube.state);
+        assert_eq!(RubiksCube::default().to_facelets(), "UUUUUUUUURRRRRRRRRFFFFFFFFFDDDDDDDDDLLLLLLLLLBBBBBBBBB");
+    }
+
+    #[test]
+    fn facelets_round_trip() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let scramble: Vec<String> = (0..25).map(|_| format!("{}{}", ["U", "R", "F", "D", "L", "B"][rng.gen_range(0..6)], ["", "2", "'"][rng.gen_range(0..3)])).collect();
+            let mut cube = RubiksCube::default();
+            cube.input_moves(&scramble.join(" "));
+
+            let facelets = cube.to_facelets();
+            let read = RubiksCube::from_facelets(&facelets).unwrap();
+            assert_eq!(read.state, cube.state);
+            assert_eq!(read.to_facelets(), facelets);
+        }
+    }
+
+    #[test]
+    fn unreachable_facelets_are_rejected() {
+        // A single flipped edge, then a single twisted corner.
+        for (facelets, problem) in [
+            ("UUUUURUUURURRRRRRRFFFFFFFFFDDDDDDDDDLLLLLLLLLBBBBBBBBB", "Edge flip"),
+            ("UUUUUUUUFURRRRRRRRFFRFFFFFFDDDDDDDDDLLLLLLLLLBBBBBBBBB", "Corner twist")
+        ] {
+            assert!(RubiksCube::from_facelets(facelets).err().is_some_and(|x| x.starts_with(problem)));
         }
+        assert!(RubiksCube::from_facelets("UUUU").is_err());
+    }
+
+    #[test]
+    fn unreachable_states_are_rejected() {
+        let broken = |change: fn(&mut [[u8; 8]; 6])| {
+            let mut cube = RubiksCube::default();
+            change(&mut cube.state);
+            cube
+        };
+        assert_invalid(&broken(|x| twist(x, &CORNERS[0])), "Corner twist");
+        assert_invalid(&broken(|x| twist(x, &EDGES[0])), "Edge flip");
+        assert_invalid(&broken(|x| swap(x, &EDGES[0], &EDGES[1])), "Parity");
+        assert_invalid(&broken(|x| copy(x, &EDGES[0], &EDGES[1])), "The edge UF is missing");
+        assert_invalid(&broken(|x| copy(x, &CORNERS[0], &CORNERS[1])), "The corner");
+        assert!(broken(|x| { swap(x, &EDGES[0], &EDGES[1]); swap(x, &CORNERS[0], &CORNERS[1]) }).validate().is_ok());
     }
-}
\ No newline at end of file
+}
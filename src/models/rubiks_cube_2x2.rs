@@ -1,8 +1,11 @@
 use std::collections::HashMap;
 use colored::{Colorize, ColoredString};
 
-use crate::models::{Puzzle, Faces};
+use crate::colour_scheme;
+use crate::algorithms::Notation;
+use crate::models::{parser, Puzzle, Faces, find_pieces, orientation_sum};
 
+#[derive(Clone)]
 pub struct RubiksCube2x2 {
     pub state: [[u8; 4]; 6]
 }
@@ -15,6 +18,15 @@ impl Default for RubiksCube2x2 {
     }
 }
 
+/// Sticker positions of each corner slot in the same order as the 3x3
+/// (URF, UFL, ULB, UBR, DFR, DLF, DBL, DRB), going clockwise.
+pub const CORNERS: [[(usize, usize); 3]; 8] = [
+    [(0, 2), (2, 0), (1, 1)], [(0, 3), (1, 0), (4, 1)], [(0, 0), (4, 0), (3, 1)], [(0, 1), (3, 0), (2, 1)],
+    [(5, 1), (1, 2), (2, 3)], [(5, 0), (4, 2), (1, 3)], [(5, 3), (3, 2), (4, 3)], [(5, 2), (2, 2), (3, 3)]
+];
+
+pub const FACE_LETTERS: [char; 6] = ['U', 'F', 'R', 'B', 'L', 'D'];
+
 impl RubiksCube2x2 {
     fn rotate(&mut self, face: Faces, magnitude: u32) {
         let tiles = &mut self.state[face.to_number()];
@@ -50,7 +62,7 @@ impl RubiksCube2x2 {
     }
 
     pub fn input_moves(&mut self, moves: &str) {
-        let moves = moves.to_uppercase();
+        let moves = parser::flatten(moves, Notation::Cube);
         let moves_list: Vec<&str> = moves.split_whitespace().collect();
 
         for x in moves_list {
@@ -96,22 +108,45 @@ impl Puzzle for RubiksCube2x2 {
         self.state == [[0u8; 4], [1u8; 4], [2u8; 4], [3u8; 4], [4u8; 4], [5u8; 4]]
     }
 
-    fn print(&self) {
+    fn validate(&self) -> Result<(), String> {
+        let (_, orientation) = find_pieces(&self.state, &CORNERS, "corner", &FACE_LETTERS)?;
+
+        if !orientation_sum(&orientation).is_multiple_of(3) {
+            return Err("Corner twist: the corner orientations do not sum to a multiple of 3".to_string());
+        }
+        Ok(())
+    }
+
+    fn render(&self) -> Vec<String> {
+        let scheme = colour_scheme::current();
         let mut print_state: Vec<Vec<ColoredString>> = vec![vec![" ".white(); 8]; 6];
         let print_map: HashMap<_, _> = vec![((0, 0), (0, 2)), ((0, 1), (0, 3)), ((0, 2), (1, 3)), ((0, 3), (1, 2)), ((1, 0), (2, 2)), ((1, 1), (2, 3)), ((1, 2), (3, 3)), ((1, 3), (3, 2)), ((2, 0), (2, 4)), ((2, 1), (2, 5)), ((2, 2), (3, 5)), ((2, 3), (3, 4)), ((3, 0), (2, 6)), ((3, 1), (2, 7)), ((3, 2), (3, 7)), ((3, 3), (3, 6)), ((4, 0), (2, 0)), ((4, 1), (2, 1)), ((4, 2), (3, 1)), ((4, 3), (3, 0)), ((5, 0), (4, 2)), ((5, 1), (4, 3)), ((5, 2), (5, 3)), ((5, 3), (5, 2))].into_iter().collect();
 
         for i in 0..6 {
             for j in 0..4 {
                 let (x, y) = print_map[&(i, j)];
-                print_state[x][y] = match self.state[i][j] {0 => "W".white(), 1 => "B".blue(), 2 => ColoredString::from(format!("\x1b[38;5;208m{}\x1b[0m", "O")), 3 => "G".green(), 4 => "R".red(), 5 => "Y".yellow(), _ => " ".white()}
+                print_state[x][y] = colour_scheme::paint(&scheme.cube, self.state[i][j])
             }
         }
 
-        for row in print_state {
-            for cell in row {
-                print!("{} ", cell);
-            }
-            println!();
-        }
+        print_state.iter().map(|row| row.iter().map(|cell| format!("{} ", cell)).collect()).collect()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::tests::{assert_invalid, copy, swap, twist};
+
+    #[test]
+    fn unreachable_states_are_rejected() {
+        let broken = |change: fn(&mut [[u8; 4]; 6])| {
+            let mut cube = RubiksCube2x2::default();
+            change(&mut cube.state);
+            cube
+        };
+        assert_invalid(&broken(|x| twist(x, &CORNERS[0])), "Corner twist");
+        assert_invalid(&broken(|x| copy(x, &CORNERS[0], &CORNERS[1])), "The corner");
+        // Any permutation of the corners can be reached on the 2x2.
+        assert!(broken(|x| swap(x, &CORNERS[0], &CORNERS[1])).validate().is_ok());
     }
-}
\ No newline at end of file
+}
@@ -1,8 +1,13 @@
 use std::collections::HashMap;
 use colored::{Colorize, ColoredString};
 
-use crate::models::{Puzzle, Faces};
+use crate::colour_scheme;
+use crate::algorithms::{Move, Notation};
+use crate::solvers::skewb as solver;
+use crate::models::{parser, Puzzle, Faces, find_pieces, permutation_parity, slot_name};
+use crate::models::rubiks_cube_2x2::{CORNERS, FACE_LETTERS};
 
+#[derive(Clone)]
 pub struct Skewb {
     pub state: [[u8;5];6]
 }
@@ -15,13 +20,27 @@ impl Default for Skewb {
     }
 }
 
+pub const CENTRES: [[(usize, usize); 1]; 6] = [[(0, 4)], [(1, 4)], [(2, 4)], [(3, 4)], [(4, 4)], [(5, 4)]];
+
+/// Indexes into `CORNERS` of the two sets of four corners that never mix:
+/// URF, ULB, DLF, DRB and UFL, UBR, DFR, DBL.
+pub(crate) const TETRADS: [[usize; 4]; 2] = [[0, 2, 5, 7], [1, 3, 4, 6]];
+
+/// Maps an even permutation of a tetrad to how far it turns the tetrad
+/// (0, 1 or 2). Undoing the double transposition that brings piece 0 home
+/// leaves a 3-cycle of the other three pieces.
+pub(crate) fn tetrad_turn(permutation: [u8; 4]) -> u32 {
+    let home = permutation.iter().position(|&x| x == 0).unwrap();
+    permutation[1 ^ home] as u32 - 1
+}
+
 impl Skewb {
     fn rotate(&mut self, face: Faces, magnitude: u32) {
         let rotation = (magnitude % 8) as usize;
 
         let adjacent = match face {
             Faces::White => [(0, [0, 4, 1, 2]), (2, [0, 4, 1, 2]), (3, [3, 4, 0, 1])],
-            Faces::Blue => [(0, [0, 4, 3, 2]), (4, [2, 4, 1, 0]), (1, [1, 4, 3, 0])],
+            Faces::Blue => [(0, [0, 4, 3, 2]), (4, [2, 4, 1, 0]), (1, [1, 4, 0, 3])],
             Faces::Red => [(0, [3, 4, 2, 1]), (1, [2, 4, 1, 0]), (2, [1, 4, 0, 3])],
             Faces::Green => [(0, [1, 4, 0, 3]), (3, [2, 4, 1, 0]), (4, [1, 4, 0, 3])],
             _ => return
@@ -67,7 +86,7 @@ impl Skewb {
     }
 
     pub fn input_moves(&mut self, moves: &str) {
-        let moves = moves.to_uppercase();
+        let moves = parser::flatten(moves, Notation::Corner);
         let moves_list: Vec<&str> = moves.split_whitespace().collect();
 
         for x in moves_list {
@@ -93,6 +112,16 @@ impl Skewb {
             }
         }
     }
+
+    /// A solution in as few moves as possible.
+    pub fn solve(&self) -> Result<Vec<Move>, String> {
+        solver::solve(self)
+    }
+
+    /// Every solution in as few moves as possible.
+    pub fn solve_all(&self) -> Result<Vec<Vec<Move>>, String> {
+        solver::solve_all(self)
+    }
 }
 
 impl Puzzle for Skewb {
@@ -111,7 +140,45 @@ impl Puzzle for Skewb {
         self.state == [[0u8;5], [1u8;5], [2u8;5], [3u8;5], [4u8;5], [5u8;5]]
     }
 
-    fn print(&self) {
+    /// The D centre never moves, corners never leave their tetrad and each
+    /// tetrad's permutation fixes the total twist of the other tetrad.
+    fn validate(&self) -> Result<(), String> {
+        let (corner_permutation, corner_orientation) = find_pieces(&self.state, &CORNERS, "corner", &FACE_LETTERS)?;
+        let (centres, _) = find_pieces(&self.state, &CENTRES, "centre", &FACE_LETTERS)?;
+
+        if centres[5] != 5 {
+            return Err("The D centre cannot move".to_string());
+        }
+        if permutation_parity(&centres) != 0 {
+            return Err("Parity: the centres are in an odd permutation".to_string());
+        }
+
+        let mut turns = [0u32; 2];
+        let mut twists = [0u32; 2];
+        for (t, tetrad) in TETRADS.iter().enumerate() {
+            let mut permutation = [0u8; 4];
+            for (i, &slot) in tetrad.iter().enumerate() {
+                match tetrad.iter().position(|&piece| piece == corner_permutation[slot] as usize) {
+                    Some(piece) => permutation[i] = piece as u8,
+                    None => return Err(format!("The corner at {} has come from the other tetrad", slot_name(&CORNERS[slot], &FACE_LETTERS)))
+                }
+                twists[t] += corner_orientation[slot] as u32;
+            }
+
+            if permutation_parity(&permutation) != 0 {
+                return Err("Parity: the corners of a tetrad are in an odd permutation".to_string());
+            }
+            turns[t] = tetrad_turn(permutation);
+        }
+
+        if twists[0] % 3 != turns[1] || twists[1] % 3 != (3 - turns[0]) % 3 {
+            return Err("Corner twist: the corner orientations do not match the corner permutation".to_string());
+        }
+        Ok(())
+    }
+
+    fn render(&self) -> Vec<String> {
+        let scheme = colour_scheme::current();
         let mut print_state: Vec<Vec<ColoredString>> = vec![vec![" ".white(); 12]; 9];
 
         let print_map: HashMap<_, _> = vec![((0, 0), (0, 3)), ((0, 1), (0, 5)), ((0, 2), (2, 5)), ((0, 3), (2, 3)), ((0, 4), (1, 4)), ((1, 0), (3, 3)), ((1, 1), (3, 5)), ((1, 2), (5, 5)), ((1, 3), (5, 3)), ((1, 4), (4, 4)), ((2, 0), (3, 6)), ((2, 1), (3, 8)), ((2, 2), (5, 8)), ((2, 3), (5, 6)), ((2, 4), (4, 7)), ((3, 0), (3, 9)), ((3, 1), (3, 11)), ((3, 2), (5, 11)), ((3, 3), (5, 9)), ((3, 4), (4, 10)), ((4, 0), (3, 0)), ((4, 1), (3, 2)), ((4, 2), (5, 2)), ((4, 3), (5, 0)), ((4, 4), (4, 1)), ((5, 0), (6, 3)), ((5, 1), (6, 5)), ((5, 2), (8, 5)), ((5, 3), (8, 3)), ((5, 4), (7, 4))].into_iter().collect();
@@ -119,16 +186,31 @@ impl Puzzle for Skewb {
         for i in 0..6 {
             for j in 0..5 {
                 let (x, y) = print_map[&(i, j)];
-                print_state[x][y] = match self.state[i][j] {0 => "W".white(), 1 => "B".blue(), 2 => ColoredString::from(format!("\x1b[38;5;208m{}\x1b[0m", "O")), 3 => "G".green(), 4 => "R".red(), 5 => "Y".yellow(), _ => " ".white()}
+                print_state[x][y] = colour_scheme::paint(&scheme.cube, self.state[i][j])
             }
         }
 
                 
-        for row in print_state {
-            for cell in row {
-                print!("{} ", cell);
-            }
-            println!();
-        }
+        print_state.iter().map(|row| row.iter().map(|cell| format!("{} ", cell)).collect()).collect()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::tests::{assert_invalid, copy, swap, twist};
+
+    #[test]
+    fn unreachable_states_are_rejected() {
+        let broken = |change: fn(&mut [[u8; 5]; 6])| {
+            let mut skewb = Skewb::default();
+            change(&mut skewb.state);
+            skewb
+        };
+        assert_invalid(&broken(|x| swap(x, &CENTRES[0], &CENTRES[5])), "The D centre cannot move");
+        assert_invalid(&broken(|x| swap(x, &CENTRES[0], &CENTRES[1])), "Parity: the centres");
+        assert_invalid(&broken(|x| swap(x, &CORNERS[0], &CORNERS[1])), "The corner at");
+        assert_invalid(&broken(|x| swap(x, &CORNERS[0], &CORNERS[2])), "Parity: the corners of a tetrad");
+        assert_invalid(&broken(|x| twist(x, &CORNERS[0])), "Corner twist");
+        assert_invalid(&broken(|x| copy(x, &CORNERS[0], &CORNERS[2])), "The corner");
     }
-}
\ No newline at end of file
+}
@@ -1,4 +1,6 @@
 use rand::Rng;
+use crate::algorithms::{format, Move, Notation};
+use crate::algorithms::simplify::simplify;
 use crate::models::PuzzleType;
 
 fn get_array_size_for_puzzle(puzzle: &PuzzleType) -> Vec<Vec<&'static str>> {
@@ -12,16 +14,16 @@ fn get_array_size_for_puzzle(puzzle: &PuzzleType) -> Vec<Vec<&'static str>> {
             vec!["B ", "B' ", "B2 "],
         ],
         PuzzleType::Skewb(_) | PuzzleType::Ivy(_) => vec![
-            vec!["U ", "U' ", "U2 "],
-            vec!["R ", "R' ", "R2 "],
-            vec!["L ", "L' ", "L2 "],
-            vec!["F ", "F' ", "F2 "],
+            vec!["U ", "U' "],
+            vec!["R ", "R' "],
+            vec!["L ", "L' "],
+            vec!["F ", "F' "],
         ],
         PuzzleType::Pyraminx(_) => vec![
-            vec!["D ", "D' ", "D2 "],
-            vec!["R ", "R' ", "R2 "],
-            vec!["L ", "L' ", "L2 "],
-            vec!["F ", "F' ", "F2 "],
+            vec!["D ", "D' "],
+            vec!["R ", "R' "],
+            vec!["L ", "L' "],
+            vec!["F ", "F' "],
         ],
         PuzzleType::Megaminx(_) => vec![
             vec!["U ", "U' ", "U2 ", "U3 "],
@@ -69,34 +71,111 @@ fn get_array_size_for_puzzle(puzzle: &PuzzleType) -> Vec<Vec<&'static str>> {
 //     }
 // }
 
+/// Faces grouped by axis. Two turns in a row never share an axis, so a
+/// scramble never has `U D U` or `R R'`.
+fn get_axes_for_puzzle(puzzle: &PuzzleType) -> Vec<Vec<usize>> {
+    match puzzle {
+        PuzzleType::RubiksCube(_) | PuzzleType::RubiksCube2x2(_) => vec![vec![0, 1], vec![2, 3], vec![4, 5]],
+        _ => (0..get_array_size_for_puzzle(puzzle).len()).map(|x| vec![x]).collect()
+    }
+}
+
+/// How many random moves make a scramble: 25 on the 3x3 as in the WCA's old
+/// random-move scrambles, 70 face turns on the Megaminx and 11 on the rest.
+/// These are not the WCA's random-state or Pochmann scrambles.
+pub fn default_length(puzzle: &PuzzleType) -> u16 {
+    match puzzle {
+        PuzzleType::RubiksCube(_) => 25,
+        PuzzleType::Megaminx(_) => 70,
+        _ => 11
+    }
+}
+
 pub fn main(n: u16, puzzle:&PuzzleType) -> String {
     let available_moves = get_array_size_for_puzzle(puzzle);
+    let axes = get_axes_for_puzzle(puzzle);
+    let notation = Notation::of(puzzle);
+    let mut rng = rand::thread_rng();
 
-    let moves = [[1, 2], [3, 4], [5, 6]];
+    let mut moves: Vec<Move> = Vec::new();
+    let mut previous_axis = None;
 
-    let mut scramble: String = String::new();
-	let mut previous_move = 2;
+    // Moves that cancel or merge with earlier ones (e.g. `U D U` on the
+    // Megaminx) are simplified away and replaced until there are `n`.
+    while moves.len() < n as usize {
+        let axis = loop {
+            let x = rng.gen_range(0..axes.len());
+            if Some(x) != previous_axis {
+                break x;
+            }
+        };
+        previous_axis = Some(axis);
+
+        let face = &available_moves[axes[axis][rng.gen_range(0..axes[axis].len())]];
+        moves.push(notation.parse_move(face[rng.gen_range(0..face.len())].trim()).unwrap());
+        moves = simplify(&moves);
+    }
+
+    let mut scramble = format(&moves) + " ";
 
-    for _ in 0..n {
-        let mut options = Vec::new();
-        for group in moves {
-            for num in group {
-                if group != moves[previous_move] {
-                    options.push(num)
-                }
+    // Pyraminx scrambles finish by twisting each tip at random.
+    if let PuzzleType::Pyraminx(_) = puzzle {
+        for tip in ["T", "E", "K", "B"] {
+            match rng.gen_range(0..3) {
+                1 => scramble += &format!("{tip} "),
+                2 => scramble += &format!("{tip}' "),
+                _ => {}
             }
         }
+    }
+
+    scramble.trim_end().to_string()
+}
 
-        let x = options[rand::thread_rng().gen_range(0..available_moves[0].len())];
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::tests::NAMES;
 
-        for (j, current_move) in moves.iter().enumerate() {
-            if current_move.contains(&x) {
-                previous_move = j
+    /// The axis of each face letter, in the order `get_array_size_for_puzzle`
+    /// lists the faces.
+    fn axis_of(puzzle: &PuzzleType, letter: char) -> Option<usize> {
+        let face = get_array_size_for_puzzle(puzzle).iter().position(|x| x[0].starts_with(letter))?;
+        get_axes_for_puzzle(puzzle).iter().position(|x| x.contains(&face))
+    }
+
+    #[test]
+    fn scrambles_have_the_right_length() {
+        for name in NAMES {
+            let puzzle = PuzzleType::from_name(name).unwrap();
+            for _ in 0..20 {
+                let scramble = main(default_length(&puzzle), &puzzle);
+                let turns = scramble.split(' ').filter(|x| axis_of(&puzzle, x.chars().next().unwrap()).is_some()).count();
+                assert_eq!(turns, default_length(&puzzle) as usize, "{name}: {scramble}");
+                assert!(scramble.split(' ').count() <= turns + 4, "{name}: {scramble}");
             }
         }
+    }
 
-        scramble += available_moves[x - 1][rand::thread_rng().gen_range(0..available_moves[0].len())];
+    #[test]
+    fn turns_in_a_row_never_share_an_axis() {
+        for name in NAMES {
+            let puzzle = PuzzleType::from_name(name).unwrap();
+            for _ in 0..20 {
+                let scramble = main(default_length(&puzzle), &puzzle);
+                let axes: Vec<usize> = scramble.split(' ').filter_map(|x| axis_of(&puzzle, x.chars().next().unwrap())).collect();
+                assert!(axes.windows(2).all(|x| x[0] != x[1]), "{name}: {scramble}");
+            }
+        }
     }
 
-    scramble[0..scramble.len() - 1].to_string()
-}
\ No newline at end of file
+    #[test]
+    fn order_three_puzzles_have_no_half_turns() {
+        for name in ["Skewb", "Pyraminx", "Ivy"] {
+            let puzzle = PuzzleType::from_name(name).unwrap();
+            let scramble = main(100, &puzzle);
+            assert!(!scramble.contains('2'), "{name}: {scramble}");
+            assert!(scramble.split(' ').all(|x| x.len() <= 2), "{name}: {scramble}");
+        }
+    }
+}
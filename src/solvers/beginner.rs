@@ -0,0 +1,258 @@
+use lazy_static::lazy_static;
+
+use crate::algorithms::{Move, Notation};
+use crate::algorithms::last_layer::PLL;
+use crate::algorithms::simplify::simplify;
+use crate::algorithms::transforms::{face_turns, rotate};
+use crate::models::Puzzle;
+use crate::models::rubiks_cube::RubiksCube;
+use crate::solvers::cubie::{allowed_after, move_name, CubieCube, MOVE_COUNT};
+use crate::solvers::stages::{Solution, Stage};
+
+/// Slots of `rubiks_cube::CORNERS` and `EDGES` in each layer. The white
+/// layer is U, and the yellow last layer D.
+const WHITE_CORNERS: [usize; 4] = [0, 1, 2, 3];
+const WHITE_EDGES: [usize; 4] = [0, 1, 2, 3];
+const YELLOW_CORNERS: [usize; 4] = [4, 5, 6, 7];
+const YELLOW_EDGES: [usize; 4] = [4, 5, 6, 7];
+const MIDDLE_EDGES: [usize; 4] = [8, 9, 10, 11];
+
+/// Nothing, D, D2 or D', to line the last layer up before and after an
+/// algorithm.
+const AUFS: [&[usize]; 4] = [&[], &[15], &[16], &[17]];
+
+/// The longest a cross edge takes to put in without moving the others.
+const CROSS_DEPTH: usize = 7;
+
+/// The algorithms of the method, written the usual way: with the white
+/// layer on D and the last layer on U.
+const CORNER_TRIGGER: &str = "R U R' U'";
+const RIGHT_INSERT: &str = "U R U' R' U' F' U F";
+const LEFT_INSERT: &str = "U' L' U L U F U' F'";
+const YELLOW_CROSS: &str = "F R U R' U' F'";
+const SUNE: &str = "R U R' U R U2 R'";
+const CORNER_PLLS: [&str; 1] = ["Aa"];
+const EDGE_PLLS: [&str; 2] = ["Ua", "Ub"];
+
+/// What each stage does, as the moves are shown: with the white layer on
+/// top and the yellow layer on the bottom, so the algorithms are the usual
+/// ones done upside down.
+const STAGES: [(&str, &str); 6] = [
+    ("Cross", "put the four white edges around the white centre on top, each matching the centre beside it"),
+    ("First layer corners", "bring each white corner to the bottom, under its slot, and repeat L D L' D' (from the side of the slot) until it goes in the right way round"),
+    ("Second layer", "bring each middle edge to the bottom, under the face of its colour, and insert it to the left or the right, e.g. D L D' L' D' F' D F"),
+    ("Yellow cross", "flip the yellow edges down with F L D L' D' F' until they make a cross on the bottom"),
+    ("OLL", "turn the yellow corners down with Sunes, L D L' D L D2 L', so the whole bottom face is yellow"),
+    ("PLL", "swap the yellow corners with A perms, then cycle the yellow edges with U perms, both done upside down")
+];
+
+/// `alg` as turns of the cube model, which cannot be turned over: done
+/// upside down (z2), so the last layer is D, and turned `quarters` times
+/// about the vertical axis, so it works on another slot.
+fn upside_down(alg: &str, quarters: i32) -> Vec<usize> {
+    let (moves, _) = face_turns(&Notation::Cube.parse(alg).unwrap()).unwrap();
+    let rotations = Notation::Cube.parse(&format!("z2 y{}", quarters)).unwrap();
+    simplify(&rotate(&moves, Notation::Cube, &rotations).unwrap()).iter()
+        .map(|x| (0..MOVE_COUNT).find(|&turn| move_name(turn) == x.to_string()).unwrap())
+        .collect()
+}
+
+fn pll(name: &str) -> Vec<usize> {
+    upside_down(PLL.iter().find(|x| x.0 == name).unwrap().1, 0)
+}
+
+lazy_static! {
+    /// The corner trigger done at each slot of the white layer, with the slot
+    /// it inserts the corner into.
+    static ref TRIGGERS: Vec<(usize, Vec<usize>)> = (0..4).map(|quarters| {
+        let turns = upside_down(CORNER_TRIGGER, quarters);
+        let cube = apply(&CubieCube::SOLVED, &turns);
+        let slot = WHITE_CORNERS.into_iter().find(|&x| cube.cp[x] as usize != x).unwrap();
+        (slot, turns)
+    }).collect();
+
+    /// Both second layer inserts done at each slot, with the slot each one
+    /// inserts into.
+    static ref INSERTS: Vec<(usize, Vec<usize>)> = [RIGHT_INSERT, LEFT_INSERT].iter().flat_map(|alg| (0..4).map(|quarters| {
+        let turns = upside_down(alg, quarters);
+        let cube = apply(&CubieCube::SOLVED, &turns);
+        let slot = MIDDLE_EDGES.into_iter().find(|&x| cube.ep[x] as usize != x).unwrap();
+        (slot, turns)
+    })).collect();
+}
+
+fn apply(cube: &CubieCube, turns: &[usize]) -> CubieCube {
+    turns.iter().fold(*cube, |cube, &turn| cube.apply(turn))
+}
+
+fn corner_solved(cube: &CubieCube, slot: usize) -> bool {
+    cube.cp[slot] as usize == slot && cube.co[slot] == 0
+}
+
+fn edge_solved(cube: &CubieCube, slot: usize) -> bool {
+    cube.ep[slot] as usize == slot && cube.eo[slot] == 0
+}
+
+fn first_layer_solved(cube: &CubieCube) -> bool {
+    WHITE_CORNERS.iter().all(|&x| corner_solved(cube, x)) && WHITE_EDGES.iter().all(|&x| edge_solved(cube, x))
+}
+
+/// The shortest turns after which `done` holds, up to `depth` of them.
+fn search(cube: &CubieCube, depth: usize, done: &dyn Fn(&CubieCube) -> bool, turns: &mut Vec<usize>) -> bool {
+    if depth == 0 {
+        return done(cube);
+    }
+    for turn in 0..MOVE_COUNT {
+        if allowed_after(turn, turns.last().map(|&x| x / 3)) {
+            turns.push(turn);
+            if search(&cube.apply(turn), depth - 1, done, turns) {
+                return true;
+            }
+            turns.pop();
+        }
+    }
+    false
+}
+
+/// The fewest of `algs` after which `done` holds, each done after turning D
+/// (or not) to line it up, and with a last turn of D if needed.
+fn algorithms(cube: &CubieCube, algs: &[Vec<usize>], most: usize, done: &dyn Fn(&CubieCube) -> bool) -> Option<Vec<usize>> {
+    let mut layer = vec![(*cube, Vec::new())];
+    for count in 0..=most {
+        for (cube, turns) in &layer {
+            if let Some(auf) = AUFS.iter().find(|auf| done(&apply(cube, auf))) {
+                return Some([turns.as_slice(), auf].concat());
+            }
+        }
+        if count < most {
+            layer = layer.iter().flat_map(|(cube, turns)| AUFS.iter().flat_map(move |auf| algs.iter().map(move |alg| {
+                (apply(&apply(cube, auf), alg), [turns.as_slice(), auf, alg].concat())
+            }))).collect();
+        }
+    }
+    None
+}
+
+fn cross(cube: &CubieCube) -> Result<Vec<usize>, String> {
+    let mut cube = *cube;
+    let mut moves = Vec::new();
+    for (i, &edge) in WHITE_EDGES.iter().enumerate() {
+        let done = |cube: &CubieCube| WHITE_EDGES[..=i].iter().all(|&x| edge_solved(cube, x));
+        let mut turns = Vec::new();
+        (0..=CROSS_DEPTH).find(|&depth| search(&cube, depth, &done, &mut turns))
+            .ok_or(format!("Could not put in the cross edge {}", move_name(edge * 3)))?;
+        cube = apply(&cube, &turns);
+        moves.extend(turns);
+    }
+    Ok(moves)
+}
+
+fn first_layer_corners(cube: &CubieCube) -> Result<Vec<usize>, String> {
+    let mut cube = *cube;
+    let mut moves = Vec::new();
+    for (slot, trigger) in TRIGGERS.iter() {
+        if corner_solved(&cube, *slot) {
+            continue;
+        }
+        // A white corner in the white layer but in the wrong slot, or
+        // twisted, comes out first.
+        let at = cube.cp.iter().position(|&x| x as usize == *slot).unwrap();
+        let mut turns: Vec<usize> = TRIGGERS.iter().find(|(x, _)| *x == at).map(|x| x.1.clone()).unwrap_or_default();
+
+        // Then it goes under its slot, and the trigger is repeated until it
+        // goes in.
+        let goes_in = |cube: &CubieCube| (1..6).any(|times| corner_solved(&apply(cube, &trigger.repeat(times)), *slot));
+        let auf = AUFS.iter().find(|auf| goes_in(&apply(&apply(&cube, &turns), auf))).ok_or("Could not bring a white corner under its slot".to_string())?;
+        turns.extend(*auf);
+        cube = apply(&cube, &turns);
+        while !corner_solved(&cube, *slot) {
+            turns.extend(trigger);
+            cube = apply(&cube, trigger);
+        }
+        moves.extend(turns);
+    }
+    Ok(moves)
+}
+
+fn second_layer(cube: &CubieCube) -> Result<Vec<usize>, String> {
+    let mut cube = *cube;
+    let mut moves = Vec::new();
+    let algs: Vec<Vec<usize>> = INSERTS.iter().map(|x| x.1.clone()).collect();
+    for (i, &edge) in MIDDLE_EDGES.iter().enumerate() {
+        let mut turns = Vec::new();
+        // A middle edge in the wrong slot, or flipped, comes out first.
+        let at = cube.ep.iter().position(|&x| x as usize == edge).unwrap();
+        if let Some((_, out)) = INSERTS.iter().find(|(x, _)| *x == at && !edge_solved(&cube, at)) {
+            turns.extend(out);
+        }
+        let done = |cube: &CubieCube| first_layer_solved(cube) && MIDDLE_EDGES[..=i].iter().all(|&x| edge_solved(cube, x));
+        turns.extend(algorithms(&apply(&cube, &turns), &algs, 1, &done).ok_or("Could not insert a middle edge".to_string())?);
+        cube = apply(&cube, &turns);
+        moves.extend(turns);
+    }
+    Ok(moves)
+}
+
+/// Solves the cube layer by layer the way it is usually taught, one stage
+/// at a time. The cube model cannot be turned over, so the white layer is
+/// built on U and the last layer solved on D, with the usual algorithms
+/// done upside down.
+pub fn solve(cube: &RubiksCube) -> Result<Solution, String> {
+    cube.validate()?;
+    let mut state = CubieCube::from_cube(cube)?;
+
+    let edges_up = |cube: &CubieCube| first_layer_solved(cube) && MIDDLE_EDGES.iter().all(|&x| edge_solved(cube, x))
+        && YELLOW_EDGES.iter().all(|&x| cube.eo[x] == 0);
+    let corners_up = |cube: &CubieCube| edges_up(cube) && YELLOW_CORNERS.iter().all(|&x| cube.co[x] == 0);
+    let corners_solved = |cube: &CubieCube| corners_up(cube) && YELLOW_CORNERS.iter().all(|&x| cube.cp[x] as usize == x);
+    let solved = |cube: &CubieCube| *cube == CubieCube::SOLVED;
+    let corner_plls: Vec<Vec<usize>> = CORNER_PLLS.iter().map(|x| pll(x)).collect();
+    let edge_plls: Vec<Vec<usize>> = EDGE_PLLS.iter().map(|x| pll(x)).collect();
+
+    let mut stages = Vec::new();
+    for (stage, &(name, goal)) in STAGES.iter().enumerate() {
+        let turns = match stage {
+            0 => cross(&state)?,
+            1 => first_layer_corners(&state)?,
+            2 => second_layer(&state)?,
+            3 => algorithms(&state, &[upside_down(YELLOW_CROSS, 0)], 3, &edges_up).ok_or("Could not make the yellow cross".to_string())?,
+            4 => algorithms(&state, &[upside_down(SUNE, 0)], 3, &corners_up).ok_or("Could not orient the yellow corners".to_string())?,
+            _ => {
+                let mut turns = algorithms(&state, &corner_plls, 2, &corners_solved).ok_or("Could not permute the yellow corners".to_string())?;
+                turns.extend(algorithms(&apply(&state, &turns), &edge_plls, 2, &solved).ok_or("Could not permute the yellow edges".to_string())?);
+                turns
+            }
+        };
+        state = apply(&state, &turns);
+        let moves: Vec<Move> = turns.iter().map(|&x| Notation::Cube.parse_move(&move_name(x)).unwrap()).collect();
+        stages.push(Stage { name, goal, moves: simplify(&moves) });
+    }
+    Ok(Solution { stages })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::format;
+    use crate::solvers::tests::SCRAMBLES;
+
+    #[test]
+    fn solves() {
+        for scramble in SCRAMBLES {
+            let mut cube = RubiksCube::default();
+            cube.input_moves(scramble);
+            let solution = solve(&cube).unwrap();
+            assert_eq!(solution.stages.len(), STAGES.len());
+            cube.input_moves(&format(&solution.moves()));
+            assert!(cube.is_solved(), "{scramble}");
+        }
+    }
+
+    #[test]
+    fn goals_show_the_moves_done() {
+        let written = |alg| format(&upside_down(alg, 0).iter().map(|&x| Notation::Cube.parse_move(&move_name(x)).unwrap()).collect::<Vec<Move>>());
+        for (stage, alg) in [(1, CORNER_TRIGGER), (2, RIGHT_INSERT), (3, YELLOW_CROSS), (4, SUNE)] {
+            assert!(STAGES[stage].1.contains(&written(alg)), "{}", STAGES[stage].0);
+        }
+    }
+}
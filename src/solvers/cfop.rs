@@ -0,0 +1,218 @@
+use std::fmt;
+
+use crate::algorithms::{format, Move};
+use crate::colour_scheme;
+use crate::models::Puzzle;
+use crate::models::rubiks_cube::{RubiksCube, CORNERS, EDGES, FACE_LETTERS};
+use crate::solvers::slice_cube::{SliceCube, Turn};
+use crate::solvers::steps::{search, Piece, Table};
+
+/// The F2L slot a corner of `face` goes in: the corner and the edge between
+/// its other two faces.
+struct Slot {
+    corner: usize,
+    edge: usize
+}
+
+impl Slot {
+    fn solved(&self, cube: &SliceCube) -> bool {
+        let cube = &cube.cube;
+        cube.cp[self.corner] as usize == self.corner && cube.co[self.corner] == 0
+            && cube.ep[self.edge] as usize == self.edge && cube.eo[self.edge] == 0
+    }
+}
+
+fn cross_edges(face: usize) -> Vec<usize> {
+    (0..12).filter(|&x| EDGES[x].iter().any(|y| y.0 == face)).collect()
+}
+
+fn slots(face: usize) -> Vec<Slot> {
+    (0..8).filter(|&x| CORNERS[x].iter().any(|y| y.0 == face)).map(|corner| {
+        let sides: Vec<usize> = CORNERS[corner].iter().map(|x| x.0).filter(|&x| x != face).collect();
+        let edge = (0..12).find(|&x| EDGES[x].iter().all(|y| sides.contains(&y.0))).unwrap();
+        Slot { corner, edge }
+    }).collect()
+}
+
+/// Whether the cross is solved, which after a slice move also needs the
+/// centres to be back where they started.
+fn cross_solved(face: usize, cube: &SliceCube) -> bool {
+    cube.centred() && cross_edges(face).iter().all(|&x| cube.cube.ep[x] as usize == x && cube.cube.eo[x] == 0)
+}
+
+/// The shortest ways of doing one step with one colour as the cross.
+pub struct Step {
+    pub face: usize,
+    /// The F2L slot, named after its edge, for an XCross or a pair.
+    pub slot: Option<String>,
+    pub solutions: Vec<Vec<Move>>
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", colour_scheme::current().cube[self.face].name)?;
+        if let Some(slot) = &self.slot {
+            write!(f, ", {slot} slot")?;
+        }
+        let length = self.solutions[0].len();
+        if length == 0 {
+            return write!(f, ": already solved");
+        }
+        let plural = if self.solutions.len() == 1 { "" } else { "s" };
+        write!(f, ": {} solution{plural} of {length} moves", self.solutions.len())?;
+        for solution in &self.solutions {
+            write!(f, "\n  {}", format(solution))?;
+        }
+        Ok(())
+    }
+}
+
+fn slot_name(slot: &Slot) -> String {
+    crate::models::slot_name(&EDGES[slot.edge], &FACE_LETTERS)
+}
+
+/// Every shortest cross on each of the six colours, using only `turns`.
+/// Colours with no cross within `steps::MAX_DEPTH` moves are left out.
+pub fn crosses(cube: &RubiksCube, turns: &[Turn]) -> Result<Vec<Step>, String> {
+    cube.validate()?;
+    let state = SliceCube::from_cube(cube)?;
+    Ok((0..6).filter_map(|face| {
+        let edges = Table::new(cross_edges(face).into_iter().map(Piece::Edge).collect(), turns);
+        let solutions = search(&state, turns, &|cube| edges.distance(cube), &|cube| cross_solved(face, cube));
+        (!solutions.is_empty()).then_some(Step { face, slot: None, solutions })
+    }).collect())
+}
+
+/// Tables for a cross and one slot: the cross edges, and the slot's corner
+/// and edge with each half of the cross. Together they rarely let a search
+/// stray more than a move or two from where the pair goes in.
+fn pair_tables(face: usize, slot: &Slot, turns: &[Turn]) -> [Table; 2] {
+    let (near, far): (Vec<usize>, Vec<usize>) = cross_edges(face).into_iter()
+        .partition(|&x| EDGES[x].iter().any(|y| EDGES[slot.edge].iter().any(|z| z.0 == y.0)));
+    [near, far].map(|edges| {
+        let pieces = [Piece::Corner(slot.corner), Piece::Edge(slot.edge)].into_iter().chain(edges.into_iter().map(Piece::Edge));
+        Table::new(pieces.collect(), turns)
+    })
+}
+
+/// The shortest ways to solve each colour's cross along with one F2L pair,
+/// and which pair, using only `turns`. Every colour needs its own tables,
+/// so this takes a few seconds in a release build.
+pub fn xcrosses(cube: &RubiksCube, turns: &[Turn]) -> Result<Vec<Step>, String> {
+    cube.validate()?;
+    let state = SliceCube::from_cube(cube)?;
+    let mut steps = Vec::new();
+    for face in 0..6 {
+        let edges = Table::new(cross_edges(face).into_iter().map(Piece::Edge).collect(), turns);
+        let slots = slots(face);
+        let tables: Vec<[Table; 2]> = slots.iter().map(|slot| pair_tables(face, slot, turns)).collect();
+        let bound = |cube: &SliceCube| edges.distance(cube).max(tables.iter().map(|[a, b]| a.distance(cube).max(b.distance(cube))).min().unwrap());
+        let done = |cube: &SliceCube| cross_solved(face, cube) && slots.iter().any(|x| x.solved(cube));
+
+        let solutions = search(&state, turns, &bound, &done);
+        for slot in &slots {
+            let solved: Vec<Vec<Move>> = solutions.iter().filter(|x| state.apply(x).is_ok_and(|cube| slot.solved(&cube))).cloned().collect();
+            if !solved.is_empty() {
+                steps.push(Step { face, slot: Some(slot_name(slot)), solutions: solved });
+            }
+        }
+    }
+    Ok(steps)
+}
+
+/// The shortest ways to solve each F2L pair that is left, on every colour
+/// whose cross is solved, using only `turns` and keeping the cross and the
+/// pairs already solved in place.
+pub fn pairs(cube: &RubiksCube, turns: &[Turn]) -> Result<Vec<Step>, String> {
+    cube.validate()?;
+    let state = SliceCube::from_cube(cube)?;
+    let faces: Vec<usize> = (0..6).filter(|&face| cross_solved(face, &state)).collect();
+    if faces.is_empty() {
+        return Err("There is no solved cross to solve pairs on".to_string());
+    }
+
+    let mut steps = Vec::new();
+    for face in faces {
+        let edges = Table::new(cross_edges(face).into_iter().map(Piece::Edge).collect(), turns);
+        let slots = slots(face);
+        let solved: Vec<&Slot> = slots.iter().filter(|x| x.solved(&state)).collect();
+        for slot in slots.iter().filter(|x| !x.solved(&state)) {
+            let [near, far] = pair_tables(face, slot, turns);
+            let bound = |cube: &SliceCube| edges.distance(cube).max(near.distance(cube)).max(far.distance(cube));
+            let done = |cube: &SliceCube| cross_solved(face, cube) && slot.solved(cube) && solved.iter().all(|x| x.solved(cube));
+            let solutions = search(&state, turns, &bound, &done);
+            if !solutions.is_empty() {
+                steps.push(Step { face, slot: Some(slot_name(slot)), solutions });
+            }
+        }
+    }
+    Ok(steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solvers::steps::move_set;
+    use crate::solvers::tests::SCRAMBLES;
+
+    fn scrambled(moves: &str) -> RubiksCube {
+        let mut cube = RubiksCube::default();
+        cube.input_moves(moves);
+        cube
+    }
+
+    /// Every solution of every step, each the same length as the others in
+    /// its step, leaves the cube where `done` holds.
+    fn check(cube: &RubiksCube, steps: &[Step], done: impl Fn(&Step, &SliceCube) -> bool) {
+        let state = SliceCube::from_cube(cube).unwrap();
+        for step in steps {
+            for solution in &step.solutions {
+                assert_eq!(solution.len(), step.solutions[0].len(), "{step}");
+                assert!(done(step, &state.apply(solution).unwrap()), "{step}");
+            }
+        }
+    }
+
+    fn slot(step: &Step) -> Slot {
+        slots(step.face).into_iter().find(|x| Some(slot_name(x)) == step.slot).unwrap()
+    }
+
+    #[test]
+    fn crosses_are_solved_on_every_colour() {
+        let cube = scrambled(SCRAMBLES[0]);
+        let steps = crosses(&cube, &move_set("").unwrap()).unwrap();
+        assert_eq!(steps.len(), 6);
+        assert!(steps.iter().all(|x| x.solutions[0].len() <= 8));
+        check(&cube, &steps, |step, cube| cross_solved(step.face, cube));
+
+        // Fewer turns can only make a cross longer.
+        let restricted = crosses(&cube, &move_set("no B").unwrap()).unwrap();
+        check(&cube, &restricted, |step, cube| cross_solved(step.face, cube));
+        for step in &restricted {
+            let all = steps.iter().find(|x| x.face == step.face).unwrap();
+            assert!(step.solutions[0].len() >= all.solutions[0].len(), "{step}");
+        }
+    }
+
+    #[test]
+    fn xcrosses_solve_the_cross_and_their_slot() {
+        let cube = scrambled("R U R' F2 D L'");
+        let steps = xcrosses(&cube, &move_set("").unwrap()).unwrap();
+        assert!(!steps.is_empty());
+        check(&cube, &steps, |step, cube| cross_solved(step.face, cube) && slot(step).solved(cube));
+    }
+
+    #[test]
+    fn pairs_keep_the_cross_and_solved_pairs() {
+        let cube = scrambled("R U R' U' L' U2 L");
+        let steps = pairs(&cube, &move_set("").unwrap()).unwrap();
+        let state = SliceCube::from_cube(&cube).unwrap();
+        assert!(steps.iter().any(|x| x.face == 5));
+        check(&cube, &steps, |step, cube| {
+            let kept = slots(step.face).iter().filter(|x| x.solved(&state)).all(|x| x.solved(cube));
+            cross_solved(step.face, cube) && slot(step).solved(cube) && kept
+        });
+
+        assert!(pairs(&scrambled(SCRAMBLES[0]), &move_set("").unwrap()).is_err());
+    }
+}
@@ -1,101 +1,417 @@
-// Link to Corner Perms
-// Link to Edge Perms
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+use serde::{Deserialize, Serialize};
 
-use std::cmp;
-use priority_queue::PriorityQueue;
-use main::PuzzleType;
+use crate::algorithms::{Move, Notation};
+use crate::db_generators::pattern_database::PatternDatabase;
+use crate::models::Puzzle;
+use crate::models::rubiks_cube::RubiksCube;
+use crate::solvers::cubie::{allowed_after, face, move_name, move_table, CubieCube, MOVE_COUNT, MOVES};
 
-fn movesTillCornersComplete(cube:PuzzleType) -> u32 {
-    // open the corner perms and return int(m[PermutationIndexer(cube.corners, cube.cornerOrientations)])
-    0
+pub const CORNER_PATH: &str = "korf_corners.pdb";
+pub const EDGE_PATHS: [&str; 2] = ["korf_edges_1.pdb", "korf_edges_2.pdb"];
+/// Where an unfinished search is saved so that it can be picked up again.
+pub const PROGRESS_PATH: &str = "korf_progress.json";
+
+const CORNER_PERMUTATIONS: usize = 40320;
+const TWISTS: usize = 2187;
+/// 8! * 3^7 corner states.
+const CORNER_STATES: usize = CORNER_PERMUTATIONS * TWISTS;
+/// Where six edges can be (12 * 11 * 10 * 9 * 8 * 7) times how they can be flipped.
+const EDGE_STATES: usize = 665280 * 64;
+
+/// The six edges each edge database follows: UR, UF, UL, UB, DR, DF and
+/// DL, DB, FR, FL, BL, BR.
+const EDGE_GROUPS: [[u8; 6]; 2] = [[0, 1, 2, 3, 4, 5], [6, 7, 8, 9, 10, 11]];
+
+/// Where six tracked edges are and how they are flipped.
+#[derive(Clone, Copy)]
+struct EdgeGroup {
+    slots: [u8; 6],
+    flips: [u8; 6]
+}
+
+impl EdgeGroup {
+    fn of(cube: &CubieCube, edges: [u8; 6]) -> Self {
+        let mut group = Self { slots: [0; 6], flips: [0; 6] };
+        for slot in 0..12 {
+            if let Some(i) = edges.iter().position(|&x| x == cube.ep[slot]) {
+                group.slots[i] = slot as u8;
+                group.flips[i] = cube.eo[slot];
+            }
+        }
+        group
+    }
+
+    fn index(&self) -> usize {
+        let mut rank = 0;
+        for i in 0..6 {
+            let earlier = self.slots[..i].iter().filter(|&&x| x < self.slots[i]).count();
+            rank = rank * (12 - i) + self.slots[i] as usize - earlier;
+        }
+        let flips = self.flips.iter().fold(0, |result, &x| result * 2 + x as usize);
+        rank * 64 + flips
+    }
+
+    fn from_index(index: usize) -> Self {
+        let (mut rank, mut flips) = (index / 64, index % 64);
+        let mut group = Self { slots: [0; 6], flips: [0; 6] };
+
+        let mut digits = [0; 6];
+        for i in (0..6).rev() {
+            digits[i] = rank % (12 - i);
+            rank /= 12 - i;
+            group.flips[i] = (flips % 2) as u8;
+            flips /= 2;
+        }
+
+        let mut unused: Vec<u8> = (0..12).collect();
+        for (slot, digit) in group.slots.iter_mut().zip(digits) {
+            *slot = unused.remove(digit);
+        }
+        group
+    }
+
+    fn apply(&self, turn: usize, edge_moves: &EdgeMoves) -> Self {
+        let mut group = *self;
+        for i in 0..6 {
+            let (slot, flip) = edge_moves[turn][self.slots[i] as usize];
+            group.slots[i] = slot;
+            group.flips[i] ^= flip;
+        }
+        group
+    }
 }
 
-fn movesTillEdgesComplete(cube:PuzzleType) -> u32 {
-    0
+/// For each move and each edge slot, where the edge in that slot goes and
+/// whether it is flipped on the way.
+type EdgeMoves = [[(u8, u8); 12]; MOVE_COUNT];
+
+fn edge_moves() -> EdgeMoves {
+    let mut table = [[(0, 0); 12]; MOVE_COUNT];
+    for (turn, cube) in MOVES.iter().enumerate() {
+        for slot in 0..12 {
+            table[turn][cube.ep[slot] as usize] = (slot as u8, cube.eo[slot]);
+        }
+    }
+    table
 }
 
-fn h(cube:PuzzleType) -> u16 {
-    cmp::max(movesTillCornersComplete(cube), movesTillEdgesComplete(cube))
+struct Databases {
+    corner_moves: Vec<[u16; MOVE_COUNT]>,
+    twist_moves: Vec<[u16; MOVE_COUNT]>,
+    edge_moves: EdgeMoves,
+    corners: PatternDatabase,
+    edges: [PatternDatabase; 2]
 }
 
-const POSSIBLE_MOVES: [[&str;3];6] = [["U ", "U' ", "U2 "],["D ", "D' ", "D2 "],["R ", "R' ", "R2 "],["L ", "L' ", "L2 "],["F ", "F' ", "F2 "],["B ", "B' ", "B2 "]];
+static DATABASES: OnceLock<Databases> = OnceLock::new();
+
+impl Databases {
+    /// Reads the pattern databases from the working directory, generating any
+    /// that are missing. Generating them all takes a few minutes and about
+    /// 90MB of disk; use a release build.
+    fn load() -> Result<Self, String> {
+        let all: [usize; MOVE_COUNT] = std::array::from_fn(|i| i);
+        let corner_moves = move_table(CORNER_PERMUTATIONS, all, CubieCube::set_corners, CubieCube::corners);
+        let twist_moves = move_table(TWISTS, all, CubieCube::set_twist, CubieCube::twist);
+        let edge_moves = edge_moves();
+
+        let corners = PatternDatabase::load_or_generate(Path::new(CORNER_PATH), CORNER_STATES, &[0], |index, next| {
+            let (permutation, twist) = (index / TWISTS, index % TWISTS);
+            next.extend((0..MOVE_COUNT).map(|turn| corner_moves[permutation][turn] as usize * TWISTS + twist_moves[twist][turn] as usize));
+        })?;
+
+        let edge_database = |i: usize| {
+            let solved = EdgeGroup::of(&CubieCube::SOLVED, EDGE_GROUPS[i]).index();
+            PatternDatabase::load_or_generate(Path::new(EDGE_PATHS[i]), EDGE_STATES, &[solved], |index, next| {
+                let edges = EdgeGroup::from_index(index);
+                next.extend((0..MOVE_COUNT).map(|turn| edges.apply(turn, &edge_moves).index()));
+            })
+        };
+        let edges = [edge_database(0)?, edge_database(1)?];
+
+        Ok(Self { corner_moves, twist_moves, edge_moves, corners, edges })
+    }
 
+    fn get() -> Result<&'static Self, String> {
+        if let Some(databases) = DATABASES.get() {
+            return Ok(databases);
+        }
+        let databases = Self::load()?;
+        Ok(DATABASES.get_or_init(|| databases))
+    }
+}
+
+/// The parts of the cube the databases look at.
+#[derive(Clone, Copy)]
 struct Node {
-    cube: PuzzleType,
-    current_move: String,
-    depth: u16
+    corners: u16,
+    twist: u16,
+    edges: [EdgeGroup; 2]
 }
 
 impl Node {
-    fn new(cube:PuzzleType, current_move:String, depth:u16) -> Self {
+    fn of(cube: &CubieCube) -> Self {
+        Self {
+            corners: cube.corners() as u16,
+            twist: cube.twist() as u16,
+            edges: EDGE_GROUPS.map(|group| EdgeGroup::of(cube, group))
+        }
+    }
+
+    fn apply(&self, turn: usize, databases: &Databases) -> Self {
         Self {
-            cube: cube,
-            current_move: current_move,
-            depth: depth
+            corners: databases.corner_moves[self.corners as usize][turn],
+            twist: databases.twist_moves[self.twist as usize][turn],
+            edges: self.edges.map(|x| x.apply(turn, &databases.edge_moves))
         }
     }
+
+    /// The fewest moves that could solve the cube. It is only 0 when the
+    /// corners and both halves of the edges are solved, i.e. the whole cube.
+    fn estimate(&self, databases: &Databases) -> u8 {
+        let corners = databases.corners.get(self.corners as usize * TWISTS + self.twist as usize);
+        let edges = (0..2).map(|i| databases.edges[i].get(self.edges[i].index()));
+        edges.fold(corners, u8::max)
+    }
 }
 
-fn get_opposite_layer(layer: char) -> Option<char> {
-    match layer {
-        'U' => Some('D'),
-        'D' => Some('U'),
-        'R' => Some('L'),
-        'L' => Some('R'),
-        'F' => Some('B'),
-        'B' => Some('F'),
-        _ => None,
+/// How far a search has got: every depth before `depth` has been searched
+/// in full, and so have the first `branch` openings at `depth`.
+#[derive(Serialize, Deserialize)]
+pub struct Progress {
+    pub facelets: String,
+    pub depth: usize,
+    pub branch: usize,
+    /// Nodes expanded at each depth so far.
+    pub nodes: Vec<u64>
+}
+
+impl Progress {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+        serde_json::from_str(&contents).map_err(|e| format!("{} is not a saved search: {e}", path.display()))
+    }
+
+    fn save(&self, path: &Path) -> Result<(), String> {
+        let contents = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, contents).map_err(|e| format!("Could not write {}: {e}", temporary.display()))?;
+        fs::rename(&temporary, path).map_err(|e| format!("Could not write {}: {e}", path.display()))
     }
 }
 
-fn prune(new_move: &str, last_move: &str) -> bool {
-    let new_move_char = new_move.chars().next();
-    [Some(last_move.chars().next()), Some(get_opposite_layer(new_move_char.unwrap()))].contains(&Some(new_move_char))
+pub struct Solution {
+    pub moves: Vec<Move>,
+    pub nodes: Vec<u64>
+}
+
+struct Search<'a> {
+    databases: &'a Databases,
+    moves: Vec<usize>,
+    nodes: u64
 }
 
-pub fn IDAStar(cube:PuzzleType) {
-    let mut node_stack = Vec::new();
-    let mut moves: [&str; 21];
-    let mut solved = false;
-    let mut next_bound: u16 = h(cube);
-    let mut bound = 0;
-
-    while !solved {
-        if node_stack.len() == 0 {
-            node_stack.push(Node::new(cube, "", 0));
-            bound = next_bound.clone();
-            next_bound = std::f64::INFINITY;
+impl Search<'_> {
+    /// Depth first search for a solution of exactly `left` more moves.
+    fn search(&mut self, node: Node, left: usize) -> bool {
+        self.nodes += 1;
+        let estimate = node.estimate(self.databases) as usize;
+        if estimate > left {
+            return false;
         }
-        let mut current_node: Node = node_stack.pop();
-        if current_node.depth > 0 {
-            moves[current_node.depth - 1] = current_node.current_move;
+        if left == 0 {
+            return estimate == 0;
         }
-        moves[current_node.depth] = "";
 
-        if current_node.depth == bound {
-            if current_node.cube.is_solved() {
-                solved = true;
-                return moves.join(" ")
+        let last = self.moves.last().map(|&x| face(x));
+        for turn in (0..MOVE_COUNT).filter(|&x| allowed_after(x, last)) {
+            self.moves.push(turn);
+            if self.search(node.apply(turn, self.databases), left - 1) {
+                return true;
             }
-        } else {
-            let mut successors = PriorityQueue::new();
-            for x in POSSIBLE_MOVES {
-                for y in x {
-                    if current_node.depth == 0 || !prune(y, current_node.current_move.as_str()) {
-                        let mut new_cube = current_node.cube.clone();
-                        new_cube.input_scramble(y);
-                        let estimated_moves = current_node.depth + h(new_cube);
-                        if estimated_moves <= bound {
-                            successors.push([new_cube, y], estimated_moves)
-                        } else if estimated_moves < next_bound {
-                            next_bound = estimated_moves;
-                        }
-                    }
+            self.moves.pop();
+        }
+        false
+    }
+}
+
+/// The first one or two moves of every solution searched at `depth`. Each
+/// opening is searched in one go, and progress is saved between them.
+fn openings(depth: usize) -> Vec<Vec<usize>> {
+    let mut openings = vec![Vec::new()];
+    for _ in 0..depth.min(2) {
+        openings = openings.into_iter().flat_map(|opening: Vec<usize>| {
+            let last = opening.last().map(|&x| face(x));
+            (0..MOVE_COUNT).filter(move |&x| allowed_after(x, last)).map(move |x| [opening.clone(), vec![x]].concat())
+        }).collect();
+    }
+    openings
+}
+
+/// Korf's optimal solver: IDA* with the largest of three pattern databases
+/// as the heuristic. Each one gives the exact number of moves needed to solve
+/// part of the cube (all the corners, or six of the edges), which is never
+/// more than solving the whole cube needs, so the first solution found is as
+/// short as possible.
+///
+/// Every depth is searched in turn. After each opening the progress is saved
+/// to `checkpoint` (if given), and when `resume` is set a saved search of the
+/// same cube carries on from there. `report` is told the nodes expanded at
+/// each depth once it is finished.
+pub fn solve(cube: &RubiksCube, checkpoint: Option<&Path>, resume: bool, mut report: impl FnMut(usize, u64)) -> Result<Solution, String> {
+    cube.validate()?;
+    let facelets = cube.to_facelets();
+
+    // Checked before the databases are loaded, which takes a while.
+    let saved = checkpoint.filter(|x| resume && x.exists()).map(Progress::load).transpose()?;
+    if saved.as_ref().is_some_and(|x| x.facelets != facelets) {
+        return Err("The saved search is for a different cube".to_string());
+    }
+
+    let databases = Databases::get()?;
+    let start = Node::of(&CubieCube::from_cube(cube)?);
+    let mut progress = saved.unwrap_or(Progress { facelets, depth: start.estimate(databases) as usize, branch: 0, nodes: Vec::new() });
+
+    let mut search = Search { databases, moves: Vec::new(), nodes: 0 };
+    loop {
+        progress.nodes.resize(progress.depth + 1, 0);
+
+        for opening in openings(progress.depth).into_iter().skip(progress.branch) {
+            let node = opening.iter().fold(start, |node, &turn| node.apply(turn, databases));
+            search.moves = opening.clone();
+            search.nodes = opening.len() as u64;
+
+            let found = search.search(node, progress.depth - opening.len());
+            progress.nodes[progress.depth] += search.nodes;
+
+            if found {
+                report(progress.depth, progress.nodes[progress.depth]);
+                if let Some(path) = checkpoint.filter(|x| x.exists()) {
+                    fs::remove_file(path).map_err(|e| format!("Could not remove {}: {e}", path.display()))?;
                 }
+                let moves = search.moves.iter().map(|&x| Notation::Cube.parse_move(&move_name(x)).unwrap()).collect();
+                return Ok(Solution { moves, nodes: progress.nodes });
             }
-            for (item, _) in successors.iter() {
-                node_stack.push(Node::new(item[0], item[1], current_node.depth + 1))
+
+            progress.branch += 1;
+            if let Some(path) = checkpoint {
+                progress.save(path)?;
+            }
+        }
+
+        report(progress.depth, progress.nodes[progress.depth]);
+        progress.depth += 1;
+        progress.branch = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use crate::algorithms::format;
+
+    fn random_cube() -> CubieCube {
+        let mut rng = rand::thread_rng();
+        (0..30).fold(CubieCube::SOLVED, |cube, _| cube.apply(rng.gen_range(0..MOVE_COUNT)))
+    }
+
+    #[test]
+    fn edge_groups_round_trip() {
+        let edge_moves = edge_moves();
+        for _ in 0..100 {
+            let cube = random_cube();
+            for group in EDGE_GROUPS {
+                let edges = EdgeGroup::of(&cube, group);
+                let read = EdgeGroup::from_index(edges.index());
+                assert_eq!((read.slots, read.flips), (edges.slots, edges.flips));
+                assert!(edges.index() < EDGE_STATES);
+
+                for turn in 0..MOVE_COUNT {
+                    assert_eq!(edges.apply(turn, &edge_moves).index(), EdgeGroup::of(&cube.apply(turn), group).index());
+                }
             }
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn openings_leave_out_redundant_pairs() {
+        assert_eq!(openings(0), vec![Vec::<usize>::new()]);
+        assert_eq!(openings(1).len(), 18);
+        // 18 moves then 15 on other faces, less one order of the 9 pairs of
+        // turns on opposite faces about each axis. Never more than two moves.
+        assert_eq!(openings(2).len(), 18 * 15 - 27);
+        assert_eq!(openings(5).len(), openings(2).len());
+    }
+
+    fn scrambled(scramble: &str) -> RubiksCube {
+        let mut cube = RubiksCube::default();
+        cube.input_moves(scramble);
+        cube
+    }
+
+    fn checkpoint(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("rubiks-rust-{}-{name}.json", std::process::id()))
+    }
+
+    #[test]
+    fn progress_is_saved_and_loaded() {
+        let path = checkpoint("progress");
+        let progress = Progress { facelets: scrambled("R U").to_facelets(), depth: 7, branch: 42, nodes: vec![1, 18, 243] };
+        progress.save(&path).unwrap();
+        let loaded = Progress::load(&path).unwrap();
+        assert_eq!((loaded.facelets, loaded.depth, loaded.branch, loaded.nodes), (progress.facelets, 7, 42, vec![1, 18, 243]));
+        assert!(!path.with_extension("tmp").exists());
+
+        fs::write(&path, "not json").unwrap();
+        assert!(Progress::load(&path).is_err());
+        fs::remove_file(&path).unwrap();
+        assert!(Progress::load(&path).is_err());
+    }
+
+    #[test]
+    fn saved_searches_are_for_one_cube() {
+        let path = checkpoint("other-cube");
+        Progress { facelets: scrambled("R U").to_facelets(), depth: 3, branch: 0, nodes: Vec::new() }.save(&path).unwrap();
+        // Fails before the databases would be generated.
+        let error = solve(&scrambled("F D"), Some(&path), true, |_, _| {}).err();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(error.as_deref(), Some("The saved search is for a different cube"));
+    }
+
+    #[test]
+    #[ignore = "generates about 90MB of pattern databases in the working directory the first time"]
+    fn solves_optimally() {
+        for (scramble, length) in [("R U F' L2", 4), ("R U R' U' R' F R2 U' R' U' R U R' F'", 14)] {
+            let mut cube = scrambled(scramble);
+            let solution = solve(&cube, None, false, |_, _| {}).unwrap();
+            assert!(solution.moves.len() <= length, "{scramble}");
+            cube.input_moves(&format(&solution.moves));
+            assert!(cube.is_solved(), "{scramble}");
+        }
+    }
+
+    #[test]
+    #[ignore = "generates about 90MB of pattern databases in the working directory the first time"]
+    fn resumes_from_a_saved_search() {
+        // Part way through depth 4, a few openings before the one that
+        // leads to the only solution.
+        let mut cube = scrambled("R U F' L2");
+        let names = |opening: &Vec<usize>| opening.iter().map(|&x| move_name(x)).collect::<Vec<_>>().join(" ");
+        let branch = openings(4).iter().position(|x| names(x) == "L2 F").unwrap() - 5;
+        let path = checkpoint("resume");
+        Progress { facelets: cube.to_facelets(), depth: 4, branch, nodes: vec![0, 0, 0, 0, 1000] }.save(&path).unwrap();
+
+        let mut depths = Vec::new();
+        let solution = solve(&cube, Some(&path), true, |depth, _| depths.push(depth)).unwrap();
+        assert_eq!(depths, vec![4]);
+        assert_eq!(format(&solution.moves), "L2 F U' R'");
+        assert!(solution.nodes[4] > 1000);
+        assert!(!path.exists());
+        cube.input_moves(&format(&solution.moves));
+        assert!(cube.is_solved());
+    }
+}
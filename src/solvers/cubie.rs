@@ -0,0 +1,294 @@
+use lazy_static::lazy_static;
+
+use crate::models::permutation_parity;
+use crate::models::rubiks_cube::{RubiksCube, FACE_LETTERS};
+
+/// The 3x3 as pieces rather than stickers, in the slot order of
+/// `rubiks_cube::CORNERS` and `EDGES`: `cp[slot]` is the corner in `slot`
+/// and `co[slot]` how far it is twisted, and the same for the edges.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CubieCube {
+    pub cp: [u8; 8],
+    pub co: [u8; 8],
+    pub ep: [u8; 12],
+    pub eo: [u8; 12]
+}
+
+/// The 18 face turns, numbered face by face in the model's order (U, F, R,
+/// B, L, D) as a quarter turn, a half turn and an anticlockwise turn.
+pub const MOVE_COUNT: usize = 18;
+
+lazy_static! {
+    /// Each face turn as a `CubieCube`, read off the sticker model so the two
+    /// always agree.
+    pub static ref MOVES: [CubieCube; MOVE_COUNT] = {
+        let mut moves = [CubieCube::SOLVED; MOVE_COUNT];
+        for (i, turn) in moves.iter_mut().enumerate() {
+            let mut cube = RubiksCube::default();
+            cube.input_moves(&move_name(i));
+            *turn = CubieCube::from_cube(&cube).unwrap();
+        }
+        moves
+    };
+}
+
+pub fn move_name(turn: usize) -> String {
+    format!("{}{}", FACE_LETTERS[turn / 3], ["", "2", "'"][turn % 3])
+}
+
+pub fn face(turn: usize) -> usize {
+    turn / 3
+}
+
+/// Whether `turn` may follow a turn of `last`. Turning the same face twice in
+/// a row is never needed, and of two opposite faces (which commute) only one
+/// order is searched.
+pub fn allowed_after(turn: usize, last: Option<usize>) -> bool {
+    const AXES: [usize; 6] = [0, 1, 2, 1, 2, 0];
+    match last {
+        Some(last) => face(turn) != last && !(AXES[face(turn)] == AXES[last] && face(turn) < last),
+        None => true
+    }
+}
+
+/// How a coordinate changes under each move, worked out by building a cube
+/// with that coordinate, turning it and reading the coordinate back.
+pub fn move_table<const N: usize>(size: usize, moves: [usize; N], set: fn(&mut CubieCube, usize), get: fn(&CubieCube) -> usize) -> Vec<[u16; N]> {
+    (0..size).map(|coordinate| {
+        let mut cube = CubieCube::SOLVED;
+        set(&mut cube, coordinate);
+        moves.map(|turn| get(&cube.apply(turn)) as u16)
+    }).collect()
+}
+
+/// Numbers a permutation from 0 to n! - 1 (its rank in lexicographic order).
+pub fn rank_permutation(permutation: &[u8]) -> usize {
+    let mut rank = 0;
+    for i in 0..permutation.len() {
+        let smaller = permutation[i + 1..].iter().filter(|&&x| x < permutation[i]).count();
+        rank = rank * (permutation.len() - i) + smaller;
+    }
+    rank
+}
+
+pub fn unrank_permutation(mut rank: usize, length: usize) -> Vec<u8> {
+    let mut digits = vec![0; length];
+    for i in (0..length).rev() {
+        digits[i] = rank % (length - i);
+        rank /= length - i;
+    }
+
+    let mut unused: Vec<u8> = (0..length as u8).collect();
+    digits.into_iter().map(|x| unused.remove(x)).collect()
+}
+
+/// Numbers an even permutation from 0 to n! / 2 - 1. Of each two permutations
+/// next to each other in lexicographic order exactly one is even.
+pub fn rank_even(permutation: &[u8]) -> usize {
+    rank_permutation(permutation) / 2
+}
+
+pub fn unrank_even(rank: usize, length: usize) -> Vec<u8> {
+    let permutation = unrank_permutation(rank * 2, length);
+    match permutation_parity(&permutation) {
+        0 => permutation,
+        _ => unrank_permutation(rank * 2 + 1, length)
+    }
+}
+
+pub fn binomial(n: usize, k: usize) -> usize {
+    if k > n {
+        return 0;
+    }
+    (0..k).fold(1, |result, i| result * (n - i) / (i + 1))
+}
+
+/// Numbers a set of `k` positions (given in increasing order) out of `n`.
+pub fn rank_combination(positions: &[usize]) -> usize {
+    positions.iter().enumerate().map(|(i, &x)| binomial(x, i + 1)).sum()
+}
+
+pub fn unrank_combination(mut rank: usize, k: usize) -> Vec<usize> {
+    let mut positions = vec![0; k];
+    for i in (0..k).rev() {
+        let mut x = i;
+        while binomial(x + 1, i + 1) <= rank {
+            x += 1;
+        }
+        positions[i] = x;
+        rank -= binomial(x, i + 1);
+    }
+    positions
+}
+
+impl CubieCube {
+    pub const SOLVED: Self = Self {
+        cp: [0, 1, 2, 3, 4, 5, 6, 7],
+        co: [0; 8],
+        ep: [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+        eo: [0; 12]
+    };
+
+    pub fn from_cube(cube: &RubiksCube) -> Result<Self, String> {
+        let pieces = cube.pieces()?;
+        Ok(Self {
+            cp: pieces.corner_permutation,
+            co: pieces.corner_orientation,
+            ep: pieces.edge_permutation,
+            eo: pieces.edge_orientation
+        })
+    }
+
+    /// `self` followed by `other`.
+    pub fn multiply(&self, other: &Self) -> Self {
+        let mut result = *self;
+        for i in 0..8 {
+            let from = other.cp[i] as usize;
+            result.cp[i] = self.cp[from];
+            result.co[i] = (self.co[from] + other.co[i]) % 3;
+        }
+        for i in 0..12 {
+            let from = other.ep[i] as usize;
+            result.ep[i] = self.ep[from];
+            result.eo[i] = (self.eo[from] + other.eo[i]) % 2;
+        }
+        result
+    }
+
+    /// The cube that undoes `self`.
+    pub fn inverse(&self) -> Self {
+        let mut result = *self;
+        for i in 0..8 {
+            let slot = self.cp[i] as usize;
+            result.cp[slot] = i as u8;
+            result.co[slot] = (3 - self.co[i]) % 3;
+        }
+        for i in 0..12 {
+            let slot = self.ep[i] as usize;
+            result.ep[slot] = i as u8;
+            result.eo[slot] = self.eo[i];
+        }
+        result
+    }
+
+    pub fn apply(&self, turn: usize) -> Self {
+        self.multiply(&MOVES[turn])
+    }
+
+    /// Corner twists as a number from 0 to 3^7 - 1. The last corner's twist
+    /// follows from the other seven.
+    pub fn twist(&self) -> usize {
+        self.co[..7].iter().fold(0, |result, &x| result * 3 + x as usize)
+    }
+
+    pub fn set_twist(&mut self, mut twist: usize) {
+        let mut sum = 0;
+        for i in (0..7).rev() {
+            self.co[i] = (twist % 3) as u8;
+            sum += self.co[i];
+            twist /= 3;
+        }
+        self.co[7] = (3 - sum % 3) % 3;
+    }
+
+    /// Edge flips as a number from 0 to 2^11 - 1.
+    pub fn flip(&self) -> usize {
+        self.eo[..11].iter().fold(0, |result, &x| result * 2 + x as usize)
+    }
+
+    pub fn set_flip(&mut self, mut flip: usize) {
+        let mut sum = 0;
+        for i in (0..11).rev() {
+            self.eo[i] = (flip % 2) as u8;
+            sum += self.eo[i];
+            flip /= 2;
+        }
+        self.eo[11] = sum % 2;
+    }
+
+    /// Which four slots hold the E slice edges (FR, FL, BL and BR), from 0 to
+    /// 494. Solved is 494.
+    pub fn slice(&self) -> usize {
+        let positions: Vec<usize> = (0..12).filter(|&i| self.ep[i] >= 8).collect();
+        rank_combination(&positions)
+    }
+
+    pub fn set_slice(&mut self, slice: usize) {
+        let positions = unrank_combination(slice, 4);
+        let (mut slice_edges, mut other_edges) = (8..12, 0..8);
+        for i in 0..12 {
+            self.ep[i] = if positions.contains(&i) { slice_edges.next() } else { other_edges.next() }.unwrap();
+        }
+    }
+
+    pub fn corners(&self) -> usize {
+        rank_permutation(&self.cp)
+    }
+
+    pub fn set_corners(&mut self, corners: usize) {
+        self.cp.copy_from_slice(&unrank_permutation(corners, 8));
+    }
+
+    /// The order of the eight U and D layer edges, once the slice edges are
+    /// in the slice.
+    pub fn ud_edges(&self) -> usize {
+        rank_permutation(&self.ep[..8])
+    }
+
+    pub fn set_ud_edges(&mut self, edges: usize) {
+        self.ep[..8].copy_from_slice(&unrank_permutation(edges, 8));
+    }
+
+    /// The order of the four slice edges within the slice.
+    pub fn slice_edges(&self) -> usize {
+        let edges: Vec<u8> = self.ep[8..].iter().map(|x| x - 8).collect();
+        rank_permutation(&edges)
+    }
+
+    pub fn set_slice_edges(&mut self, edges: usize) {
+        for (i, x) in unrank_permutation(edges, 4).into_iter().enumerate() {
+            self.ep[8 + i] = x + 8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn cubies_follow_the_cube() {
+        let mut turned = RubiksCube::default();
+        turned.input_moves("R");
+        assert_eq!(CubieCube::from_cube(&turned).unwrap(), MOVES[6]);
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let turns: Vec<usize> = (0..25).map(|_| rng.gen_range(0..MOVE_COUNT)).collect();
+            let mut cube = RubiksCube::default();
+            cube.input_moves(&turns.iter().map(|&x| move_name(x)).collect::<Vec<_>>().join(" "));
+
+            let cubies = turns.iter().fold(CubieCube::SOLVED, |cube, &turn| cube.apply(turn));
+            assert_eq!(CubieCube::from_cube(&cube).unwrap(), cubies);
+            assert_eq!(cubies.multiply(&cubies.inverse()), CubieCube::SOLVED);
+        }
+    }
+
+    #[test]
+    fn coordinates_round_trip() {
+        for coordinate in [0, 1, 1000, 2186] {
+            let mut cube = CubieCube::SOLVED;
+            cube.set_twist(coordinate);
+            assert_eq!(cube.twist(), coordinate);
+        }
+        for coordinate in [0, 1, 1000, 2047] {
+            let mut cube = CubieCube::SOLVED;
+            cube.set_flip(coordinate);
+            assert_eq!(cube.flip(), coordinate);
+        }
+        for rank in [0, 1, 5039, 40319] {
+            assert_eq!(rank_permutation(&unrank_permutation(rank, 8)), rank);
+        }
+    }
+}
@@ -1,88 +1,75 @@
-use priority_queue::PriorityQueue;
-use main::PuzzleType;
+use std::sync::OnceLock;
 
-fn h(cube:PuzzleType) -> u16 {
-    0
-}
+use crate::algorithms::{Move, Notation};
+use crate::db_generators::ivy_database_generator::{self, IvyState, MOVES};
+use crate::db_generators::pattern_database::PatternDatabase;
+use crate::models::Puzzle;
+use crate::models::ivy::Ivy;
+use crate::solvers::optimal::{solutions, Indexed};
 
-const POSSIBLE_MOVES: [[&str;3];6] = [["U ", "U' ", "U2 "],["D ", "D' ", "D2 "],["R ", "R' ", "R2 "],["L ", "L' ", "L2 "],["F ", "F' ", "F2 "],["B ", "B' ", "B2 "]];
+static TABLE: OnceLock<PatternDatabase> = OnceLock::new();
 
-struct Node {
-    cube: PuzzleType,
-    current_move: String,
-    depth: u16
+fn table() -> Result<&'static PatternDatabase, String> {
+    if let Some(table) = TABLE.get() {
+        return Ok(table);
+    }
+    let table = ivy_database_generator::load()?;
+    Ok(TABLE.get_or_init(|| table))
 }
 
-impl Node {
-    fn new(cube:PuzzleType, current_move:String, depth:u16) -> Self {
-        Self {
-            cube: cube,
-            current_move: current_move,
-            depth: depth
-        }
-    }
+fn optimal(ivy: &Ivy, all: bool) -> Result<Vec<Vec<Move>>, String> {
+    ivy.validate()?;
+    let found = solutions(IvyState::of(ivy)?, table()?, MOVES.len(), all);
+    Ok(found.iter().map(|turns| turns.iter().map(|&x| Notation::Corner.parse_move(MOVES[x]).unwrap()).collect()).collect())
 }
 
-fn get_opposite_layer(layer: char) -> Option<char> {
-    match layer {
-        'U' => Some('D'),
-        'D' => Some('U'),
-        'R' => Some('L'),
-        'L' => Some('R'),
-        'F' => Some('B'),
-        'B' => Some('F'),
-        _ => None,
-    }
+/// How many moves the Ivy is from solved.
+pub fn distance(ivy: &Ivy) -> Result<u8, String> {
+    ivy.validate()?;
+    Ok(table()?.get(IvyState::of(ivy)?.index()))
 }
 
-fn prune(new_move: &str, last_move: &str) -> bool {
-    let new_move_char = new_move.chars().next();
-    [Some(last_move.chars().next()), Some(get_opposite_layer(new_move_char.unwrap()))].contains(&Some(new_move_char))
+/// A solution in as few moves as possible, looked up in the table of every
+/// Ivy state (`gidb`), which is generated the first time it is needed.
+pub fn solve(ivy: &Ivy) -> Result<Vec<Move>, String> {
+    Ok(optimal(ivy, false)?.remove(0))
 }
 
-pub fn IDAStar(cube:PuzzleType) {
-    let mut node_stack = Vec::new();
-    let mut moves: [&str; 21];
-    let mut solved = false;
-    let mut next_bound: u16 = h(cube);
-    let mut bound = 0;
+/// Every solution in as few moves as possible.
+pub fn solve_all(ivy: &Ivy) -> Result<Vec<Vec<Move>>, String> {
+    optimal(ivy, true)
+}
 
-    while !solved {
-        if node_stack.len() == 0 {
-            node_stack.push(Node::new(cube, "", 0));
-            bound = next_bound.clone();
-            next_bound = std::f64::INFINITY;
-        }
-        let mut current_node: Node = node_stack.pop();
-        if current_node.depth > 0 {
-            moves[current_node.depth - 1] = current_node.current_move;
-        }
-        moves[current_node.depth] = "";
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::format;
 
-        if current_node.depth == bound {
-            if current_node.cube.is_solved() {
-                solved = true;
-                return moves.join(" ")
-            }
-        } else {
-            let mut successors = PriorityQueue::new();
-            for x in POSSIBLE_MOVES {
-                for y in x {
-                    if current_node.depth == 0 || !prune(y, current_node.current_move.as_str()) {
-                        let mut new_cube = current_node.cube.clone();
-                        new_cube.input_scramble(y);
-                        let estimated_moves = current_node.depth + h(new_cube);
-                        if estimated_moves <= bound {
-                            successors.push([new_cube, y], estimated_moves)
-                        } else if estimated_moves < next_bound {
-                            next_bound = estimated_moves;
-                        }
-                    }
-                }
-            }
-            for (item, _) in successors.iter() {
-                node_stack.push(Node::new(item[0], item[1], current_node.depth + 1))
+    const SCRAMBLES: [&str; 3] = ["R U L' F R' U'", "U' F L R' U F' L", "L R U' F L' R'"];
+
+    #[test]
+    fn solutions_solve_the_ivy_in_its_distance() {
+        for scramble in SCRAMBLES {
+            let mut ivy = Ivy::default();
+            ivy.input_moves(scramble);
+            let distance = distance(&ivy).unwrap() as usize;
+            let all = solve_all(&ivy).unwrap();
+            assert!(!all.is_empty(), "{scramble}");
+            for moves in all.iter() {
+                assert_eq!(moves.len(), distance, "{scramble}");
+                let mut solved = ivy.clone();
+                solved.input_moves(&format(moves));
+                assert!(solved.is_solved(), "{scramble}: {}", format(moves));
             }
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn distances_count_the_moves() {
+        assert_eq!(distance(&Ivy::default()).unwrap(), 0);
+        let mut ivy = Ivy::default();
+        ivy.input_moves("R U'");
+        assert_eq!(distance(&ivy).unwrap(), 2);
+        assert_eq!(solve(&ivy).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,251 @@
+use lazy_static::lazy_static;
+
+use crate::algorithms::{Move, Notation};
+use crate::algorithms::simplify::simplify;
+use crate::algorithms::transforms::inverse;
+use crate::db_generators::pattern_database::byte_table;
+use crate::models::Puzzle;
+use crate::models::rubiks_cube::RubiksCube;
+use crate::solvers::cubie::{allowed_after, face, move_name, move_table, CubieCube, MOVE_COUNT};
+
+const TWISTS: usize = 2187;
+const FLIPS: usize = 2048;
+const SLICES: usize = 495;
+const CORNERS: usize = 40320;
+const UD_EDGES: usize = 40320;
+const SLICE_EDGES: usize = 24;
+
+/// `CubieCube::slice` of a cube in G1.
+const SOLVED_SLICE: usize = 494;
+
+/// Moves that stay in G1: U, U2, U', D, D2, D', R2, L2, F2 and B2.
+const PHASE_2_MOVES: [usize; 10] = [0, 1, 2, 15, 16, 17, 7, 13, 4, 10];
+
+/// The longest solution searched for by default. Twenty moves are always
+/// enough, but finding such a solution can take much longer.
+pub const MAX_LENGTH: usize = 21;
+
+struct Tables {
+    twist_moves: Vec<[u16; MOVE_COUNT]>,
+    flip_moves: Vec<[u16; MOVE_COUNT]>,
+    slice_moves: Vec<[u16; MOVE_COUNT]>,
+    corner_moves: Vec<[u16; 10]>,
+    ud_edge_moves: Vec<[u16; 10]>,
+    slice_edge_moves: Vec<[u16; 10]>,
+    /// How many moves it takes to solve each pair of coordinates together,
+    /// which is a lower bound for solving the phase.
+    twist_slice_distance: Vec<u8>,
+    flip_slice_distance: Vec<u8>,
+    twist_flip_distance: Vec<u8>,
+    corner_slice_distance: Vec<u8>,
+    ud_edge_slice_distance: Vec<u8>
+}
+
+lazy_static! {
+    static ref TABLES: Tables = Tables::new();
+}
+
+/// How many moves away from solved each pair of coordinates is.
+fn distance_table<const N: usize>(a_moves: &[[u16; N]], b_moves: &[[u16; N]], solved: (usize, usize)) -> Vec<u8> {
+    let b_size = b_moves.len();
+    byte_table(a_moves.len() * b_size, &[solved.0 * b_size + solved.1], |&x| x, |&index, next| {
+        let (a, b) = (index / b_size, index % b_size);
+        next.extend((0..N).map(|turn| a_moves[a][turn] as usize * b_size + b_moves[b][turn] as usize));
+    })
+}
+
+impl Tables {
+    fn new() -> Self {
+        let all: [usize; MOVE_COUNT] = std::array::from_fn(|i| i);
+
+        let twist_moves = move_table(TWISTS, all, CubieCube::set_twist, CubieCube::twist);
+        let flip_moves = move_table(FLIPS, all, CubieCube::set_flip, CubieCube::flip);
+        let slice_moves = move_table(SLICES, all, CubieCube::set_slice, CubieCube::slice);
+        let corner_moves = move_table(CORNERS, PHASE_2_MOVES, CubieCube::set_corners, CubieCube::corners);
+        let ud_edge_moves = move_table(UD_EDGES, PHASE_2_MOVES, CubieCube::set_ud_edges, CubieCube::ud_edges);
+        let slice_edge_moves = move_table(SLICE_EDGES, PHASE_2_MOVES, CubieCube::set_slice_edges, CubieCube::slice_edges);
+
+        Self {
+            twist_slice_distance: distance_table(&twist_moves, &slice_moves, (0, SOLVED_SLICE)),
+            flip_slice_distance: distance_table(&flip_moves, &slice_moves, (0, SOLVED_SLICE)),
+            twist_flip_distance: distance_table(&twist_moves, &flip_moves, (0, 0)),
+            corner_slice_distance: distance_table(&corner_moves, &slice_edge_moves, (0, 0)),
+            ud_edge_slice_distance: distance_table(&ud_edge_moves, &slice_edge_moves, (0, 0)),
+            twist_moves,
+            flip_moves,
+            slice_moves,
+            corner_moves,
+            ud_edge_moves,
+            slice_edge_moves
+        }
+    }
+
+    fn phase_1_distance(&self, twist: usize, flip: usize, slice: usize) -> usize {
+        let twist_slice = self.twist_slice_distance[twist * SLICES + slice];
+        let flip_slice = self.flip_slice_distance[flip * SLICES + slice];
+        let twist_flip = self.twist_flip_distance[twist * FLIPS + flip];
+        twist_slice.max(flip_slice).max(twist_flip) as usize
+    }
+
+    fn phase_2_distance(&self, corners: usize, ud_edges: usize, slice_edges: usize) -> usize {
+        let corner_slice = self.corner_slice_distance[corners * SLICE_EDGES + slice_edges];
+        let ud_edge_slice = self.ud_edge_slice_distance[ud_edges * SLICE_EDGES + slice_edges];
+        corner_slice.max(ud_edge_slice) as usize
+    }
+}
+
+struct Search<'a> {
+    tables: &'a Tables,
+    cube: CubieCube,
+    moves: Vec<usize>,
+    max_length: usize
+}
+
+impl Search<'_> {
+    fn last_face(&self) -> Option<usize> {
+        self.moves.last().map(|&x| face(x))
+    }
+
+    /// Looks for phase 1 solutions of exactly `left` more moves, and tries to
+    /// finish each one with phase 2.
+    fn phase_1(&mut self, twist: usize, flip: usize, slice: usize, left: usize) -> bool {
+        if left == 0 {
+            // A phase 1 solution ending in a phase 2 move would have been
+            // found one move shorter.
+            let ends_in_g1 = self.moves.last().is_some_and(|x| PHASE_2_MOVES.contains(x));
+            return twist == 0 && flip == 0 && slice == SOLVED_SLICE && !ends_in_g1 && self.start_phase_2();
+        }
+        if self.tables.phase_1_distance(twist, flip, slice) > left {
+            return false;
+        }
+
+        for turn in 0..MOVE_COUNT {
+            if !allowed_after(turn, self.last_face()) {
+                continue;
+            }
+            self.moves.push(turn);
+            let t = self.tables;
+            if self.phase_1(t.twist_moves[twist][turn] as usize, t.flip_moves[flip][turn] as usize, t.slice_moves[slice][turn] as usize, left - 1) {
+                return true;
+            }
+            self.moves.pop();
+        }
+        false
+    }
+
+    fn start_phase_2(&mut self) -> bool {
+        let cube = self.moves.iter().fold(self.cube, |cube, &turn| cube.apply(turn));
+        let (corners, ud_edges, slice_edges) = (cube.corners(), cube.ud_edges(), cube.slice_edges());
+
+        let phase_1_length = self.moves.len();
+        let start = self.tables.phase_2_distance(corners, ud_edges, slice_edges);
+        for length in start..=self.max_length.saturating_sub(phase_1_length) {
+            if self.phase_2(corners, ud_edges, slice_edges, length) {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn phase_2(&mut self, corners: usize, ud_edges: usize, slice_edges: usize, left: usize) -> bool {
+        if left == 0 {
+            return corners == 0 && ud_edges == 0 && slice_edges == 0;
+        }
+        if self.tables.phase_2_distance(corners, ud_edges, slice_edges) > left {
+            return false;
+        }
+
+        for (i, &turn) in PHASE_2_MOVES.iter().enumerate() {
+            if !allowed_after(turn, self.last_face()) {
+                continue;
+            }
+            self.moves.push(turn);
+            let t = self.tables;
+            if self.phase_2(t.corner_moves[corners][i] as usize, t.ud_edge_moves[ud_edges][i] as usize, t.slice_edge_moves[slice_edges][i] as usize, left - 1) {
+                return true;
+            }
+            self.moves.pop();
+        }
+        false
+    }
+}
+
+/// A solution of at most `max_length` moves, if there is one. The tables are
+/// built the first time this is called, which takes a moment.
+pub fn solve_within(cube: &RubiksCube, max_length: usize) -> Result<Vec<Move>, String> {
+    cube.validate()?;
+    let start = CubieCube::from_cube(cube)?;
+    let tables: &Tables = &TABLES;
+
+    // Solving the inverse and inverting the answer also works, and one of
+    // the two is often much quicker to find.
+    let mut searches = [start, start.inverse()].map(|cube| Search { tables, cube, moves: Vec::new(), max_length });
+    for length in 0..=max_length {
+        for (i, search) in searches.iter_mut().enumerate() {
+            let (twist, flip, slice) = (search.cube.twist(), search.cube.flip(), search.cube.slice());
+            if search.phase_1(twist, flip, slice, length) {
+                let moves: Vec<Move> = search.moves.iter().map(|&x| Notation::Cube.parse_move(&move_name(x)).unwrap()).collect();
+                return Ok(simplify(&if i == 0 { moves } else { inverse(&moves) }));
+            }
+        }
+    }
+    Err(format!("No solution of {max_length} moves or fewer was found"))
+}
+
+/// Kociemba's two-phase algorithm. Phase 1 brings the cube into the group
+/// G1 = <U, D, R2, L2, F2, B2>, where no corner is twisted, no edge flipped
+/// and the E slice edges are in the E slice. Phase 2 then solves it using only
+/// those moves. Both phases are IDA* searches over small coordinates, with
+/// pruning tables giving a lower bound on the moves left.
+pub fn solve(cube: &RubiksCube) -> Result<Vec<Move>, String> {
+    solve_within(cube, MAX_LENGTH)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use crate::algorithms::format;
+    use crate::solvers::tests::SCRAMBLES;
+
+    /// A single move, and the superflip, one of the hardest positions there is.
+    const EXTREMES: [&str; 2] = ["R", "U R2 F B R B2 R U2 L B2 R U' D' R2 F R' L B2 U2 F2"];
+
+    #[test]
+    fn solves_within_the_limit() {
+        for scramble in EXTREMES.into_iter().chain(SCRAMBLES) {
+            let mut cube = RubiksCube::default();
+            cube.input_moves(scramble);
+            let moves = solve(&cube).unwrap();
+            assert!(moves.len() <= MAX_LENGTH, "{scramble}");
+            cube.input_moves(&format(&moves));
+            assert!(cube.is_solved(), "{scramble}");
+        }
+    }
+
+    #[test]
+    fn solves_random_cubes() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..5 {
+            let turns: Vec<String> = (0..30).map(|_| move_name(rng.gen_range(0..MOVE_COUNT))).collect();
+            let mut cube = RubiksCube::default();
+            cube.input_moves(&turns.join(" "));
+            let moves = solve(&cube).unwrap();
+            assert!(moves.len() <= MAX_LENGTH);
+            cube.input_moves(&format(&moves));
+            assert!(cube.is_solved(), "{}", turns.join(" "));
+        }
+    }
+
+    #[test]
+    fn solved_needs_nothing() {
+        assert!(solve(&RubiksCube::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn too_short_a_limit_is_an_error() {
+        let mut cube = RubiksCube::default();
+        cube.input_moves("R U F' L2 D");
+        assert!(solve_within(&cube, 3).is_err());
+    }
+}
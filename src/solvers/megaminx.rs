@@ -0,0 +1,522 @@
+use std::collections::VecDeque;
+use lazy_static::lazy_static;
+
+use crate::algorithms::{Move, Notation};
+use crate::algorithms::simplify::simplify;
+use crate::models::{Puzzle, PuzzleType};
+use crate::models::megaminx::{Faces, Megaminx, CORNERS, EDGES, FACE_LETTERS};
+use crate::solvers::pieces::{turn_pieces, Orbit};
+use crate::solvers::stages::{Solution, Stage};
+
+/// The turns of each face, numbered face * 4 + the index of the amount.
+const AMOUNTS: [i32; 4] = [1, 2, -2, -1];
+const TURNS_COUNT: usize = 48;
+
+/// How deep to look for a few moves that solve one more piece before
+/// falling back on a commutator.
+const SEARCH_DEPTH: usize = 4;
+
+/// A 3-cycle of each kind of piece that leaves everything else alone. Every
+/// other 3-cycle is one of these with set up moves before and after.
+const CORNER_CYCLE: &str = "D' G D S' D' G' D S";
+const EDGE_CYCLE: &str = "F V3 L F' V2 U V3 F L' V2 F' U'";
+
+const STAGES: [(&str, &str); 8] = [
+    ("Star", "solve the edges of D"),
+    ("First layer", "solve the corners of D"),
+    ("F2L", "solve the edges between the faces next to D and the corners above them"),
+    ("S2L", "solve everything except U"),
+    ("EO", "turn the edges of U so their U sticker is on U"),
+    ("EP", "put the edges of U in place"),
+    ("CO", "turn the corners of U so their U sticker is on U"),
+    ("CP", "put the corners of U in place")
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Need {
+    Oriented,
+    Solved
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Corners,
+    Edges
+}
+
+const KINDS: [Kind; 2] = [Kind::Corners, Kind::Edges];
+
+impl Kind {
+    fn slots(&self) -> usize {
+        match self {
+            Kind::Corners => CORNERS.len(),
+            Kind::Edges => EDGES.len()
+        }
+    }
+
+    fn orientations(&self) -> u8 {
+        match self {
+            Kind::Corners => 3,
+            Kind::Edges => 2
+        }
+    }
+
+    fn faces(&self, slot: usize) -> Vec<usize> {
+        match self {
+            Kind::Corners => CORNERS[slot].iter().map(|x| x.0).collect(),
+            Kind::Edges => EDGES[slot].iter().map(|x| x.0).collect()
+        }
+    }
+
+    /// What a piece in `slot` has to be once `stage` is done, if anything.
+    fn need(&self, slot: usize, stage: usize) -> Option<Need> {
+        let (top, bottom) = (Faces::White.to_number(), Faces::Gray.to_number());
+        let faces = self.faces(slot);
+        let next_to_bottom = faces.iter().filter(|&&face| EDGES.iter().any(|x| [x[0].0, x[1].0] == [face, bottom] || [x[0].0, x[1].0] == [bottom, face])).count();
+
+        // The first stage the piece is oriented in and the one it is solved in.
+        let (oriented, solved) = match self {
+            Kind::Edges if faces.contains(&bottom) => (0, 0),
+            Kind::Corners if faces.contains(&bottom) => (1, 1),
+            _ if next_to_bottom == 2 => (2, 2),
+            Kind::Edges if faces.contains(&top) => (4, 5),
+            Kind::Corners if faces.contains(&top) => (6, 7),
+            _ => (3, 3)
+        };
+        match stage {
+            x if x >= solved => Some(Need::Solved),
+            x if x >= oriented => Some(Need::Oriented),
+            _ => None
+        }
+    }
+}
+
+/// The Megaminx as its pieces.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Minx {
+    corners: Orbit<20>,
+    edges: Orbit<30>
+}
+
+fn turn(index: usize) -> Move {
+    Move { layers: String::new(), letter: FACE_LETTERS[index / 4], wide: false, amount: AMOUNTS[index % 4], notation: Notation::Megaminx }
+}
+
+fn inverse(turn: usize) -> usize {
+    turn / 4 * 4 + 3 - turn % 4
+}
+
+fn parse(alg: &str) -> Vec<usize> {
+    Notation::Megaminx.parse(alg).unwrap().iter()
+        .map(|x| FACE_LETTERS.iter().position(|&y| y == x.letter).unwrap() * 4 + AMOUNTS.iter().position(|&y| (y - x.amount) % 5 == 0).unwrap())
+        .collect()
+}
+
+lazy_static! {
+    static ref TURNS: Vec<Minx> = {
+        let names: Vec<String> = (0..TURNS_COUNT).map(|x| turn(x).to_string()).collect();
+        let names: Vec<&str> = names.iter().map(|x| x.as_str()).collect();
+        turn_pieces(&PuzzleType::Megaminx(Megaminx::default()), &names).iter()
+            .map(|pieces| Minx { corners: Orbit::of(&pieces[0]), edges: Orbit::of(&pieces[1]) })
+            .collect()
+    };
+
+    /// Whether turns of two faces can be done in either order.
+    static ref COMMUTE: Vec<Vec<bool>> = (0..12).map(|a| (0..12).map(|b| {
+        Minx::solved().apply(a * 4).apply(b * 4) == Minx::solved().apply(b * 4).apply(a * 4)
+    }).collect()).collect();
+
+    /// For each kind of piece, the fewest turns that bring a piece from each
+    /// slot and orientation back home, indexed by (home, slot, orientation).
+    static ref DISTANCES: Vec<Vec<u8>> = KINDS.iter().map(|&kind| {
+        let (slots, orientations) = (kind.slots(), kind.orientations() as usize);
+        let mut distances = vec![u8::MAX; slots * slots * orientations];
+        for home in 0..slots {
+            let table = &mut distances[home * slots * orientations..(home + 1) * slots * orientations];
+            table[home * orientations] = 0;
+            let mut queue = VecDeque::from([(home, 0)]);
+            while let Some((slot, orientation)) = queue.pop_front() {
+                let depth = table[slot * orientations + orientation];
+                for turn in 0..TURNS_COUNT {
+                    let (next, twist) = destination(kind, turn, slot);
+                    let index = next * orientations + (orientation + twist as usize) % orientations;
+                    if table[index] == u8::MAX {
+                        table[index] = depth + 1;
+                        queue.push_back((next, (orientation + twist as usize) % orientations));
+                    }
+                }
+            }
+        }
+        distances
+    }).collect();
+
+    static ref SETUPS: Vec<Setups> = vec![Setups::new(Kind::Corners, CORNER_CYCLE), Setups::new(Kind::Edges, EDGE_CYCLE)];
+}
+
+/// The slot a turn moves the piece in `slot` to, and how far it twists it.
+fn destination(kind: Kind, turn: usize, slot: usize) -> (usize, u8) {
+    let (permutation, orientation) = TURNS[turn].pieces(kind);
+    let next = permutation.iter().position(|&x| x as usize == slot).unwrap();
+    (next, orientation[next])
+}
+
+impl Minx {
+    fn solved() -> Self {
+        Self { corners: Orbit::solved(), edges: Orbit::solved() }
+    }
+
+    fn of(megaminx: &Megaminx) -> Result<Self, String> {
+        let pieces = PuzzleType::Megaminx(megaminx.clone()).pieces()?;
+        Ok(Self { corners: Orbit::of(&pieces[0]), edges: Orbit::of(&pieces[1]) })
+    }
+
+    fn apply(&self, turn: usize) -> Self {
+        Self { corners: self.corners.multiply(&TURNS[turn].corners, 3), edges: self.edges.multiply(&TURNS[turn].edges, 2) }
+    }
+
+    fn pieces(&self, kind: Kind) -> (&[u8], &[u8]) {
+        match kind {
+            Kind::Corners => (&self.corners.permutation, &self.corners.orientation),
+            Kind::Edges => (&self.edges.permutation, &self.edges.orientation)
+        }
+    }
+}
+
+/// Whether `piece`, sitting in `slot` with `orientation`, is what `need` asks for.
+fn meets(need: Option<Need>, slot: usize, piece: u8, orientation: u8) -> bool {
+    match need {
+        Some(Need::Solved) => piece as usize == slot && orientation == 0,
+        Some(Need::Oriented) => orientation == 0,
+        None => false
+    }
+}
+
+/// What every slot has to hold once a stage is done.
+struct Goal {
+    needs: Vec<Vec<Option<Need>>>
+}
+
+impl Goal {
+    fn of(stage: usize) -> Self {
+        Self { needs: KINDS.iter().map(|kind| (0..kind.slots()).map(|slot| kind.need(slot, stage)).collect()).collect() }
+    }
+
+    /// Which slots already hold what they need.
+    fn met(&self, state: &Minx) -> Vec<Vec<bool>> {
+        KINDS.iter().zip(&self.needs).map(|(&kind, needs)| {
+            let (permutation, orientation) = state.pieces(kind);
+            (0..kind.slots()).map(|slot| meets(needs[slot], slot, permutation[slot], orientation[slot])).collect()
+        }).collect()
+    }
+
+    fn count(&self, state: &Minx) -> usize {
+        KINDS.iter().zip(&self.needs).map(|(&kind, needs)| {
+            let (permutation, orientation) = state.pieces(kind);
+            (0..kind.slots()).filter(|&slot| meets(needs[slot], slot, permutation[slot], orientation[slot])).count()
+        }).sum()
+    }
+
+    fn total(&self) -> usize {
+        self.needs.iter().flatten().filter(|x| x.is_some()).count()
+    }
+
+    /// Whether every slot in `kept` still holds what it needs.
+    fn keeps(&self, state: &Minx, kept: &[Vec<bool>]) -> bool {
+        KINDS.iter().zip(&self.needs).zip(kept).all(|((&kind, needs), kept)| {
+            let (permutation, orientation) = state.pieces(kind);
+            (0..kind.slots()).all(|slot| !kept[slot] || meets(needs[slot], slot, permutation[slot], orientation[slot]))
+        })
+    }
+
+    /// Whether each piece that has to end up back home is close enough to
+    /// get there in `depth` turns.
+    fn within(&self, state: &Minx, kept: &[Vec<bool>], depth: usize) -> bool {
+        KINDS.iter().enumerate().all(|(k, &kind)| {
+            let (slots, orientations) = (kind.slots(), kind.orientations() as usize);
+            let (permutation, orientation) = state.pieces(kind);
+            (0..slots).all(|slot| {
+                let piece = permutation[slot] as usize;
+                !kept[k][piece] || self.needs[k][piece] != Some(Need::Solved)
+                    || DISTANCES[k][(piece * slots + slot) * orientations + orientation[slot] as usize] as usize <= depth
+            })
+        })
+    }
+
+    /// A few turns that meet one more need without undoing any that are met.
+    fn search(&self, state: &Minx) -> Option<Vec<usize>> {
+        let kept = self.met(state);
+        let count = self.count(state);
+        let mut turns = Vec::new();
+        (1..=SEARCH_DEPTH).find(|&depth| self.search_depth(state, &kept, count, depth, &mut turns)).map(|_| turns)
+    }
+
+    fn search_depth(&self, state: &Minx, kept: &[Vec<bool>], count: usize, depth: usize, turns: &mut Vec<usize>) -> bool {
+        if depth == 0 {
+            return self.count(state) > count && self.keeps(state, kept);
+        }
+        if !self.within(state, kept, depth) {
+            return false;
+        }
+        for turn in 0..TURNS_COUNT {
+            if let Some(&last) = turns.last() {
+                // Turns of the same face are merged, and of two faces that
+                // commute only one order is tried.
+                if last / 4 == turn / 4 || (COMMUTE[last / 4][turn / 4] && last > turn) {
+                    continue;
+                }
+            }
+            turns.push(turn);
+            if self.search_depth(&state.apply(turn), kept, count, depth - 1, turns) {
+                return true;
+            }
+            turns.pop();
+        }
+        false
+    }
+
+    /// A 3-cycle that meets as many more needs as possible without undoing
+    /// any that are met, the shortest if there are several. If none meets
+    /// more, one that takes a piece turned the wrong way in its own slot out
+    /// of it, so the next can bring it back the right way.
+    fn cycle(&self, state: &Minx) -> Option<Vec<usize>> {
+        let kept = self.met(state);
+        // How many more needs the best cycle meets, its length and the cycle.
+        let mut best: Option<(usize, usize, Cycle)> = None;
+
+        for (k, &kind) in KINDS.iter().enumerate() {
+            let (permutation, orientation) = state.pieces(kind);
+            let n = kind.orientations();
+            // Slots that may be cycled: those still to do, and those that
+            // only need a piece turned the right way, which can get another.
+            let free: Vec<usize> = (0..kind.slots()).filter(|&x| !kept[k][x] || self.needs[k][x] == Some(Need::Oriented)).collect();
+
+            for &p in &free {
+                for &q in &free {
+                    for &r in &free {
+                        if p == q || q == r || r == p {
+                            continue;
+                        }
+                        for f1 in 0..n {
+                            for f2 in 0..n {
+                                let f3 = (2 * n - f1 - f2) % n;
+                                // The pieces in p, q and r move on to q, r and p.
+                                let moved = [(q, p, f1), (r, q, f2), (p, r, f3)];
+                                let met: Vec<bool> = moved.iter()
+                                    .map(|&(to, from, twist)| meets(self.needs[k][to], to, permutation[from], (orientation[from] + twist) % n))
+                                    .collect();
+                                if moved.iter().zip(&met).any(|(&(to, _, _), &met)| kept[k][to] && !met) {
+                                    continue;
+                                }
+                                let gained = met.iter().filter(|&&x| x).count() - [p, q, r].iter().filter(|&&x| kept[k][x]).count();
+                                let unstick = gained == 0 && permutation[p] as usize == p && self.needs[k][p] == Some(Need::Solved);
+                                if gained == 0 && !unstick {
+                                    continue;
+                                }
+                                let Some(length) = SETUPS[k].length([p, q, r], [f1, f2]) else { continue };
+                                if best.is_none_or(|(best_gained, best_length, _)| (gained, best_length) > (best_gained, length)) {
+                                    best = Some((gained, length, Cycle { kind: k, slots: [p, q, r], twists: [f1, f2] }));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        best.map(|(_, _, cycle)| SETUPS[cycle.kind].turns(cycle.slots, cycle.twists).unwrap())
+    }
+}
+
+/// Three slots of one kind of piece, the piece in each moving on to the
+/// next, with how far the first two are twisted on the way.
+#[derive(Clone, Copy)]
+struct Cycle {
+    kind: usize,
+    slots: [usize; 3],
+    twists: [u8; 2]
+}
+
+/// Every way of bringing three pieces of one kind into the slots of a pure
+/// 3-cycle, found by a breadth first search from those slots, so any three
+/// pieces can be cycled by setting them up, cycling and undoing the set up.
+struct Setups {
+    kind: Kind,
+    cycle: Vec<usize>,
+    /// How far the cycle twists each piece on its way to the next slot.
+    twists: [u8; 3],
+    /// The state each state was first reached from and the turn that did it.
+    previous: Vec<Option<(usize, usize)>>
+}
+
+impl Setups {
+    fn new(kind: Kind, cycle: &str) -> Self {
+        let cycle = parse(cycle);
+        let state = cycle.iter().fold(Minx::solved(), |state, &turn| state.apply(turn));
+        let (permutation, orientation) = state.pieces(kind);
+
+        let first = (0..kind.slots()).find(|&x| permutation[x] as usize != x).unwrap();
+        let second = permutation.iter().position(|&x| x as usize == first).unwrap();
+        let third = permutation.iter().position(|&x| x as usize == second).unwrap();
+        let slots = [first, second, third];
+        let twists = [orientation[second], orientation[third], orientation[first]];
+
+        let mut setups = Self { kind, cycle, twists, previous: Vec::new() };
+        let n = kind.orientations();
+        let size = kind.slots().pow(3) * (n as usize).pow(3);
+        setups.previous = vec![None; size];
+
+        let start = setups.index(slots, [0; 3]);
+        setups.previous[start] = Some((start, 0));
+        let mut queue = VecDeque::from([(slots, [0u8; 3])]);
+        while let Some((at, twisted)) = queue.pop_front() {
+            let index = setups.index(at, twisted);
+            for turn in 0..TURNS_COUNT {
+                let mut next = at;
+                let mut next_twisted = twisted;
+                for i in 0..3 {
+                    let (slot, twist) = destination(kind, turn, at[i]);
+                    next[i] = slot;
+                    next_twisted[i] = (twisted[i] + twist) % n;
+                }
+                let next_index = setups.index(next, next_twisted);
+                if setups.previous[next_index].is_none() {
+                    setups.previous[next_index] = Some((index, turn));
+                    queue.push_back((next, next_twisted));
+                }
+            }
+        }
+        setups
+    }
+
+    fn index(&self, slots: [usize; 3], twists: [u8; 3]) -> usize {
+        let n = self.kind.orientations() as usize;
+        let index = slots.iter().fold(0, |index, &x| index * self.kind.slots() + x);
+        twists.iter().fold(index, |index, &x| index * n + x as usize)
+    }
+
+    /// The state reached by the shortest set up that brings the cycle's
+    /// pieces to `slots`, twisted so the whole moves the piece in each slot
+    /// on to the next, twisting the first two by `twists`.
+    fn setup(&self, slots: [usize; 3], twists: [u8; 2]) -> Option<usize> {
+        let n = self.kind.orientations();
+        (0..n).filter_map(|a| {
+            // Set up, cycle and undo the set up twists a piece by minus the
+            // twist of the slot it leaves, plus the cycle's, plus the twist
+            // of the slot it arrives in.
+            let b = (a + twists[0] + n - self.twists[0]) % n;
+            let c = (b + twists[1] + n - self.twists[1]) % n;
+            let index = self.index(slots, [a, b, c]);
+            self.previous[index].map(|_| index)
+        }).min_by_key(|&index| self.depth(index))
+    }
+
+    fn depth(&self, mut index: usize) -> usize {
+        let mut depth = 0;
+        while let Some((previous, _)) = self.previous[index].filter(|&(previous, _)| previous != index) {
+            index = previous;
+            depth += 1;
+        }
+        depth
+    }
+
+    fn length(&self, slots: [usize; 3], twists: [u8; 2]) -> Option<usize> {
+        self.setup(slots, twists).map(|index| 2 * self.depth(index) + self.cycle.len())
+    }
+
+    fn turns(&self, slots: [usize; 3], twists: [u8; 2]) -> Option<Vec<usize>> {
+        let mut index = self.setup(slots, twists)?;
+        let mut setup = Vec::new();
+        while let Some((previous, turn)) = self.previous[index].filter(|&(previous, _)| previous != index) {
+            setup.push(turn);
+            index = previous;
+        }
+        // `setup` is now the set up backwards: undone, it brings the pieces
+        // to the cycle's slots, and done forwards it takes them back.
+        let mut turns: Vec<usize> = setup.iter().map(|&x| inverse(x)).collect();
+        turns.extend(&self.cycle);
+        turns.extend(setup.iter().rev());
+        Some(turns)
+    }
+}
+
+/// Solves the Megaminx in the stages people use, with D as the first face
+/// and U as the last layer. Each stage solves one piece at a time, with a
+/// short search for a few turns where there is one and otherwise a 3-cycle
+/// of pieces set up from `CORNER_CYCLE` or `EDGE_CYCLE`, so any state can be
+/// solved even though the solutions are long.
+pub fn solve(megaminx: &Megaminx) -> Result<Solution, String> {
+    megaminx.validate()?;
+    let mut state = Minx::of(megaminx)?;
+
+    let mut stages = Vec::new();
+    for (stage, &(name, goal)) in STAGES.iter().enumerate() {
+        let needs = Goal::of(stage);
+        let mut turns = Vec::new();
+        while needs.count(&state) < needs.total() {
+            let step = needs.search(&state).or_else(|| needs.cycle(&state)).ok_or(format!("Could not finish the {name} stage"))?;
+            state = step.iter().fold(state, |state, &turn| state.apply(turn));
+            turns.extend(step);
+        }
+        stages.push(Stage { name, goal, moves: simplify(&turns.iter().map(|&x| turn(x)).collect::<Vec<Move>>()) });
+    }
+    Ok(Solution { stages })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use crate::algorithms::format;
+
+    const SCRAMBLES: [&str; 2] = ["R U F' L2 D B' G V2 O S' J P2 R' D2", "U2 F R' V G2 S O' J2 P D' L B2 U'"];
+
+    fn check(megaminx: &Megaminx, scramble: &str) {
+        let solution = solve(megaminx).unwrap();
+        let mut state = megaminx.clone();
+        for (stage, done) in solution.stages.iter().enumerate() {
+            state.input_moves(&format(&done.moves));
+            let goal = Goal::of(stage);
+            assert_eq!(goal.count(&Minx::of(&state).unwrap()), goal.total(), "{scramble}: {}", done.name);
+        }
+        assert!(state.is_solved(), "{scramble}");
+
+        let mut solved = megaminx.clone();
+        solved.input_moves(&format(&solution.moves()));
+        assert!(solved.is_solved(), "{scramble}");
+    }
+
+    #[test]
+    fn stages_reach_their_goals() {
+        for scramble in SCRAMBLES {
+            let mut megaminx = Megaminx::default();
+            megaminx.input_moves(scramble);
+            check(&megaminx, scramble);
+        }
+    }
+
+    #[test]
+    fn solves_random_megaminxes() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..3 {
+            let scramble = format(&(0..60).map(|_| turn(rng.gen_range(0..TURNS_COUNT))).collect::<Vec<Move>>());
+            let mut megaminx = Megaminx::default();
+            megaminx.input_moves(&scramble);
+            check(&megaminx, &scramble);
+        }
+    }
+
+    #[test]
+    fn cycles_only_move_three_pieces() {
+        for (alg, kind) in [(CORNER_CYCLE, Kind::Corners), (EDGE_CYCLE, Kind::Edges)] {
+            let state = parse(alg).iter().fold(Minx::solved(), |state, &turn| state.apply(turn));
+            let (permutation, orientation) = state.pieces(kind);
+            let moved = (0..permutation.len()).filter(|&x| permutation[x] as usize != x || orientation[x] != 0).count();
+            assert_eq!(moved, 3, "{alg}");
+            let other = match kind {
+                Kind::Corners => Kind::Edges,
+                Kind::Edges => Kind::Corners
+            };
+            let (permutation, orientation) = state.pieces(other);
+            assert!((0..permutation.len()).all(|x| permutation[x] as usize == x && orientation[x] == 0), "{alg}");
+        }
+    }
+}
@@ -0,0 +1,27 @@
+pub mod beginner;
+pub mod cfop;
+pub mod corner_solver;
+pub mod cubie;
+pub mod ivy;
+pub mod kociemba;
+pub mod megaminx;
+pub mod optimal;
+pub mod pieces;
+pub mod pyraminx;
+pub mod roux;
+pub mod skewb;
+pub mod slice_cube;
+pub mod stages;
+pub mod steps;
+pub mod thistlethwaite;
+pub mod zz;
+
+#[cfg(test)]
+pub(crate) mod tests {
+    /// Scrambles the 3x3 solvers are all tested on.
+    pub const SCRAMBLES: [&str; 3] = [
+        "R U2 F' L D B2 R' U F2 D' L2 B U' R2 F D2 L' B' U2 R",
+        "D2 F R' B U L2 D' F2 R U' B2 L D R2 F' U2 B' L' D' F",
+        "F' D2 L U R2 B' D F' L2 U2 R B D' L' F2 U' R' B2 D2 L"
+    ];
+}
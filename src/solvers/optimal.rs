@@ -0,0 +1,39 @@
+use crate::db_generators::pattern_database::PatternDatabase;
+
+/// A puzzle state with a number in a table of distances from solved, and
+/// turns numbered the same way as the table was generated with.
+pub trait Indexed: Copy {
+    fn index(&self) -> usize;
+    fn apply(&self, turn: usize) -> Self;
+}
+
+/// Every shortest solution of `state`, or only the first one found, as turn
+/// numbers. With the exact distance of every state known, a turn is on a
+/// shortest path exactly when it takes the puzzle one move closer to solved,
+/// so nothing is searched that does not lead to a solution.
+pub fn solutions<S: Indexed>(state: S, distances: &PatternDatabase, turns: usize, all: bool) -> Vec<Vec<usize>> {
+    let mut solutions = Vec::new();
+    collect(state, distances, turns, all, &mut Vec::new(), &mut solutions);
+    solutions
+}
+
+fn collect<S: Indexed>(state: S, distances: &PatternDatabase, turns: usize, all: bool, moves: &mut Vec<usize>, solutions: &mut Vec<Vec<usize>>) {
+    let distance = distances.get(state.index());
+    if distance == 0 {
+        solutions.push(moves.clone());
+        return;
+    }
+
+    for turn in 0..turns {
+        let next = state.apply(turn);
+        if distances.get(next.index()) + 1 != distance {
+            continue;
+        }
+        moves.push(turn);
+        collect(next, distances, turns, all, moves, solutions);
+        moves.pop();
+        if !all && !solutions.is_empty() {
+            return;
+        }
+    }
+}
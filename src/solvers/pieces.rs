@@ -0,0 +1,45 @@
+use crate::models::{PieceSet, PuzzleType};
+
+/// One kind of piece as numbers rather than stickers, the same way
+/// `CubieCube` holds the 3x3: `permutation[slot]` is the piece in `slot` and
+/// `orientation[slot]` how far it is twisted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Orbit<const N: usize> {
+    pub permutation: [u8; N],
+    pub orientation: [u8; N]
+}
+
+impl<const N: usize> Orbit<N> {
+    pub fn solved() -> Self {
+        Self { permutation: std::array::from_fn(|i| i as u8), orientation: [0; N] }
+    }
+
+    pub fn of(pieces: &PieceSet) -> Self {
+        let mut orbit = Self::solved();
+        orbit.permutation.copy_from_slice(&pieces.permutation);
+        orbit.orientation.copy_from_slice(&pieces.orientation);
+        orbit
+    }
+
+    /// `self` followed by `other`, for pieces with `orientations` ways of
+    /// sitting in a slot.
+    pub fn multiply(&self, other: &Self, orientations: u8) -> Self {
+        let mut result = *self;
+        for i in 0..N {
+            let from = other.permutation[i] as usize;
+            result.permutation[i] = self.permutation[from];
+            result.orientation[i] = (self.orientation[from] + other.orientation[i]) % orientations;
+        }
+        result
+    }
+}
+
+/// Each of `turns` done to a solved `puzzle`, as the pieces it moves, read
+/// off the sticker model so the two always agree.
+pub fn turn_pieces(puzzle: &PuzzleType, turns: &[&str]) -> Vec<Vec<PieceSet>> {
+    turns.iter().map(|turn| {
+        let mut puzzle = puzzle.solved();
+        puzzle.input_moves(turn);
+        puzzle.pieces().unwrap()
+    }).collect()
+}
@@ -0,0 +1,190 @@
+use std::sync::OnceLock;
+
+use crate::algorithms::{Move, Notation};
+use crate::algorithms::transforms::rotate;
+use crate::db_generators::pattern_database::PatternDatabase;
+use crate::db_generators::pyaminx_database_generator::{self, PyraminxCore, MOVES};
+use crate::models::{Puzzle, PuzzleType};
+use crate::models::pyraminx::{Pyraminx, EDGES};
+use crate::solvers::optimal::solutions;
+
+/// Each tip in the order of `pyraminx::TIPS`, the face opposite it, and its
+/// letter in WCA notation, where vertices are upper case and tips lower case.
+const VERTICES: [(char, char, char); 4] = [('T', 'D', 'U'), ('E', 'L', 'R'), ('K', 'R', 'L'), ('B', 'F', 'B')];
+
+static TABLE: OnceLock<PatternDatabase> = OnceLock::new();
+
+fn table() -> Result<&'static PatternDatabase, String> {
+    if let Some(table) = TABLE.get() {
+        return Ok(table);
+    }
+    let table = pyaminx_database_generator::load()?;
+    Ok(TABLE.get_or_init(|| table))
+}
+
+/// A turn of each tip that is not lined up with the axial centre under it.
+fn tip_moves(pyraminx: &Pyraminx) -> Result<Vec<Move>, String> {
+    let lined_up = |pyraminx: &Pyraminx, slot: usize| -> Result<bool, String> {
+        let pieces = PuzzleType::Pyraminx(pyraminx.clone()).pieces()?;
+        Ok(pieces[0].orientation[slot] == pieces[1].orientation[slot])
+    };
+
+    let mut moves = Vec::new();
+    for (slot, &(tip, _, _)) in VERTICES.iter().enumerate() {
+        if lined_up(pyraminx, slot)? {
+            continue;
+        }
+        let turn = Move { layers: String::new(), letter: tip, wide: false, amount: 1, notation: Notation::Pyraminx };
+        let mut turned = pyraminx.clone();
+        turned.input_moves(&turn.to_string());
+        moves.push(if lined_up(&turned, slot)? { turn } else { turn.inverse() });
+    }
+    Ok(moves)
+}
+
+/// Rewrites tip and face turns of the model in WCA notation. A face turn is
+/// the same as turning the vertex opposite it the same way and then turning
+/// the whole puzzle like the face, e.g. F is B followed by turning the whole
+/// puzzle around F. WCA notation never turns the whole puzzle, so each later
+/// move is rewritten as if the puzzle had stayed where it was, the same way
+/// `transforms::face_turns` does for the cube.
+fn to_wca(turns: &[Move]) -> Result<Vec<Move>, String> {
+    let mut held: Vec<Move> = Vec::new();
+    let mut wca = Vec::new();
+
+    for turn in turns {
+        let opposite = VERTICES.iter().find(|x| x.1 == turn.letter);
+        let tip = Move { letter: opposite.map_or(turn.letter, |x| x.0), ..turn.clone() };
+        let tip = rotate(&[tip], Notation::Pyraminx, &held)?.remove(0);
+
+        let &(_, _, vertex) = VERTICES.iter().find(|x| x.0 == tip.letter).ok_or(format!("{turn} is not a Pyraminx move"))?;
+        let letter = if opposite.is_some() { vertex } else { vertex.to_ascii_lowercase() };
+        wca.push(Move { letter, ..tip });
+
+        if opposite.is_some() {
+            held.push(turn.clone());
+        }
+    }
+    Ok(wca)
+}
+
+fn optimal(pyraminx: &Pyraminx, all: bool) -> Result<Vec<Vec<Move>>, String> {
+    pyraminx.validate()?;
+    let tips = tip_moves(pyraminx)?;
+    let found = solutions(PyraminxCore::of(pyraminx)?, table()?, MOVES.len(), all);
+
+    found.iter().map(|turns| {
+        let core = turns.iter().map(|&x| Notation::Pyraminx.parse_move(MOVES[x]).unwrap());
+        to_wca(&tips.iter().cloned().chain(core).collect::<Vec<Move>>())
+    }).collect()
+}
+
+/// A solution in WCA notation: the tips first, each with at most one move,
+/// then the rest in as few moves as possible, looked up in a table of every
+/// state of the puzzle without its tips (`gpdb`). The moves are written with
+/// the WCA letters, which the model itself does not read.
+pub fn solve(pyraminx: &Pyraminx) -> Result<Vec<Move>, String> {
+    Ok(optimal(pyraminx, false)?.remove(0))
+}
+
+/// Every shortest solution of the last four edges (L4E), for practising
+/// them: the axial centres and two edges next to each other (a V) must
+/// already be solved.
+pub fn solve_l4e(pyraminx: &Pyraminx) -> Result<Vec<Vec<Move>>, String> {
+    pyraminx.validate()?;
+    let core = PyraminxCore::of(pyraminx)?;
+    let solved = PyraminxCore::solved().into_iter().find(|x| x.axials == core.axials)
+        .ok_or("The axial centres are not solved, so this is not an L4E case".to_string())?;
+
+    let edges: Vec<usize> = (0..EDGES.len())
+        .filter(|&i| core.edges.permutation[i] == solved.edges.permutation[i] && core.edges.orientation[i] == solved.edges.orientation[i])
+        .collect();
+    let share_a_face = |a: usize, b: usize| EDGES[a].iter().any(|x| EDGES[b].iter().any(|y| x.0 == y.0));
+    if !edges.iter().any(|&a| edges.iter().any(|&b| a != b && share_a_face(a, b))) {
+        return Err(format!("No two solved edges form a V ({} solved), so this is not an L4E case", edges.len()));
+    }
+    optimal(pyraminx, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::format;
+
+    const SCRAMBLES: [&str; 3] = ["F R' L D F' R L' T E'", "D L' F R D' L F' K B", "R F' D L R' F D' L' T' K'"];
+
+    /// WCA moves as the model's own, undoing `to_wca`: each vertex turn is
+    /// the face opposite it with the whole puzzle turned back.
+    fn from_wca(wca: &[Move]) -> Vec<Move> {
+        let mut held: Vec<Move> = Vec::new();
+        wca.iter().map(|turn| {
+            let &(tip, _, _) = VERTICES.iter().find(|x| x.2 == turn.letter.to_ascii_uppercase()).unwrap();
+            let back: Vec<Move> = held.iter().rev().map(|x| x.inverse()).collect();
+            let tip = rotate(&[Move { letter: tip, ..turn.clone() }], Notation::Pyraminx, &back).unwrap().remove(0);
+            if turn.letter.is_ascii_lowercase() {
+                return tip;
+            }
+            let face = Move { letter: VERTICES.iter().find(|x| x.0 == tip.letter).unwrap().1, ..tip };
+            held.push(face.clone());
+            face
+        }).collect()
+    }
+
+    /// Reads WCA notation, which `Notation::Pyraminx` does not.
+    fn wca(alg: &str) -> Vec<Move> {
+        alg.split_whitespace().map(|x| {
+            let amount = if x.ends_with('\'') { -1 } else { 1 };
+            Move { layers: String::new(), letter: x.chars().next().unwrap(), wide: false, amount, notation: Notation::Pyraminx }
+        }).collect()
+    }
+
+    fn solves(pyraminx: &Pyraminx, wca: &[Move]) -> bool {
+        let mut solved = pyraminx.clone();
+        solved.input_moves(&format(&from_wca(wca)));
+        solved.is_solved()
+    }
+
+    #[test]
+    fn solutions_solve_the_pyraminx() {
+        for scramble in SCRAMBLES {
+            let mut pyraminx = Pyraminx::default();
+            pyraminx.input_moves(scramble);
+            let moves = solve(&pyraminx).unwrap();
+            assert!(solves(&pyraminx, &moves), "{scramble}: {}", format(&moves));
+
+            // Tips come first, one move each, and the rest is at most 11.
+            let tips = moves.iter().take_while(|x| x.letter.is_ascii_lowercase()).count();
+            assert!(tips <= 4 && moves.len() - tips <= 11, "{scramble}");
+            assert!(moves[tips..].iter().all(|x| x.letter.is_ascii_uppercase()), "{scramble}");
+        }
+    }
+
+    #[test]
+    fn model_moves_are_written_in_wca_notation() {
+        // Each face turn is the WCA turn of the vertex opposite it, and the
+        // tips keep their vertex's letter.
+        for (model, expected) in [("D", "U"), ("D'", "U'"), ("L", "R"), ("R", "L"), ("F'", "B'"), ("T'", "u'"), ("E", "r"), ("K'", "l'"), ("B", "b"), ("D D", "U U")] {
+            let turns = Notation::Pyraminx.parse(model).unwrap();
+            assert_eq!(format(&to_wca(&turns).unwrap()), expected, "{model}");
+            assert_eq!(format(&from_wca(&wca(expected))), model, "{expected}");
+        }
+    }
+
+    #[test]
+    fn l4e_cases_are_solved() {
+        // A three cycle of edges in WCA notation, which keeps the axial
+        // centres and so the V between the two edges it leaves alone.
+        let mut pyraminx = Pyraminx::default();
+        pyraminx.input_moves(&format(&from_wca(&wca("R' L R L'"))));
+        let all = solve_l4e(&pyraminx).unwrap();
+        assert!(!all.is_empty());
+        for moves in all.iter() {
+            assert_eq!(moves.len(), 4);
+            assert!(solves(&pyraminx, moves), "{}", format(moves));
+        }
+
+        pyraminx.input_moves(&format(&from_wca(&wca("U"))));
+        assert!(solve_l4e(&pyraminx).is_err());
+        assert!(solve(&Pyraminx::default()).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,199 @@
+use std::sync::OnceLock;
+use lazy_static::lazy_static;
+
+use crate::algorithms::{Move, Notation};
+use crate::algorithms::last_layer::{OLL, PLL};
+use crate::algorithms::simplify::{metrics, simplify};
+use crate::db_generators::pattern_database::byte_table;
+use crate::models::Puzzle;
+use crate::models::rubiks_cube::RubiksCube;
+use crate::solvers::cubie::rank_permutation;
+use crate::solvers::slice_cube::{turns, SliceCube, Turn};
+use crate::solvers::stages::{Solution, Stage};
+use crate::solvers::steps::{search, Piece, Table, MAX_DEPTH};
+
+/// The pieces of each block, by slot: the first on L and the second on R,
+/// both along the bottom.
+const FIRST_BLOCK: [Piece; 5] = [Piece::Corner(5), Piece::Corner(6), Piece::Edge(6), Piece::Edge(9), Piece::Edge(10)];
+const SECOND_BLOCK: [Piece; 5] = [Piece::Corner(4), Piece::Corner(7), Piece::Edge(4), Piece::Edge(8), Piece::Edge(11)];
+
+/// The moves each block is built with.
+const FIRST_BLOCK_TURNS: &str = "UFRBLDMr";
+const SECOND_BLOCK_TURNS: &str = "URMr";
+
+/// The six edges left for the last step (UR, UF, UL, UB, DF and DB), and
+/// the centres the M slice brings to U in turn.
+const LSE_EDGES: [usize; 6] = [0, 1, 2, 3, 5, 7];
+const M_CENTRES: [u8; 4] = [0, 3, 5, 1];
+/// Orders and flips of those edges, where the M slice centres are, and how
+/// far U is turned.
+const LSE_STATES: usize = 720 * 64 * 4 * 4;
+
+const STAGES: [(&str, &str); 4] = [
+    ("First block", "build a 1x2x3 block on the left, along the bottom"),
+    ("Second block", "build the matching block on the right with R, r, U and M, keeping the first"),
+    ("CMLL", "solve the last four corners with last layer algorithms, which keep both blocks"),
+    ("LSE", "solve the last six edges and the centres with M and U")
+];
+
+fn solved(pieces: &[Piece], cube: &SliceCube) -> bool {
+    pieces.iter().all(|&piece| match piece {
+        Piece::Corner(slot) => cube.cube.cp[slot] as usize == slot && cube.cube.co[slot] == 0,
+        Piece::Edge(slot) => cube.cube.ep[slot] as usize == slot && cube.cube.eo[slot] == 0
+    })
+}
+
+/// Builds `pieces` in as few moves as `turns` allow. Each table covers four
+/// of the five pieces, so between them they know about all of them.
+fn block(cube: &SliceCube, pieces: &[Piece; 5], turns: &[Turn], name: &str) -> Result<Vec<Move>, String> {
+    let tables = [[0, 1, 2, 3], [0, 1, 2, 4], [0, 2, 3, 4]].map(|x| Table::new(x.map(|i| pieces[i]).to_vec(), turns));
+    let bound = |cube: &SliceCube| tables.iter().map(|x| x.distance(cube)).max().unwrap();
+    let done = |cube: &SliceCube| solved(pieces, cube) && solved(&FIRST_BLOCK, cube);
+    search(cube, turns, &bound, &done).into_iter().next().ok_or(format!("No {name} within {MAX_DEPTH} moves"))
+}
+
+lazy_static! {
+    /// The OLL and PLL algorithms that move any of the last layer corners,
+    /// with what they do. All of them keep the first two layers, so the
+    /// blocks, and the centres where they were.
+    static ref CMLL: Vec<(Vec<Move>, SliceCube)> = OLL.iter().chain(PLL.iter()).filter_map(|(_, alg)| {
+        let moves = Notation::Cube.parse(alg).ok()?;
+        let cube = SliceCube::SOLVED.apply(&moves).ok()?;
+        let corners = (0..4).any(|x| cube.cube.cp[x] as usize != x || cube.cube.co[x] != 0);
+        (corners && cube.centred()).then_some((moves, cube))
+    }).collect();
+
+    static ref U_TURNS: Vec<Turn> = turns("U").unwrap();
+}
+
+fn corners_solved(cube: &SliceCube) -> bool {
+    solved(&FIRST_BLOCK, cube) && solved(&SECOND_BLOCK, cube) && (0..4).all(|x| cube.cube.cp[x] as usize == x && cube.cube.co[x] == 0)
+}
+
+/// The shortest way (in STM) of solving the corners with as few algorithms
+/// as possible, turning U before each and at the end. Two always do: one to
+/// orient them and one to swap them.
+fn cmll(cube: &SliceCube) -> Result<Vec<Move>, String> {
+    let aufs: Vec<(Vec<Move>, SliceCube)> = [(Vec::new(), SliceCube::SOLVED)].into_iter()
+        .chain(U_TURNS.iter().map(|x| (vec![x.name.clone()], x.cube)))
+        .collect();
+    // Each way of getting somewhere as the turn of U and the algorithm done
+    // after it, by number, only written out as moves once it works.
+    let written = |steps: &[(usize, usize)], last: usize| {
+        let moves: Vec<Move> = steps.iter().flat_map(|&(auf, alg)| [aufs[auf].0.clone(), CMLL[alg].0.clone()].concat()).chain(aufs[last].0.clone()).collect();
+        simplify(&moves)
+    };
+
+    let mut layer: Vec<(SliceCube, Vec<(usize, usize)>)> = vec![(*cube, Vec::new())];
+    for count in 0..=2 {
+        let solutions = layer.iter().flat_map(|(cube, steps)| {
+            (0..aufs.len()).filter(|&last| corners_solved(&cube.multiply(&aufs[last].1))).map(|last| written(steps, last))
+        });
+        if let Some(best) = solutions.min_by_key(|x| metrics(x).stm) {
+            return Ok(best);
+        }
+        if count < 2 {
+            let aufs = &aufs;
+            layer = layer.iter().flat_map(|(cube, steps)| (0..aufs.len()).flat_map(move |auf| (0..CMLL.len()).map(move |alg| {
+                (cube.multiply(&aufs[auf].1).multiply(&CMLL[alg].1), [steps.as_slice(), &[(auf, alg)]].concat())
+            }))).collect();
+        }
+    }
+    Err("Could not solve the corners".to_string())
+}
+
+fn lse_index(cube: &SliceCube) -> Option<usize> {
+    let order: Vec<u8> = LSE_EDGES.iter().map(|&slot| LSE_EDGES.iter().position(|&x| x == cube.cube.ep[slot] as usize).map(|x| x as u8)).collect::<Option<_>>()?;
+    let flips = LSE_EDGES.iter().fold(0, |result, &slot| result * 2 + cube.cube.eo[slot] as usize);
+    let centres = M_CENTRES.iter().position(|&x| x == cube.centres[0])?;
+    let turn = (cube.cube.cp[0] < 4).then_some(cube.cube.cp[0] as usize)?;
+    Some(((rank_permutation(&order) * 64 + flips) * 4 + centres) * 4 + turn)
+}
+
+/// Moves to solve every LSE state with M and U.
+fn lse_table() -> &'static (Vec<Turn>, Vec<u8>) {
+    static TABLE: OnceLock<(Vec<Turn>, Vec<u8>)> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let turns = turns("MU").unwrap();
+        let distances = byte_table(LSE_STATES, &[SliceCube::SOLVED], |cube| lse_index(cube).unwrap(), |cube, next| {
+            next.extend(turns.iter().map(|x| cube.multiply(&x.cube)));
+        });
+        (turns, distances)
+    })
+}
+
+fn lse(cube: &SliceCube) -> Result<Vec<Move>, String> {
+    let (turns, distances) = lse_table();
+    let distance = |cube: &SliceCube| lse_index(cube).map(|x| distances[x]).filter(|&x| x != u8::MAX);
+
+    let mut cube = *cube;
+    let mut moves = Vec::new();
+    let mut left = distance(&cube).ok_or("The last six edges cannot be solved with M and U".to_string())?;
+    while left > 0 {
+        let turn = turns.iter().find(|x| distance(&cube.multiply(&x.cube)) == Some(left - 1)).unwrap();
+        cube = cube.multiply(&turn.cube);
+        moves.push(turn.name.clone());
+        left -= 1;
+    }
+    Ok(moves)
+}
+
+/// Solves the cube with Roux, the blocks in as few moves as possible, CMLL
+/// with the OLL and PLL algorithms and the last six edges optimally. The
+/// model cannot be turned over, so the blocks are always built on L and R
+/// along the bottom.
+pub fn solve(cube: &RubiksCube) -> Result<Solution, String> {
+    cube.validate()?;
+    let mut state = SliceCube::from_cube(cube)?;
+
+    let mut stages = Vec::new();
+    for (step, &(name, goal)) in STAGES.iter().enumerate() {
+        let moves = match step {
+            0 => block(&state, &FIRST_BLOCK, &turns(FIRST_BLOCK_TURNS)?, "first block")?,
+            1 => block(&state, &SECOND_BLOCK, &turns(SECOND_BLOCK_TURNS)?, "second block")?,
+            2 => cmll(&state)?,
+            _ => lse(&state)?
+        };
+        state = state.apply(&moves)?;
+        stages.push(Stage { name, goal, moves });
+    }
+    Ok(Solution { stages })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::format;
+    use crate::solvers::tests::SCRAMBLES;
+
+    #[test]
+    fn stages_reach_their_goals() {
+        for scramble in SCRAMBLES {
+            let mut cube = RubiksCube::default();
+            cube.input_moves(scramble);
+            let solution = solve(&cube).unwrap();
+            let mut state = SliceCube::from_cube(&cube).unwrap();
+            let goals: [&dyn Fn(&SliceCube) -> bool; 4] = [
+                &|x| solved(&FIRST_BLOCK, x),
+                &|x| solved(&FIRST_BLOCK, x) && solved(&SECOND_BLOCK, x),
+                &corners_solved,
+                &|x| *x == SliceCube::SOLVED
+            ];
+            for (stage, goal) in solution.stages.iter().zip(goals) {
+                state = state.apply(&stage.moves).unwrap();
+                assert!(goal(&state), "{scramble}: {} {}", stage.name, format(&stage.moves));
+            }
+            assert_eq!(SliceCube::from_cube(&cube).unwrap().apply(&solution.moves()).unwrap(), SliceCube::SOLVED, "{scramble}");
+        }
+    }
+
+    #[test]
+    fn every_lse_state_is_reached() {
+        let (_, distances) = lse_table();
+        let reached = distances.iter().filter(|&&x| x != u8::MAX).count();
+        // M and U keep the edge flips even, and tie the parity of the edge
+        // permutation to how far U is turned, so a quarter of the states.
+        assert_eq!(reached, LSE_STATES / 4);
+        assert!(lse(&SliceCube::SOLVED).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,68 @@
+use std::sync::OnceLock;
+
+use crate::algorithms::{Move, Notation};
+use crate::db_generators::pattern_database::PatternDatabase;
+use crate::db_generators::skewb_database_generator::{self, SkewbState, MOVES};
+use crate::models::Puzzle;
+use crate::models::skewb::Skewb;
+use crate::solvers::optimal::solutions;
+
+static TABLE: OnceLock<PatternDatabase> = OnceLock::new();
+
+fn table() -> Result<&'static PatternDatabase, String> {
+    if let Some(table) = TABLE.get() {
+        return Ok(table);
+    }
+    let table = skewb_database_generator::load()?;
+    Ok(TABLE.get_or_init(|| table))
+}
+
+fn optimal(skewb: &Skewb, all: bool) -> Result<Vec<Vec<Move>>, String> {
+    skewb.validate()?;
+    let found = solutions(SkewbState::of(skewb)?, table()?, MOVES.len(), all);
+    Ok(found.iter().map(|turns| turns.iter().map(|&x| Notation::Corner.parse_move(MOVES[x]).unwrap()).collect()).collect())
+}
+
+/// A solution in as few moves as possible, looked up in the table of every
+/// Skewb state (`gsdb`), which is generated the first time it is needed.
+pub fn solve(skewb: &Skewb) -> Result<Vec<Move>, String> {
+    Ok(optimal(skewb, false)?.remove(0))
+}
+
+/// Every solution in as few moves as possible.
+pub fn solve_all(skewb: &Skewb) -> Result<Vec<Vec<Move>>, String> {
+    optimal(skewb, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::format;
+
+    const SCRAMBLES: [&str; 3] = ["R U L' B R' U' L B'", "U' R L U B' R U' L' R", "L R' U B L' R U' B R'"];
+
+    #[test]
+    fn solutions_solve_the_skewb() {
+        for scramble in SCRAMBLES {
+            let mut skewb = Skewb::default();
+            skewb.input_moves(scramble);
+            let all = solve_all(&skewb).unwrap();
+            assert!(!all.is_empty(), "{scramble}");
+            for moves in all.iter() {
+                assert_eq!(moves.len(), all[0].len(), "{scramble}");
+                assert!(moves.len() <= 11, "{scramble}");
+                let mut solved = skewb.clone();
+                solved.input_moves(&format(moves));
+                assert!(solved.is_solved(), "{scramble}: {}", format(moves));
+            }
+        }
+    }
+
+    #[test]
+    fn solves_short_scrambles_in_as_many_moves() {
+        let mut skewb = Skewb::default();
+        skewb.input_moves("R U' L");
+        assert_eq!(solve(&skewb).unwrap().len(), 3);
+        assert!(solve(&Skewb::default()).unwrap().is_empty());
+    }
+}
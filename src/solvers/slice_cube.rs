@@ -0,0 +1,189 @@
+use crate::algorithms::{Move, Notation};
+use crate::models::rubiks_cube::{RubiksCube, EDGES};
+use crate::solvers::cubie::{CubieCube, MOVES};
+
+/// Each slice with the faces it turns like (M as L, E as D and S as F): the
+/// face every centre it moves goes to, in the model's face order (U, F, R,
+/// B, L, D).
+const SLICES: [(char, [usize; 6]); 3] = [
+    ('M', [1, 5, 2, 0, 4, 3]),
+    ('E', [0, 2, 3, 4, 1, 5]),
+    ('S', [2, 1, 5, 3, 0, 4])
+];
+
+/// Wide moves and rotations as a face turn and the slices turned with it,
+/// each with its direction: r is R M', x is R M' L' and so on.
+const COMPOSITES: [(char, &[(char, i32)]); 9] = [
+    ('r', &[('R', 1), ('M', -1)]),
+    ('l', &[('L', 1), ('M', 1)]),
+    ('u', &[('U', 1), ('E', -1)]),
+    ('d', &[('D', 1), ('E', 1)]),
+    ('f', &[('F', 1), ('S', 1)]),
+    ('b', &[('B', 1), ('S', -1)]),
+    ('x', &[('R', 1), ('M', -1), ('L', -1)]),
+    ('y', &[('U', 1), ('E', -1), ('D', -1)]),
+    ('z', &[('F', 1), ('S', 1), ('B', -1)])
+];
+
+/// The letters a move can have, in the order two turns about the same axis
+/// are searched in, and the axis each one turns about.
+const LETTERS: &str = "UFRBLDMESufrbldxyz";
+const AXES: [usize; 18] = [0, 1, 2, 1, 2, 0, 2, 0, 1, 0, 1, 2, 1, 2, 0, 2, 0, 1];
+
+/// The 3x3 as a `CubieCube` along with its centres, so it can do slice moves
+/// as well as face turns. The sticker model keeps its centres still and so
+/// cannot. Pieces are numbered by where they are in space rather than
+/// against the centres: after an M, the cube only counts as solved once the
+/// centres are back too.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SliceCube {
+    pub cube: CubieCube,
+    /// The centre at each face, numbered as the faces.
+    pub centres: [u8; 6]
+}
+
+impl SliceCube {
+    pub const SOLVED: Self = Self { cube: CubieCube::SOLVED, centres: [0, 1, 2, 3, 4, 5] };
+
+    pub fn from_cube(cube: &RubiksCube) -> Result<Self, String> {
+        Ok(Self { cube: CubieCube::from_cube(cube)?, ..Self::SOLVED })
+    }
+
+    /// `self` followed by `other`.
+    pub fn multiply(&self, other: &Self) -> Self {
+        Self { cube: self.cube.multiply(&other.cube), centres: other.centres.map(|x| self.centres[x as usize]) }
+    }
+
+    pub fn centred(&self) -> bool {
+        self.centres == Self::SOLVED.centres
+    }
+
+    /// A quarter turn of a slice, read off the faces it sends each centre to.
+    fn slice(faces: [usize; 6]) -> Self {
+        let mut result = Self::SOLVED;
+        for (face, &to) in faces.iter().enumerate() {
+            result.centres[to] = face as u8;
+        }
+        for (slot, edge) in EDGES.iter().enumerate() {
+            if edge.iter().any(|x| faces[x.0] == x.0) {
+                continue;
+            }
+            let to = EDGES.iter().position(|x| edge.iter().all(|y| x.iter().any(|z| z.0 == faces[y.0]))).unwrap();
+            result.cube.ep[to] = slot as u8;
+            result.cube.eo[to] = EDGES[to].iter().position(|x| x.0 == faces[edge[0].0]).unwrap() as u8;
+        }
+        result
+    }
+
+    /// A quarter turn of `letter`, clockwise.
+    fn quarter(letter: char) -> Result<Self, String> {
+        if let Some(face) = "UFRBLD".find(letter) {
+            return Ok(Self { cube: MOVES[face * 3], ..Self::SOLVED });
+        }
+        if let Some(&(_, faces)) = SLICES.iter().find(|x| x.0 == letter) {
+            return Ok(Self::slice(faces));
+        }
+        let &(_, parts) = COMPOSITES.iter().find(|x| x.0 == letter).ok_or(format!("'{letter}' is not a 3x3 move"))?;
+        parts.iter().try_fold(Self::SOLVED, |cube, &(letter, direction)| {
+            Ok(cube.multiply(&Self::quarter(letter)?.power(direction)))
+        })
+    }
+
+    fn power(&self, amount: i32) -> Self {
+        (0..amount.rem_euclid(4)).fold(Self::SOLVED, |cube, _| cube.multiply(self))
+    }
+
+    /// What `turn` does to a solved cube.
+    pub fn of(turn: &Move) -> Result<Self, String> {
+        if turn.notation != Notation::Cube || !turn.layers.is_empty() {
+            return Err(format!("{turn} is not a 3x3 move"));
+        }
+        let letter = if turn.wide { turn.letter.to_ascii_lowercase() } else { turn.letter };
+        Ok(Self::quarter(letter)?.power(turn.amount))
+    }
+
+    pub fn apply(&self, moves: &[Move]) -> Result<Self, String> {
+        moves.iter().try_fold(*self, |cube, turn| Ok(cube.multiply(&Self::of(turn)?)))
+    }
+}
+
+/// A move a search may make, with what it does worked out ahead of time.
+pub struct Turn {
+    pub name: Move,
+    pub cube: SliceCube,
+    axis: usize,
+    order: usize
+}
+
+impl Turn {
+    /// Whether the search should try `self` straight after `last`. Turns
+    /// about the same axis all commute, so of those only one order is tried,
+    /// and the same layer is never turned twice in a row.
+    pub fn allowed_after(&self, last: Option<&Turn>) -> bool {
+        last.is_none_or(|last| self.axis != last.axis || self.order > last.order)
+    }
+}
+
+/// The quarter, half and anticlockwise turns of each of `letters` (faces,
+/// slices or wide moves, with wide moves in lower case).
+pub fn turns(letters: &str) -> Result<Vec<Turn>, String> {
+    let mut turns = Vec::new();
+    for letter in letters.chars() {
+        let order = LETTERS.find(letter).ok_or(format!("'{letter}' is not a 3x3 move"))?;
+        for amount in [1, 2, -1] {
+            let name = Move { layers: String::new(), letter, wide: false, amount, notation: Notation::Cube };
+            turns.push(Turn { cube: SliceCube::of(&name)?, name, axis: AXES[order], order });
+        }
+    }
+    Ok(turns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use crate::algorithms::format;
+
+    fn of(alg: &str) -> SliceCube {
+        SliceCube::SOLVED.apply(&Notation::Cube.parse(alg).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn face_turns_agree_with_the_sticker_model() {
+        let mut rng = rand::thread_rng();
+        let faces: Vec<Move> = "UFRBLD".chars().flat_map(|letter| [1, 2, -1].map(|amount| {
+            Move { layers: String::new(), letter, wide: false, amount, notation: Notation::Cube }
+        })).collect();
+        for _ in 0..20 {
+            let moves: Vec<Move> = (0..25).map(|_| faces[rng.gen_range(0..faces.len())].clone()).collect();
+            let mut cube = RubiksCube::default();
+            cube.input_moves(&format(&moves));
+            assert_eq!(SliceCube::SOLVED.apply(&moves).unwrap(), SliceCube::from_cube(&cube).unwrap(), "{}", format(&moves));
+        }
+    }
+
+    #[test]
+    fn slices_and_wide_moves_are_made_of_turns() {
+        for (a, b) in [("r", "R M'"), ("l", "L M"), ("u", "U E'"), ("d", "D E"), ("f", "F S"), ("b", "B S'"),
+                       ("Rw2", "R2 M2"), ("x", "R M' L'"), ("y", "U E' D'"), ("z", "F S B'"),
+                       ("M", "L' R x'"), ("E", "U D' y'"), ("S", "F' B z"), ("x U x'", "F"), ("y R y'", "B")] {
+            assert_eq!(of(a), of(b), "{a} and {b}");
+        }
+        for alg in ["M", "E", "S", "r", "x", "y", "z"] {
+            assert!(!of(alg).centred(), "{alg}");
+            assert_eq!(of(&format!("{alg} {alg} {alg} {alg}")), SliceCube::SOLVED, "{alg}");
+        }
+        assert!(of("M2 U2 M2 U2").centred());
+        assert!(SliceCube::of(&Notation::Cube.parse_move("2R").unwrap()).is_err());
+    }
+
+    #[test]
+    fn turns_skip_repeated_layers_and_one_order_on_an_axis() {
+        let all = turns("RLMU").unwrap();
+        let (r, l, m, u) = (&all[0], &all[3], &all[6], &all[9]);
+        assert!(r.allowed_after(None) && u.allowed_after(Some(r)) && r.allowed_after(Some(u)));
+        assert!(!r.allowed_after(Some(&all[1])));
+        assert!(m.allowed_after(Some(l)) && !l.allowed_after(Some(m)));
+        assert!(turns("RQ").is_err());
+    }
+}
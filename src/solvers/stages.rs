@@ -0,0 +1,57 @@
+use std::fmt;
+
+use crate::algorithms::{format, Move};
+use crate::algorithms::simplify::{metrics, simplify};
+use crate::models::PuzzleType;
+
+/// One step of a solve done in stages: what it does and the moves that do it.
+pub struct Stage {
+    pub name: &'static str,
+    pub goal: &'static str,
+    pub moves: Vec<Move>
+}
+
+pub struct Solution {
+    pub stages: Vec<Stage>
+}
+
+impl Solution {
+    /// Every stage one after the other, with turns of the same face where
+    /// two stages meet merged.
+    pub fn moves(&self) -> Vec<Move> {
+        simplify(&self.stages.iter().flat_map(|x| x.moves.clone()).collect::<Vec<Move>>())
+    }
+
+    /// Prints the solution the way `Display` does, drawing `puzzle` as it is
+    /// after each stage.
+    pub fn print(&self, puzzle: &PuzzleType) {
+        let mut puzzle = puzzle.clone();
+        for stage in &self.stages {
+            print!("{stage}");
+            puzzle.input_moves(&format(&stage.moves));
+            puzzle.print();
+        }
+        let moves = self.moves();
+        println!("Solution: {} ({})", format(&moves), metrics(&moves));
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}: {}", self.name, self.goal)?;
+        match self.moves.is_empty() {
+            true => writeln!(f, "  (already done)"),
+            false => writeln!(f, "  {} ({} move{})", format(&self.moves), self.moves.len(), if self.moves.len() == 1 { "" } else { "s" })
+        }
+    }
+}
+
+impl fmt::Display for Solution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for stage in &self.stages {
+            write!(f, "{stage}")?;
+        }
+        let moves = self.moves();
+        write!(f, "Solution: {} ({})", format(&moves), metrics(&moves))
+    }
+}
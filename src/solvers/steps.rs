@@ -0,0 +1,214 @@
+use crate::algorithms::{Move, Notation};
+use crate::db_generators::pattern_database::PatternDatabase;
+use crate::solvers::cubie::CubieCube;
+use crate::solvers::slice_cube::{turns, SliceCube, Turn};
+
+/// The longest step searched for. Tables cannot hold anything further, and
+/// a step that is longer than this with the moves allowed is not worth it.
+pub const MAX_DEPTH: usize = 14;
+
+/// Ways to flip the edges, as numbered by `CubieCube::flip`.
+const FLIPS: usize = 2048;
+
+/// A piece the step solvers track, by the slot it belongs in.
+#[derive(Clone, Copy)]
+pub enum Piece {
+    Corner(usize),
+    Edge(usize)
+}
+
+/// Where each corner and edge location goes when `cube` is done. A location
+/// is slot * 3 + twist for a corner and slot * 2 + flip for an edge, 24
+/// either way.
+fn locations(cube: &CubieCube) -> [[u8; 24]; 2] {
+    let mut locations = [[0; 24]; 2];
+    for slot in 0..8 {
+        let from = cube.cp[slot] as usize;
+        for twist in 0..3 {
+            locations[0][from * 3 + twist] = (slot * 3 + (twist + cube.co[slot] as usize) % 3) as u8;
+        }
+    }
+    for slot in 0..12 {
+        let from = cube.ep[slot] as usize;
+        for flip in 0..2 {
+            locations[1][from * 2 + flip] = (slot * 2 + (flip + cube.eo[slot] as usize) % 2) as u8;
+        }
+    }
+    locations
+}
+
+impl Piece {
+    fn location(self, cube: &CubieCube) -> usize {
+        match self {
+            Piece::Corner(piece) => {
+                let slot = cube.cp.iter().position(|&x| x as usize == piece).unwrap();
+                slot * 3 + cube.co[slot] as usize
+            }
+            Piece::Edge(piece) => {
+                let slot = cube.ep.iter().position(|&x| x as usize == piece).unwrap();
+                slot * 2 + cube.eo[slot] as usize
+            }
+        }
+    }
+
+    fn turned(self, location: usize, locations: &[[u8; 24]; 2]) -> usize {
+        let kind = match self {
+            Piece::Corner(_) => 0,
+            Piece::Edge(_) => 1
+        };
+        locations[kind][location] as usize
+    }
+}
+
+/// Moves to put up to four pieces where they belong with the turns allowed,
+/// for every location they could be in, and optionally also to orient every
+/// edge. A few hundred thousand states without the edges, or a couple of
+/// million with them, so it is quick enough to build for each search rather
+/// than keep.
+pub struct Table {
+    pieces: Vec<Piece>,
+    flips: bool,
+    distances: PatternDatabase
+}
+
+impl Table {
+    pub fn new(pieces: Vec<Piece>, turns: &[Turn]) -> Self {
+        Self::build(pieces, false, turns)
+    }
+
+    pub fn with_flips(pieces: Vec<Piece>, turns: &[Turn]) -> Self {
+        Self::build(pieces, true, turns)
+    }
+
+    fn build(pieces: Vec<Piece>, flips: bool, turns: &[Turn]) -> Self {
+        let places = 24usize.pow(pieces.len() as u32);
+        let size = places * if flips { FLIPS } else { 1 };
+        let solved = index(&pieces, &CubieCube::SOLVED);
+        let moves: Vec<[[u8; 24]; 2]> = turns.iter().map(|x| locations(&x.cube.cube)).collect();
+        let flip_moves: Vec<Vec<u16>> = turns.iter().map(|turn| match flips {
+            true => (0..FLIPS).map(|flip| {
+                let mut cube = CubieCube::SOLVED;
+                cube.set_flip(flip);
+                cube.multiply(&turn.cube.cube).flip() as u16
+            }).collect(),
+            false => vec![0]
+        }).collect();
+
+        let distances = PatternDatabase::generate(size, &[solved], |index, next| {
+            let (flip, index) = (index / places, index % places);
+            next.extend(moves.iter().zip(&flip_moves).map(|(locations, flips)| {
+                flips[flip] as usize * places + pieces.iter().fold((0, index, 1), |(result, rest, scale), piece| {
+                    (result + piece.turned(rest % 24, locations) * scale, rest / 24, scale * 24)
+                }).0
+            }));
+        }, |_, _| {});
+        Self { pieces, flips, distances }
+    }
+
+    pub fn distance(&self, cube: &SliceCube) -> usize {
+        let flip = if self.flips { cube.cube.flip() } else { 0 };
+        self.distances.get(flip * 24usize.pow(self.pieces.len() as u32) + index(&self.pieces, &cube.cube)) as usize
+    }
+}
+
+/// The locations of `pieces` as one number in base 24, the first piece in
+/// the lowest place.
+fn index(pieces: &[Piece], cube: &CubieCube) -> usize {
+    pieces.iter().rev().fold(0, |result, piece| result * 24 + piece.location(cube))
+}
+
+/// The turns of a move set, written either as the layers it is generated
+/// by, e.g. `<R, U>` or `<M, U>`, or as the faces it leaves out, e.g. `no
+/// B`. Nothing at all means every face.
+pub fn move_set(layers: &str) -> Result<Vec<Turn>, String> {
+    let layers = layers.trim();
+    let (letters, left_out) = if let Some(letters) = layers.strip_prefix("no ") {
+        (letters, true)
+    } else if let Some(letters) = layers.strip_prefix('<').and_then(|x| x.strip_suffix('>')) {
+        (letters, false)
+    } else if layers.is_empty() {
+        ("", true)
+    } else {
+        return Err(format!("'{layers}' is not a move set, e.g. <R, U> or no B"));
+    };
+
+    let mut chosen: Vec<char> = Vec::new();
+    for name in letters.split([',', ' ']).filter(|x| !x.is_empty()) {
+        let turn = Notation::Cube.parse_move(name).ok().filter(|x| x.amount == 1 && x.layers.is_empty() && !"xyz".contains(x.letter))
+            .ok_or(format!("'{name}' is not a face, slice or wide move"))?;
+        chosen.push(if turn.wide { turn.letter.to_ascii_lowercase() } else { turn.letter });
+    }
+    if left_out {
+        chosen = "UFRBLD".chars().filter(|x| !chosen.contains(x)).collect();
+    }
+    turns(&chosen.into_iter().collect::<String>())
+}
+
+/// Every shortest sequence of `turns` after which `done` holds, leaving out
+/// those that only differ in the order of turns about the same axis. `bound`
+/// never says more moves are needed than really are.
+pub fn search(cube: &SliceCube, turns: &[Turn], bound: &dyn Fn(&SliceCube) -> usize, done: &dyn Fn(&SliceCube) -> bool) -> Vec<Vec<Move>> {
+    let mut solutions = Vec::new();
+    for depth in bound(cube)..=MAX_DEPTH {
+        collect(cube, turns, depth, bound, done, &mut Vec::new(), &mut solutions);
+        if !solutions.is_empty() {
+            break;
+        }
+    }
+    solutions.iter().map(|x: &Vec<usize>| x.iter().map(|&turn| turns[turn].name.clone()).collect()).collect()
+}
+
+fn collect(cube: &SliceCube, turns: &[Turn], depth: usize, bound: &dyn Fn(&SliceCube) -> usize, done: &dyn Fn(&SliceCube) -> bool, moves: &mut Vec<usize>, solutions: &mut Vec<Vec<usize>>) {
+    if depth == 0 {
+        if done(cube) {
+            solutions.push(moves.clone());
+        }
+        return;
+    }
+    if bound(cube) > depth {
+        return;
+    }
+    for (i, turn) in turns.iter().enumerate() {
+        if turn.allowed_after(moves.last().map(|&x| &turns[x])) {
+            moves.push(i);
+            collect(&cube.multiply(&turn.cube), turns, depth - 1, bound, done, moves, solutions);
+            moves.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn move_sets_are_read() {
+        for (layers, count) in [("", 18), ("no B", 15), ("no B, D", 12), ("<R, U>", 6), ("<r U M>", 9), ("<Rw, U>", 6)] {
+            assert_eq!(move_set(layers).unwrap().len(), count, "{layers}");
+        }
+        for layers in ["R U", "<x>", "<R2, U>", "<Q>", "no 2R"] {
+            assert!(move_set(layers).is_err(), "{layers}");
+        }
+    }
+
+    #[test]
+    fn tables_never_overestimate() {
+        let turns = move_set("").unwrap();
+        let table = Table::new(vec![Piece::Edge(5), Piece::Edge(7)], &turns);
+        let flips = Table::with_flips(vec![Piece::Edge(5)], &turns);
+        for (alg, moves) in [("", 0), ("F", 1), ("F R", 2), ("D2 F' R", 3)] {
+            let cube = SliceCube::SOLVED.apply(&Notation::Cube.parse(alg).unwrap()).unwrap();
+            assert!(table.distance(&cube) <= moves && flips.distance(&cube) <= moves, "{alg}");
+        }
+        let cube = SliceCube::SOLVED.apply(&Notation::Cube.parse("F").unwrap()).unwrap();
+        assert_eq!((table.distance(&cube), flips.distance(&cube)), (1, 1));
+    }
+
+    #[test]
+    fn searches_find_every_shortest_way() {
+        let turns = move_set("<R, U>").unwrap();
+        let cube = SliceCube::SOLVED.apply(&Notation::Cube.parse("R U").unwrap()).unwrap();
+        let solutions = search(&cube, &turns, &|_| 0, &|x| *x == SliceCube::SOLVED);
+        assert_eq!(solutions.iter().map(|x| crate::algorithms::format(x)).collect::<Vec<String>>(), vec!["U' R'"]);
+    }
+}
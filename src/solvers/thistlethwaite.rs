@@ -0,0 +1,223 @@
+use lazy_static::lazy_static;
+
+use crate::algorithms::Notation;
+use crate::db_generators::pattern_database::{byte_table, PatternDatabase, UNKNOWN};
+use crate::models::Puzzle;
+use crate::models::rubiks_cube::RubiksCube;
+use crate::solvers::stages::{Solution, Stage};
+use crate::solvers::cubie::{move_name, move_table, rank_combination, rank_permutation, unrank_combination, unrank_permutation, CubieCube};
+
+const FLIPS: usize = 2048;
+const TWISTS: usize = 2187;
+const SLICES: usize = 495;
+const CORNERS: usize = 40320;
+/// Ways to place the four M slice edges among the eight U and D layer slots.
+const M_SLICES: usize = 70;
+/// The orders of the edges within the M, S and E slices (24^3).
+const SLICE_ORDERS: usize = 13824;
+
+/// `CubieCube::slice` once the E slice edges are in the E slice.
+const SOLVED_SLICE: usize = 494;
+
+/// The slots of each slice, which in G3 hold exactly that slice's edges: M
+/// (UF, UB, DF, DB), S (UR, UL, DR, DL) and E (FR, FL, BL, BR).
+const SLICE_SLOTS: [[u8; 4]; 3] = [[1, 3, 5, 7], [0, 2, 4, 6], [8, 9, 10, 11]];
+
+/// The moves of each group, numbered as in `cubie`:
+/// G0 = <U, D, R, L, F, B>, G1 = <U, D, R, L, F2, B2>,
+/// G2 = <U, D, R2, L2, F2, B2> and G3 = <U2, D2, R2, L2, F2, B2>.
+const G0_MOVES: [usize; 18] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17];
+const G1_MOVES: [usize; 14] = [0, 1, 2, 4, 6, 7, 8, 10, 12, 13, 14, 15, 16, 17];
+const G2_MOVES: [usize; 10] = [0, 1, 2, 4, 7, 10, 13, 15, 16, 17];
+const G3_MOVES: [usize; 6] = [1, 4, 7, 10, 13, 16];
+
+/// Which slots of the U and D layers hold the M slice edges.
+fn m_slice(cube: &CubieCube) -> usize {
+    let positions: Vec<usize> = (0..8).filter(|&i| SLICE_SLOTS[0].contains(&cube.ep[i])).collect();
+    rank_combination(&positions)
+}
+
+fn set_m_slice(cube: &mut CubieCube, m_slice: usize) {
+    let positions = unrank_combination(m_slice, 4);
+    let (mut m_edges, mut s_edges) = (SLICE_SLOTS[0].iter(), SLICE_SLOTS[1].iter());
+    for i in 0..8 {
+        cube.ep[i] = *if positions.contains(&i) { m_edges.next() } else { s_edges.next() }.unwrap();
+    }
+}
+
+/// The order of the edges within each slice, for a cube in G3.
+fn slice_orders(cube: &CubieCube) -> usize {
+    SLICE_SLOTS.iter().fold(0, |result, slots| {
+        let order: Vec<u8> = slots.iter().map(|&slot| slots.iter().position(|&x| x == cube.ep[slot as usize]).unwrap() as u8).collect();
+        result * 24 + rank_permutation(&order)
+    })
+}
+
+fn set_slice_orders(cube: &mut CubieCube, mut orders: usize) {
+    for slots in SLICE_SLOTS.iter().rev() {
+        for (&slot, i) in slots.iter().zip(unrank_permutation(orders % 24, 4)) {
+            cube.ep[slot as usize] = slots[i as usize];
+        }
+        orders /= 24;
+    }
+}
+
+struct Tables {
+    /// Where each of the 96 corner permutations that half turns alone can
+    /// reach comes in the list of them.
+    g3_corner_index: Vec<Option<usize>>,
+    /// Moves to reach the next group from each coordinate of the first three
+    /// phases.
+    distances: [PatternDatabase; 3],
+    /// Moves to solve each coordinate of G3. Some need 15, so this is a
+    /// `byte_table`.
+    g3_distances: Vec<u8>
+}
+
+lazy_static! {
+    static ref TABLES: Tables = Tables::new();
+}
+
+impl Tables {
+    fn new() -> Self {
+        let quiet = |_, _| {};
+
+        let flip_moves = move_table(FLIPS, G0_MOVES, CubieCube::set_flip, CubieCube::flip);
+        let g0 = PatternDatabase::generate(FLIPS, &[0], |flip, next| {
+            next.extend(flip_moves[flip].iter().map(|&x| x as usize));
+        }, quiet);
+
+        let twist_moves = move_table(TWISTS, G1_MOVES, CubieCube::set_twist, CubieCube::twist);
+        let slice_moves = move_table(SLICES, G1_MOVES, CubieCube::set_slice, CubieCube::slice);
+        let g1 = PatternDatabase::generate(TWISTS * SLICES, &[SOLVED_SLICE], |index, next| {
+            let (twist, slice) = (index / SLICES, index % SLICES);
+            next.extend((0..G1_MOVES.len()).map(|i| twist_moves[twist][i] as usize * SLICES + slice_moves[slice][i] as usize));
+        }, quiet);
+
+        let corner_moves = move_table(CORNERS, G2_MOVES, CubieCube::set_corners, CubieCube::corners);
+        let m_slice_moves = move_table(M_SLICES, G2_MOVES, set_m_slice, m_slice);
+
+        // The corner permutations of G3, found by breadth first search.
+        let mut g3_corners = vec![0];
+        let mut g3_corner_index = vec![None; CORNERS];
+        g3_corner_index[0] = Some(0);
+        let mut i = 0;
+        while i < g3_corners.len() {
+            for turn in G3_MOVES {
+                let next = corner_moves[g3_corners[i]][G2_MOVES.iter().position(|&x| x == turn).unwrap()] as usize;
+                if g3_corner_index[next].is_none() {
+                    g3_corner_index[next] = Some(g3_corners.len());
+                    g3_corners.push(next);
+                }
+            }
+            i += 1;
+        }
+
+        let solved_m_slice = m_slice(&CubieCube::SOLVED);
+        let g3_states: Vec<usize> = g3_corners.iter().map(|&x| x * M_SLICES + solved_m_slice).collect();
+        let g2 = PatternDatabase::generate(CORNERS * M_SLICES, &g3_states, |index, next| {
+            let (corners, m_slice) = (index / M_SLICES, index % M_SLICES);
+            next.extend((0..G2_MOVES.len()).map(|i| corner_moves[corners][i] as usize * M_SLICES + m_slice_moves[m_slice][i] as usize));
+        }, quiet);
+
+        let g3 = byte_table(g3_corners.len() * SLICE_ORDERS, &[0], |&x| x, |&index, next| {
+            let mut cube = CubieCube::SOLVED;
+            cube.set_corners(g3_corners[index / SLICE_ORDERS]);
+            set_slice_orders(&mut cube, index % SLICE_ORDERS);
+            next.extend(G3_MOVES.iter().map(|&turn| {
+                let cube = cube.apply(turn);
+                g3_corner_index[cube.corners()].unwrap() * SLICE_ORDERS + slice_orders(&cube)
+            }));
+        });
+
+        Self { g3_corner_index, distances: [g0, g1, g2], g3_distances: g3 }
+    }
+
+    /// The coordinate of `cube` that `distances[phase]` is indexed by.
+    fn coordinate(&self, phase: usize, cube: &CubieCube) -> usize {
+        match phase {
+            0 => cube.flip(),
+            1 => cube.twist() * SLICES + cube.slice(),
+            2 => cube.corners() * M_SLICES + m_slice(cube),
+            _ => self.g3_corner_index[cube.corners()].unwrap() * SLICE_ORDERS + slice_orders(cube)
+        }
+    }
+
+    /// Moves from `cube` to the next group, or `None` if the table never
+    /// reached it.
+    fn distance(&self, phase: usize, cube: &CubieCube) -> Option<u8> {
+        let index = self.coordinate(phase, cube);
+        match phase {
+            3 => Some(self.g3_distances[index]).filter(|&x| x != u8::MAX),
+            _ => Some(self.distances[phase].get(index)).filter(|&x| x != UNKNOWN)
+        }
+    }
+}
+
+/// Each step of the reduction, from one group to the next, with the moves
+/// of the group it starts in.
+const STAGES: [(&str, &str, &[usize]); 4] = [
+    ("G0 → G1", "orient the edges, so F and B are only needed as half turns", &G0_MOVES),
+    ("G1 → G2", "orient the corners and put the E slice edges in the E slice, so R and L are only needed as half turns", &G1_MOVES),
+    ("G2 → G3", "put the corners in their tetrads and the M and S slice edges in their slices, so U and D are only needed as half turns", &G2_MOVES),
+    ("G3 → solved", "solve the cube with half turns only", &G3_MOVES)
+];
+
+/// Thistlethwaite's algorithm, as a way of seeing group reduction at work
+/// rather than a way of finding short solutions. Each stage brings the cube
+/// into a smaller group, after which fewer moves are needed to solve it, and
+/// is solved in as few moves as possible using the moves of the group it
+/// starts in. Each stage has its own small table of distances to the next
+/// group, built the first time this is called (a couple of seconds in a
+/// release build).
+pub fn solve(cube: &RubiksCube) -> Result<Solution, String> {
+    cube.validate()?;
+    let tables: &Tables = &TABLES;
+    let mut state = CubieCube::from_cube(cube)?;
+
+    let mut stages = Vec::new();
+    for (phase, &(name, goal, allowed)) in STAGES.iter().enumerate() {
+        let mut moves = Vec::new();
+        let unreached = || format!("The table for {name} does not reach this cube");
+        let mut distance = tables.distance(phase, &state).ok_or_else(unreached)?;
+        while distance > 0 {
+            let turn = *allowed.iter().find(|&&turn| tables.distance(phase, &state.apply(turn)) == Some(distance - 1)).ok_or_else(unreached)?;
+            state = state.apply(turn);
+            moves.push(Notation::Cube.parse_move(&move_name(turn)).unwrap());
+            distance -= 1;
+        }
+        stages.push(Stage { name, goal, moves });
+    }
+    Ok(Solution { stages })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::format;
+    use crate::solvers::tests::SCRAMBLES;
+
+    #[test]
+    fn g3_table_holds_every_depth() {
+        let tables: &Tables = &TABLES;
+        // Half of the coordinates have the wrong edge parity for their
+        // corners, so only the other half are reached.
+        let reached: Vec<u8> = tables.g3_distances.iter().copied().filter(|&x| x != u8::MAX).collect();
+        assert_eq!(reached.len(), tables.g3_distances.len() / 2);
+        assert_eq!(reached.iter().max(), Some(&15));
+    }
+
+    #[test]
+    fn solves_in_stages() {
+        for scramble in SCRAMBLES {
+            let mut cube = RubiksCube::default();
+            cube.input_moves(scramble);
+            let solution = solve(&cube).unwrap();
+            for (stage, &(_, _, allowed)) in solution.stages.iter().zip(STAGES.iter()) {
+                assert!(stage.moves.iter().all(|x| allowed.iter().any(|&turn| move_name(turn) == x.to_string())));
+            }
+            cube.input_moves(&format(&solution.moves()));
+            assert!(cube.is_solved(), "{scramble}");
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use crate::algorithms::Move;
+use crate::models::Puzzle;
+use crate::models::rubiks_cube::RubiksCube;
+use crate::solvers::slice_cube::{SliceCube, Turn};
+use crate::solvers::steps::{search, Piece, Table};
+
+/// The edges of the line (DF and DB) and the rest of the cross (DR and DL).
+const LINE: [usize; 2] = [5, 7];
+const CROSS_SIDES: [usize; 2] = [4, 6];
+
+/// Every shortest way of orienting all the edges (so F and B are no longer
+/// needed as quarter turns) and solving `edges`, with `turns` only.
+fn orient(cube: &RubiksCube, turns: &[Turn], edges: &[usize], tables: &[Table]) -> Result<Vec<Vec<Move>>, String> {
+    cube.validate()?;
+    let state = SliceCube::from_cube(cube)?;
+    let bound = |cube: &SliceCube| tables.iter().map(|x| x.distance(cube)).max().unwrap();
+    let done = |cube: &SliceCube| {
+        cube.centred() && cube.cube.eo.iter().all(|&x| x == 0) && edges.iter().all(|&x| cube.cube.ep[x] as usize == x)
+    };
+    Ok(search(&state, turns, &bound, &done))
+}
+
+fn edges(slots: &[usize]) -> Vec<Piece> {
+    slots.iter().map(|&x| Piece::Edge(x)).collect()
+}
+
+/// Every shortest EOLine: the edges oriented and DF and DB solved.
+pub fn eoline(cube: &RubiksCube, turns: &[Turn]) -> Result<Vec<Vec<Move>>, String> {
+    orient(cube, turns, &LINE, &[Table::with_flips(edges(&LINE), turns)])
+}
+
+/// Every shortest EOCross: the edges oriented and the whole D cross solved.
+pub fn eocross(cube: &RubiksCube, turns: &[Turn]) -> Result<Vec<Vec<Move>>, String> {
+    let cross = [LINE, CROSS_SIDES].concat();
+    let tables = [Table::with_flips(edges(&LINE), turns), Table::with_flips(edges(&CROSS_SIDES), turns), Table::new(edges(&cross), turns)];
+    orient(cube, turns, &cross, &tables)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solvers::steps::move_set;
+    use crate::solvers::tests::SCRAMBLES;
+
+    fn oriented(cube: &RubiksCube, moves: &[Move], edges: &[usize]) -> bool {
+        let cube = SliceCube::from_cube(cube).unwrap().apply(moves).unwrap();
+        cube.centred() && cube.cube.eo.iter().all(|&x| x == 0) && edges.iter().all(|&x| cube.cube.ep[x] as usize == x)
+    }
+
+    #[test]
+    fn eolines_and_eocrosses_orient_the_edges() {
+        let mut cube = RubiksCube::default();
+        cube.input_moves(SCRAMBLES[0]);
+        let turns = move_set("").unwrap();
+
+        let lines = eoline(&cube, &turns).unwrap();
+        assert!(!lines.is_empty() && lines.iter().all(|x| x.len() == lines[0].len() && oriented(&cube, x, &LINE)));
+
+        let crosses = eocross(&cube, &turns).unwrap();
+        assert!(!crosses.is_empty() && crosses.iter().all(|x| x.len() == crosses[0].len() && oriented(&cube, x, &[LINE, CROSS_SIDES].concat())));
+        assert!(crosses[0].len() >= lines[0].len());
+
+        cube = RubiksCube::default();
+        cube.input_moves("R U R' U'");
+        assert_eq!(eoline(&cube, &turns).unwrap(), vec![Vec::<Move>::new()]);
+    }
+}
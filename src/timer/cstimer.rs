@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use serde_json::{json, Map, Value};
+
+use crate::models::PuzzleType;
+use crate::timer::{Penalty, Solve};
+
+/// csTimer scramble types for each puzzle, matched by prefix on import so
+/// that variants such as `333oh` or `222so` are kept.
+const SCRAMBLE_TYPES: [(&str, &str); 6] = [("222", "2x2"), ("333", "3x3"), ("skb", "Skewb"), ("pyr", "Pyraminx"), ("mgm", "Megaminx"), ("ivy", "Ivy")];
+const EXPORT_TYPES: [(&str, &str); 6] = [("2x2", "222so"), ("3x3", "333"), ("Skewb", "skbso"), ("Pyraminx", "pyrso"), ("Megaminx", "mgmp"), ("Ivy", "ivyso")];
+
+pub fn puzzle_from_scramble_type(scramble_type: &str) -> Option<PuzzleType> {
+    let (_, name) = SCRAMBLE_TYPES.iter().find(|(prefix, _)| scramble_type.starts_with(prefix))?;
+    PuzzleType::from_name(name)
+}
+
+pub fn scramble_type(puzzle: &PuzzleType) -> &'static str {
+    EXPORT_TYPES.iter().find(|(name, _)| *name == puzzle.name()).map(|x| x.1).unwrap_or("333")
+}
+
+/// Solves read from a csTimer export, and what was left out: sessions for a
+/// puzzle that is not one of ours, and solves that could not be read.
+pub struct Import {
+    pub solves: Vec<Solve>,
+    pub skipped: Vec<String>
+}
+
+/// Reads a csTimer export: `sessionN` arrays of `[[penalty, time], scramble,
+/// comment, date]` with session names and scramble types kept as a JSON
+/// string in `properties.sessionData`.
+pub fn from_json(contents: &str) -> Result<Import, String> {
+    let export: Map<String, Value> = serde_json::from_str(contents).map_err(|e| format!("Not a csTimer export: {e}"))?;
+
+    let session_data: Map<String, Value> = match export.get("properties").and_then(|x| x.get("sessionData")) {
+        Some(Value::String(data)) => serde_json::from_str(data).map_err(|e| format!("Could not read sessionData: {e}"))?,
+        Some(Value::Object(data)) => data.clone(),
+        _ => Map::new()
+    };
+
+    let mut sessions: Vec<(u64, &Vec<Value>)> = export.iter().filter_map(|(key, value)| {
+        Some((key.strip_prefix("session")?.parse().ok()?, value.as_array()?))
+    }).collect();
+    sessions.sort_by_key(|x| x.0);
+
+    let mut import = Import { solves: Vec::new(), skipped: Vec::new() };
+
+    for (number, times) in sessions {
+        let data = session_data.get(&number.to_string());
+        let name = data.and_then(|x| x.get("name")).map(|x| match x {
+            Value::String(name) => name.clone(),
+            other => other.to_string()
+        }).unwrap_or(number.to_string());
+        let scramble_type = data.and_then(|x| x.get("opt")).and_then(|x| x.get("scrType")).and_then(|x| x.as_str()).unwrap_or("333");
+
+        let Some(puzzle) = puzzle_from_scramble_type(scramble_type) else {
+            import.skipped.push(format!("{name} ({scramble_type})"));
+            continue;
+        };
+
+        for (i, time) in times.iter().enumerate() {
+            match solve_from_json(time, &puzzle, &name) {
+                Some(solve) => import.solves.push(solve),
+                None => import.skipped.push(format!("solve {} of {name} (malformed)", i + 1))
+            }
+        }
+    }
+
+    Ok(import)
+}
+
+fn solve_from_json(time: &Value, puzzle: &PuzzleType, session: &str) -> Option<Solve> {
+    let penalty = time.get(0)?.get(0)?.as_i64()?;
+    let ms = time.get(0)?.get(1)?.as_u64()?;
+
+    Some(Solve {
+        puzzle: puzzle.name().to_string(),
+        session: session.to_string(),
+        scramble: time.get(1).and_then(|x| x.as_str()).unwrap_or_default().to_string(),
+        time: ms,
+        penalty: match penalty {
+            -1 => Penalty::Dnf,
+            0 => Penalty::None,
+            _ => Penalty::PlusTwo
+        },
+        date: time.get(3).and_then(|x| x.as_u64()).unwrap_or(0),
+        comment: time.get(2).and_then(|x| x.as_str()).unwrap_or_default().to_string()
+    })
+}
+
+/// Writes solves in csTimer's export format, one csTimer session for each
+/// session and puzzle, in the order they first appear.
+pub fn to_json(solves: &[Solve]) -> String {
+    let mut order: Vec<(String, String)> = Vec::new();
+    let mut grouped: BTreeMap<(String, String), Vec<Value>> = BTreeMap::new();
+
+    for solve in solves {
+        let key = (solve.session.clone(), solve.puzzle.clone());
+        if !grouped.contains_key(&key) {
+            order.push(key.clone());
+        }
+
+        let penalty = match solve.penalty {
+            Penalty::None => 0,
+            Penalty::PlusTwo => 2000,
+            Penalty::Dnf => -1
+        };
+        grouped.entry(key).or_default().push(json!([[penalty, solve.time], solve.scramble, solve.comment, solve.date]));
+    }
+
+    let mut export = Map::new();
+    let mut session_data = Map::new();
+
+    for (i, key) in order.into_iter().enumerate() {
+        let scramble_type = PuzzleType::from_name(&key.1).map(|x| scramble_type(&x)).unwrap_or("333");
+        session_data.insert((i + 1).to_string(), json!({ "name": key.0, "opt": { "scrType": scramble_type }, "rank": i + 1 }));
+        export.insert(format!("session{}", i + 1), Value::Array(grouped.remove(&key).unwrap_or_default()));
+    }
+
+    export.insert("properties".to_string(), json!({ "sessionData": Value::Object(session_data).to_string() }));
+    Value::Object(export).to_string()
+}
+
+pub fn import(path: &Path) -> Result<Import, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+    from_json(&contents)
+}
+
+pub fn export(path: &Path, solves: &[Solve]) -> Result<(), String> {
+    fs::write(path, to_json(solves)).map_err(|e| format!("Could not write {}: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solve(puzzle: &str, session: &str, time: u64, penalty: Penalty, comment: &str) -> Solve {
+        Solve {
+            puzzle: puzzle.to_string(),
+            session: session.to_string(),
+            scramble: "R U R' U'".to_string(),
+            time,
+            penalty,
+            date: 1700000000 + time,
+            comment: comment.to_string()
+        }
+    }
+
+    #[test]
+    fn exports_read_back_the_same() {
+        let solves = vec![
+            solve("3x3", "Default", 12345, Penalty::None, ""),
+            solve("3x3", "Default", 23456, Penalty::Dnf, "lockup"),
+            solve("Megaminx", "Default", 65432, Penalty::PlusTwo, "\"quoted\", with a comma"),
+            solve("3x3", "OH", 34567, Penalty::PlusTwo, ""),
+            solve("Pyraminx", "Default", 4321, Penalty::None, "")
+        ];
+        let import = from_json(&to_json(&solves)).unwrap();
+        assert!(import.skipped.is_empty());
+        assert_eq!(import.solves, solves);
+    }
+
+    #[test]
+    fn scramble_types_pick_the_puzzle() {
+        let name = |x| puzzle_from_scramble_type(x).map(|x| x.name());
+        assert_eq!(name("333"), Some("3x3"));
+        assert_eq!(name("333oh"), Some("3x3"));
+        assert_eq!(name("333fm"), Some("3x3"));
+        assert_eq!(name("222so"), Some("2x2"));
+        assert_eq!(name("mgmp"), Some("Megaminx"));
+        assert_eq!(name("444wca"), None);
+        assert_eq!(name("sqrs"), None);
+    }
+
+    #[test]
+    fn unknown_puzzles_and_malformed_solves_are_skipped() {
+        let contents = r#"{
+            "session1": [[[0, 10000], "R U", "", 1700000000], [[0], "R U"], "junk", [[2000, 11000], "U R", "", 1700000001]],
+            "session2": [[[0, 30000], "R U", "", 1700000002]],
+            "session3": [[[0, 40000], "R U", "", 1700000003]],
+            "properties": {"sessionData": "{\"1\": {\"name\": \"Main\", \"opt\": {\"scrType\": \"333oh\"}}, \"2\": {\"name\": \"Big\", \"opt\": {\"scrType\": \"444wca\"}}, \"3\": {\"name\": 3}}"}
+        }"#;
+        let import = from_json(contents).unwrap();
+        assert_eq!(import.skipped, vec!["solve 2 of Main (malformed)", "solve 3 of Main (malformed)", "Big (444wca)"]);
+        assert_eq!(import.solves.iter().map(|x| (x.puzzle.as_str(), x.session.as_str(), x.result())).collect::<Vec<_>>(),
+            vec![("3x3", "Main", Some(10000)), ("3x3", "Main", Some(13000)), ("3x3", "3", Some(40000))]);
+        assert!(from_json("[]").is_err());
+    }
+}
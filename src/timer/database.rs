@@ -0,0 +1,220 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::timer::{Penalty, Solve};
+
+pub const DEFAULT_PATH: &str = "solves.json";
+
+/// Upgrades a file from the version one above the index to the next one.
+/// Version 1 is the first, so there is nothing to upgrade yet.
+const MIGRATIONS: [fn(Value) -> Result<Value, String>; 0] = [];
+pub const VERSION: u64 = MIGRATIONS.len() as u64 + 1;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub id: u64,
+    #[serde(flatten)]
+    pub solve: Solve
+}
+
+/// Filters for `Database::query`. Fields left as `None` match everything.
+#[derive(Clone, Debug, Default)]
+pub struct Query {
+    pub puzzle: Option<String>,
+    pub session: Option<String>,
+    pub penalty: Option<Penalty>,
+    /// Only solves finished at or after this Unix time.
+    pub since: Option<u64>,
+    pub until: Option<u64>,
+    /// Keep only the latest `last` matches.
+    pub last: Option<usize>
+}
+
+impl Query {
+    pub fn matches(&self, solve: &Solve) -> bool {
+        self.puzzle.as_ref().is_none_or(|x| *x == solve.puzzle)
+            && self.session.as_ref().is_none_or(|x| *x == solve.session)
+            && self.penalty.is_none_or(|x| x == solve.penalty)
+            && self.since.is_none_or(|x| solve.date >= x)
+            && self.until.is_none_or(|x| solve.date <= x)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct File {
+    version: u64,
+    next_id: u64,
+    solves: Vec<Record>
+}
+
+/// Every solve ever recorded, kept in one JSON file. Changes are written
+/// through to disk straight away, unless there is no file to write to.
+pub struct Database {
+    path: Option<PathBuf>,
+    next_id: u64,
+    records: Vec<Record>
+}
+
+impl Database {
+    /// Opens the file at `path`, creating an empty database if it does not
+    /// exist and upgrading it if it was written by an older version.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Self { path: Some(path.to_path_buf()), ..Self::unsaved() });
+        }
+
+        let contents = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+        let mut value: Value = serde_json::from_str(&contents).map_err(|e| format!("{} is not a solve database: {e}", path.display()))?;
+
+        let version = value.get("version").and_then(|x| x.as_u64()).filter(|&x| x > 0)
+            .ok_or(format!("{} is not a solve database: it has no version", path.display()))?;
+        if version > VERSION {
+            return Err(format!("{} was written by a newer version (database version {version})", path.display()));
+        }
+        for migration in MIGRATIONS[version as usize - 1..].iter() {
+            value = migration(value)?;
+        }
+
+        let file: File = serde_json::from_value(value).map_err(|e| format!("{} is not a solve database: {e}", path.display()))?;
+        let database = Self { path: Some(path.to_path_buf()), next_id: file.next_id, records: file.solves };
+        if version < VERSION {
+            database.save()?;
+        }
+        Ok(database)
+    }
+
+    /// A database kept only in memory, for when the file cannot be used.
+    pub fn unsaved() -> Self {
+        Self { path: None, next_id: 1, records: Vec::new() }
+    }
+
+    /// Writes to a temporary file first so a crash never leaves half a file.
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else { return Ok(()) };
+        let file = File { version: VERSION, next_id: self.next_id, solves: self.records.clone() };
+        let contents = serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?;
+
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, contents).map_err(|e| format!("Could not write {}: {e}", temporary.display()))?;
+        fs::rename(&temporary, path).map_err(|e| format!("Could not write {}: {e}", path.display()))
+    }
+
+    /// Stores the solves and returns their ids.
+    pub fn add(&mut self, solves: Vec<Solve>) -> Result<Vec<u64>, String> {
+        let mut ids = Vec::new();
+        for solve in solves {
+            ids.push(self.next_id);
+            self.records.push(Record { id: self.next_id, solve });
+            self.next_id += 1;
+        }
+        self.save()?;
+        Ok(ids)
+    }
+
+    fn update(&mut self, id: u64, change: impl FnOnce(&mut Solve)) -> Result<(), String> {
+        let record = self.records.iter_mut().find(|x| x.id == id).ok_or(format!("There is no solve {id}"))?;
+        change(&mut record.solve);
+        self.save()
+    }
+
+    pub fn set_penalty(&mut self, id: u64, penalty: Penalty) -> Result<(), String> {
+        self.update(id, |solve| solve.penalty = penalty)
+    }
+
+    pub fn set_comment(&mut self, id: u64, comment: &str) -> Result<(), String> {
+        self.update(id, |solve| solve.comment = comment.to_string())
+    }
+
+    pub fn remove(&mut self, id: u64) -> Result<Solve, String> {
+        let index = self.records.iter().position(|x| x.id == id).ok_or(format!("There is no solve {id}"))?;
+        let record = self.records.remove(index);
+        self.save()?;
+        Ok(record.solve)
+    }
+
+    /// Matching solves with their ids, oldest first.
+    pub fn query(&self, query: &Query) -> Vec<&Record> {
+        let matches: Vec<&Record> = self.records.iter().filter(|x| query.matches(&x.solve)).collect();
+        let skip = query.last.map(|x| matches.len().saturating_sub(x)).unwrap_or(0);
+        matches[skip..].to_vec()
+    }
+
+    /// Matching solves without their ids, for `stats` and `cstimer`.
+    pub fn solves(&self, query: &Query) -> Vec<Solve> {
+        self.query(query).into_iter().map(|x| x.solve.clone()).collect()
+    }
+
+    /// Each session with its puzzle and number of solves, in the order the
+    /// sessions were first used.
+    pub fn sessions(&self) -> Vec<(String, String, usize)> {
+        let mut sessions: Vec<(String, String, usize)> = Vec::new();
+        for record in self.records.iter() {
+            match sessions.iter_mut().find(|x| x.0 == record.solve.session && x.1 == record.solve.puzzle) {
+                Some(session) => session.2 += 1,
+                None => sessions.push((record.solve.session.clone(), record.solve.puzzle.clone(), 1))
+            }
+        }
+        sessions
+    }
+
+    pub fn rename_session(&mut self, from: &str, to: &str) -> Result<usize, String> {
+        let mut renamed = 0;
+        for record in self.records.iter_mut().filter(|x| x.solve.session == from) {
+            record.solve.session = to.to_string();
+            renamed += 1;
+        }
+        self.save()?;
+        Ok(renamed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::models::PuzzleType;
+
+    fn temporary(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("rubiks-rust-{}-{name}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn solve(time: u64) -> Solve {
+        Solve::new(&PuzzleType::from_name("3x3").unwrap(), "Default", "R U", Duration::from_millis(time), Penalty::None)
+    }
+
+    #[test]
+    fn solves_are_kept_between_opens() {
+        let path = temporary("kept");
+        let mut database = Database::open(&path).unwrap();
+        assert_eq!(database.add(vec![solve(1000), solve(2000)]).unwrap(), vec![1, 2]);
+        database.set_penalty(2, Penalty::Dnf).unwrap();
+        database.remove(1).unwrap();
+
+        let database = Database::open(&path).unwrap();
+        let records = database.query(&Query::default());
+        assert_eq!(records.len(), 1);
+        assert_eq!((records[0].id, records[0].solve.penalty), (2, Penalty::Dnf));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn unusable_files_are_errors() {
+        let path = temporary("newer");
+        fs::write(&path, format!(r#"{{"version": {}, "next_id": 1, "solves": []}}"#, VERSION + 1)).unwrap();
+        assert!(Database::open(&path).is_err());
+        fs::write(&path, "[]").unwrap();
+        assert!(Database::open(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn unsaved_databases_still_work() {
+        let mut database = Database::unsaved();
+        database.add(vec![solve(1000)]).unwrap();
+        assert_eq!(database.solves(&Query::default()).len(), 1);
+    }
+}
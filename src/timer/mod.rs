@@ -0,0 +1,305 @@
+use std::io::{stdout, Write};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use termion::event::Key;
+use termion::input::TermRead;
+use termion::raw::IntoRawMode;
+use termion::screen::IntoAlternateScreen;
+use termion::{clear, cursor};
+
+use crate::models::PuzzleType;
+use crate::scramble_generator;
+use stats::Statistics;
+
+pub mod cstimer;
+pub mod database;
+pub mod stats;
+
+/// WCA inspection: starting within 15 seconds is fine, within 17 seconds
+/// costs two seconds and anything later is a DNF.
+const INSPECTION: Duration = Duration::from_secs(15);
+const INSPECTION_LIMIT: Duration = Duration::from_secs(17);
+const SOLVES_SHOWN: usize = 5;
+pub const DEFAULT_SESSION: &str = "Default";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Penalty {
+    None,
+    PlusTwo,
+    Dnf
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Solve {
+    /// `PuzzleType::name()` of the puzzle that was solved.
+    pub puzzle: String,
+    #[serde(default = "default_session")]
+    pub session: String,
+    pub scramble: String,
+    /// Time on the clock in milliseconds, before any penalty.
+    pub time: u64,
+    pub penalty: Penalty,
+    /// Seconds since the Unix epoch when the solve finished.
+    pub date: u64,
+    #[serde(default)]
+    pub comment: String
+}
+
+fn default_session() -> String {
+    DEFAULT_SESSION.to_string()
+}
+
+impl Solve {
+    pub fn new(puzzle: &PuzzleType, session: &str, scramble: &str, time: Duration, penalty: Penalty) -> Self {
+        Self {
+            puzzle: puzzle.name().to_string(),
+            session: session.to_string(),
+            scramble: scramble.to_string(),
+            time: time.as_millis() as u64,
+            penalty,
+            date: SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_secs()).unwrap_or(0),
+            comment: String::new()
+        }
+    }
+
+    /// The time that counts, in milliseconds, or `None` for a DNF.
+    pub fn result(&self) -> Option<u64> {
+        match self.penalty {
+            Penalty::None => Some(self.time),
+            Penalty::PlusTwo => Some(self.time + 2000),
+            Penalty::Dnf => None
+        }
+    }
+}
+
+impl std::fmt::Display for Solve {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match (self.result(), self.penalty) {
+            (Some(time), Penalty::PlusTwo) => write!(f, "{}+", format_time(time)),
+            (Some(time), _) => write!(f, "{}", format_time(time)),
+            (None, _) => write!(f, "DNF({})", format_time(self.time))
+        }
+    }
+}
+
+/// Formats milliseconds as `12.345` or `1:02.345`.
+pub fn format_time(ms: u64) -> String {
+    let (minutes, seconds, ms) = (ms / 60_000, ms / 1000 % 60, ms % 1000);
+    if minutes > 0 {
+        format!("{minutes}:{seconds:02}.{ms:03}")
+    } else {
+        format!("{seconds}.{ms:03}")
+    }
+}
+
+pub fn inspection_penalty(inspection: Duration) -> Penalty {
+    if inspection <= INSPECTION {
+        Penalty::None
+    } else if inspection <= INSPECTION_LIMIT {
+        Penalty::PlusTwo
+    } else {
+        Penalty::Dnf
+    }
+}
+
+/// Keys typed since the last call, waiting at most `timeout` for the first.
+/// Reads straight from the terminal rather than through a background
+/// thread, so nothing is left reading stdin once the timer returns.
+fn poll_keys(timeout: Duration) -> Vec<Key> {
+    let mut poll = libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 };
+    if unsafe { libc::poll(&mut poll, 1, timeout.as_millis() as libc::c_int) } <= 0 {
+        return Vec::new();
+    }
+
+    let mut buffer = [0u8; 64];
+    let read = unsafe { libc::read(libc::STDIN_FILENO, buffer.as_mut_ptr() as *mut libc::c_void, buffer.len()) };
+    if read <= 0 {
+        return Vec::new();
+    }
+    buffer[..read as usize].keys().filter_map(|x| x.ok()).collect()
+}
+
+enum Phase {
+    Scrambled,
+    Inspecting(Instant),
+    Solving(Instant, Penalty),
+    Stopped
+}
+
+fn clock(phase: &Phase, solves: &[Solve]) -> String {
+    match phase {
+        Phase::Scrambled => "0.000".to_string(),
+        Phase::Inspecting(start) => match INSPECTION.checked_sub(start.elapsed()) {
+            Some(left) => (left.as_secs() + 1).to_string(),
+            None => format!("{:?}", inspection_penalty(start.elapsed()))
+        },
+        Phase::Solving(start, _) => format_time(start.elapsed().as_millis() as u64),
+        Phase::Stopped => solves.last().map(|x| x.to_string()).unwrap_or_default()
+    }
+}
+
+/// Draws the whole screen and returns the row the clock is on. Only needed
+/// when the scramble, the phase or the solves change.
+fn draw(stdout: &mut impl Write, puzzle: &PuzzleType, scramble: &str, phase: &Phase, solves: &[Solve]) -> u16 {
+    write!(stdout, "{}{}", clear::All, cursor::Goto(1, 1)).unwrap();
+    write!(stdout, "{} | {}\r\n\r\n", puzzle.name(), scramble).unwrap();
+
+    let mut scrambled = puzzle.solved();
+    scrambled.input_moves(scramble);
+    let net = scrambled.render();
+    for row in &net {
+        write!(stdout, "{row}\r\n").unwrap();
+    }
+
+    let help = match phase {
+        Phase::Scrambled => "Space to start",
+        Phase::Inspecting(_) => "Inspecting, space to start solving",
+        Phase::Solving(..) => "Any key to stop",
+        Phase::Stopped => "Space for the next scramble, 2 for +2, d for DNF"
+    };
+    write!(stdout, "\r\n    {}\r\n\r\n{help}, Esc to leave\r\n", clock(phase, solves)).unwrap();
+
+    let recent: Vec<String> = solves.iter().rev().take(SOLVES_SHOWN).map(|x| x.to_string()).collect();
+    write!(stdout, "Solves: {} | Recent: {}\r\n", solves.len(), recent.join(" ")).unwrap();
+    write!(stdout, "{}\r\n", Statistics::new(solves).summary()).unwrap();
+    stdout.flush().unwrap();
+    net.len() as u16 + 4
+}
+
+/// Redraws just the clock, which is all that changes while it runs.
+fn draw_clock(stdout: &mut impl Write, row: u16, phase: &Phase, solves: &[Solve]) {
+    write!(stdout, "{}{}    {}", cursor::Goto(1, row), clear::CurrentLine, clock(phase, solves)).unwrap();
+    stdout.flush().unwrap();
+}
+
+/// Where `key` takes the timer from `phase`. Stopping the clock adds the
+/// solve made by `solve` to `solves`, and 2 or d toggles a penalty on the
+/// last one.
+fn press(phase: Phase, key: Key, inspection: bool, solves: &mut Vec<Solve>, solve: impl FnOnce(Duration, Penalty) -> Solve) -> Phase {
+    match (phase, key) {
+        (Phase::Solving(start, penalty), _) => {
+            solves.push(solve(start.elapsed(), penalty));
+            Phase::Stopped
+        }
+        (Phase::Scrambled, Key::Char(' ')) if inspection => Phase::Inspecting(Instant::now()),
+        (Phase::Scrambled, Key::Char(' ')) => Phase::Solving(Instant::now(), Penalty::None),
+        (Phase::Inspecting(start), Key::Char(' ')) => Phase::Solving(Instant::now(), inspection_penalty(start.elapsed())),
+        (Phase::Stopped, Key::Char(' ')) => Phase::Scrambled,
+        (Phase::Stopped, Key::Char(c)) if c == '2' || c == 'd' => {
+            if let Some(solve) = solves.last_mut() {
+                let penalty = if c == '2' { Penalty::PlusTwo } else { Penalty::Dnf };
+                solve.penalty = if solve.penalty == penalty { Penalty::None } else { penalty };
+            }
+            Phase::Stopped
+        }
+        (phase, _) => phase
+    }
+}
+
+/// Runs the timer in raw mode until Esc is pressed. Each solve is timed on a
+/// fresh scramble, optionally after WCA inspection, and returned in order.
+pub fn run(puzzle: &PuzzleType, session: &str, inspection: bool) -> Vec<Solve> {
+    let mut stdout = stdout().into_raw_mode().unwrap().into_alternate_screen().unwrap();
+    let mut solves: Vec<Solve> = Vec::new();
+
+    let mut scramble = scramble_generator::main(scramble_generator::default_length(puzzle), puzzle);
+    let mut phase = Phase::Scrambled;
+
+    write!(stdout, "{}", cursor::Hide).unwrap();
+    let mut clock_row = draw(&mut stdout, puzzle, &scramble, &phase, &solves);
+
+    'timer: loop {
+        let keys = poll_keys(Duration::from_millis(30));
+        for &key in &keys {
+            if key == Key::Esc {
+                break 'timer;
+            }
+
+            let stopped = matches!(phase, Phase::Stopped);
+            phase = press(phase, key, inspection, &mut solves, |time, penalty| Solve::new(puzzle, session, &scramble, time, penalty));
+            if stopped && matches!(phase, Phase::Scrambled) {
+                scramble = scramble_generator::main(scramble_generator::default_length(puzzle), puzzle);
+            }
+        }
+
+        if !keys.is_empty() {
+            clock_row = draw(&mut stdout, puzzle, &scramble, &phase, &solves);
+        } else if matches!(phase, Phase::Inspecting(_) | Phase::Solving(..)) {
+            draw_clock(&mut stdout, clock_row, &phase, &solves);
+        }
+    }
+
+    write!(stdout, "{}", cursor::Show).unwrap();
+    solves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press_space(phase: Phase, inspection: bool, solves: &mut Vec<Solve>) -> Phase {
+        let puzzle = PuzzleType::from_name("3x3").unwrap();
+        press(phase, Key::Char(' '), inspection, solves, |time, penalty| Solve::new(&puzzle, DEFAULT_SESSION, "R U", time, penalty))
+    }
+
+    fn inspected_for(seconds: u64) -> Penalty {
+        match press_space(Phase::Inspecting(Instant::now() - Duration::from_secs(seconds)), true, &mut Vec::new()) {
+            Phase::Solving(_, penalty) => penalty,
+            _ => panic!("Not solving after inspection")
+        }
+    }
+
+    #[test]
+    fn space_goes_round_the_phases() {
+        let mut solves = Vec::new();
+        assert!(matches!(press_space(Phase::Scrambled, false, &mut solves), Phase::Solving(_, Penalty::None)));
+        let inspecting = press_space(Phase::Scrambled, true, &mut solves);
+        assert!(matches!(inspecting, Phase::Inspecting(_)));
+        assert!(matches!(press_space(inspecting, true, &mut solves), Phase::Solving(_, Penalty::None)));
+        assert!(solves.is_empty());
+
+        let solving = Phase::Solving(Instant::now() - Duration::from_secs(10), Penalty::PlusTwo);
+        assert!(matches!(press_space(solving, true, &mut solves), Phase::Stopped));
+        assert_eq!(solves.len(), 1);
+        assert!(solves[0].time >= 10000);
+        assert_eq!((solves[0].penalty, solves[0].scramble.as_str()), (Penalty::PlusTwo, "R U"));
+        assert!(matches!(press_space(Phase::Stopped, true, &mut solves), Phase::Scrambled));
+    }
+
+    #[test]
+    fn any_key_stops_the_clock() {
+        let mut solves = Vec::new();
+        let phase = press(Phase::Solving(Instant::now(), Penalty::None), Key::Char('x'), false, &mut solves, |time, penalty| {
+            Solve::new(&PuzzleType::from_name("2x2").unwrap(), DEFAULT_SESSION, "", time, penalty)
+        });
+        assert!(matches!(phase, Phase::Stopped));
+        assert_eq!(solves.len(), 1);
+        assert!(matches!(press_space(Phase::Scrambled, false, &mut solves), Phase::Solving(..)));
+        assert!(matches!(press(Phase::Scrambled, Key::Char('x'), false, &mut solves, |_, _| unreachable!()), Phase::Scrambled));
+    }
+
+    #[test]
+    fn late_inspection_costs_two_seconds_then_a_dnf() {
+        assert_eq!(inspected_for(10), Penalty::None);
+        assert_eq!(inspected_for(16), Penalty::PlusTwo);
+        assert_eq!(inspected_for(18), Penalty::Dnf);
+    }
+
+    #[test]
+    fn penalties_toggle_on_the_last_solve() {
+        let puzzle = PuzzleType::from_name("3x3").unwrap();
+        let mut solves = vec![Solve::new(&puzzle, DEFAULT_SESSION, "", Duration::from_secs(10), Penalty::None); 2];
+        let mut toggle = |c| press(Phase::Stopped, Key::Char(c), false, &mut solves, |_, _| unreachable!());
+        assert!(matches!(toggle('2'), Phase::Stopped));
+        toggle('d');
+        toggle('d');
+        toggle('2');
+        assert_eq!((solves[0].penalty, solves[1].penalty), (Penalty::None, Penalty::PlusTwo));
+        assert_eq!((solves[1].result(), solves[1].to_string()), (Some(12000), "12.000+".to_string()));
+
+        press(Phase::Stopped, Key::Char('d'), false, &mut solves, |_, _| unreachable!());
+        assert_eq!((solves[1].result(), solves[1].to_string()), (None, "DNF(10.000)".to_string()));
+        press(Phase::Stopped, Key::Char('d'), false, &mut solves, |_, _| unreachable!());
+        assert_eq!(solves[1].penalty, Penalty::None);
+    }
+}
@@ -0,0 +1,214 @@
+use std::collections::BTreeMap;
+
+use crate::models::PuzzleType;
+use crate::timer::{format_time, Solve};
+
+/// Means and averages shown for every puzzle, as (name, size, trimmed mean).
+pub const AVERAGES: [(&str, usize, bool); 4] = [("mo3", 3, false), ("ao5", 5, true), ("ao12", 12, true), ("ao100", 100, true)];
+const HISTOGRAM_WIDTH: usize = 40;
+
+/// Formats a result from this module, where `None` is a DNF.
+pub fn format_result(result: Option<u64>) -> String {
+    match result {
+        Some(time) => format_time(time),
+        None => "DNF".to_string()
+    }
+}
+
+/// Plain mean of every solve. Any DNF makes the mean a DNF.
+pub fn mean_of(solves: &[Solve]) -> Option<u64> {
+    let times = solves.iter().map(|x| x.result()).collect::<Option<Vec<u64>>>()?;
+    Some(times.iter().sum::<u64>() / times.len().max(1) as u64)
+}
+
+/// WCA style average: the best and worst 5% (at least one solve each) are
+/// dropped and the rest are averaged. DNFs count as the worst times, so one
+/// DNF is trimmed away but two make the average a DNF.
+pub fn average_of(solves: &[Solve]) -> Option<u64> {
+    let trim = solves.len().div_ceil(20);
+    let mut times: Vec<Option<u64>> = solves.iter().map(|x| x.result()).collect();
+    times.sort_by_key(|x| x.unwrap_or(u64::MAX));
+
+    let counting = times.get(trim..times.len().saturating_sub(trim))?;
+    let counting = counting.iter().copied().collect::<Option<Vec<u64>>>()?;
+    Some(counting.iter().sum::<u64>() / counting.len().max(1) as u64)
+}
+
+/// The solves done on one kind of puzzle, in their original order.
+pub fn of_puzzle(solves: &[Solve], puzzle: &PuzzleType) -> Vec<Solve> {
+    solves.iter().filter(|x| x.puzzle == puzzle.name()).cloned().collect()
+}
+
+fn compare(a: &Option<u64>, b: &Option<u64>) -> std::cmp::Ordering {
+    a.unwrap_or(u64::MAX).cmp(&b.unwrap_or(u64::MAX))
+}
+
+pub struct Average {
+    pub name: &'static str,
+    /// Over the latest solves. `None` if there are not enough solves yet,
+    /// `Some(None)` for a DNF.
+    pub current: Option<Option<u64>>,
+    /// Best over any run of consecutive solves.
+    pub best: Option<Option<u64>>
+}
+
+pub struct Statistics {
+    pub solves: usize,
+    pub dnfs: usize,
+    pub best: Option<u64>,
+    pub worst: Option<u64>,
+    /// Mean of every solve that is not a DNF.
+    pub mean: Option<u64>,
+    pub averages: Vec<Average>
+}
+
+impl Statistics {
+    /// Statistics over the solves in order, oldest first.
+    pub fn new(solves: &[Solve]) -> Self {
+        let times: Vec<u64> = solves.iter().filter_map(|x| x.result()).collect();
+
+        let averages = AVERAGES.iter().map(|&(name, size, trimmed)| {
+            let of = if trimmed { average_of } else { mean_of };
+            let results: Vec<Option<u64>> = solves.windows(size).map(of).collect();
+
+            Average {
+                name,
+                current: results.last().copied(),
+                best: results.into_iter().min_by(compare)
+            }
+        }).collect();
+
+        Self {
+            solves: solves.len(),
+            dnfs: solves.len() - times.len(),
+            best: times.iter().min().copied(),
+            worst: times.iter().max().copied(),
+            mean: (!times.is_empty()).then(|| times.iter().sum::<u64>() / times.len() as u64),
+            averages
+        }
+    }
+
+    /// Statistics over the solves done on one kind of puzzle.
+    pub fn for_puzzle(solves: &[Solve], puzzle: &PuzzleType) -> Self {
+        Self::new(&of_puzzle(solves, puzzle))
+    }
+
+    /// Statistics for every puzzle that has solves, keyed by `PuzzleType::name()`.
+    pub fn by_puzzle(solves: &[Solve]) -> BTreeMap<String, Self> {
+        let mut grouped: BTreeMap<String, Vec<Solve>> = BTreeMap::new();
+        for solve in solves {
+            grouped.entry(solve.puzzle.clone()).or_default().push(solve.clone());
+        }
+        grouped.into_iter().map(|(puzzle, solves)| (puzzle, Self::new(&solves))).collect()
+    }
+
+    /// One line per figure, for printing in the REPL.
+    pub fn render(&self) -> Vec<String> {
+        let time = |x: Option<u64>| x.map(format_time).unwrap_or("-".to_string());
+        let result = |x: Option<Option<u64>>| x.map(format_result).unwrap_or("-".to_string());
+
+        let mut rows = vec![
+            format!("Solves: {} ({} DNF)", self.solves, self.dnfs),
+            format!("Best single: {}   Worst: {}   Mean: {}", time(self.best), time(self.worst), time(self.mean))
+        ];
+        for average in self.averages.iter() {
+            rows.push(format!("{:<6} current: {:<10} best: {}", average.name, result(average.current), result(average.best)));
+        }
+        rows
+    }
+
+    /// The current mean and averages on a single line, for the timer view.
+    pub fn summary(&self) -> String {
+        let mut parts = vec![format!("best: {}", self.best.map(format_time).unwrap_or("-".to_string()))];
+        for average in self.averages.iter().filter(|x| x.current.is_some()) {
+            parts.push(format!("{}: {}", average.name, average.current.map(format_result).unwrap_or_default()));
+        }
+        parts.join(" | ")
+    }
+}
+
+/// Counts of the non-DNF times in `buckets` equal ranges between the best and
+/// worst time, as (start of range in milliseconds, count).
+pub fn histogram(solves: &[Solve], buckets: usize) -> Vec<(u64, usize)> {
+    let times: Vec<u64> = solves.iter().filter_map(|x| x.result()).collect();
+    let (Some(&best), Some(&worst)) = (times.iter().min(), times.iter().max()) else {
+        return Vec::new();
+    };
+
+    let buckets = buckets.max(1);
+    let width = ((worst - best) / buckets as u64).max(1);
+    let mut counts = vec![0; buckets];
+    for time in times {
+        counts[(((time - best) / width) as usize).min(buckets - 1)] += 1;
+    }
+    counts.into_iter().enumerate().map(|(i, count)| (best + i as u64 * width, count)).collect()
+}
+
+pub fn render_histogram(solves: &[Solve], buckets: usize) -> Vec<String> {
+    let histogram = histogram(solves, buckets);
+    let most = histogram.iter().map(|x| x.1).max().unwrap_or(0).max(1);
+
+    histogram.iter().map(|&(start, count)| {
+        format!("{:>10} | {:<HISTOGRAM_WIDTH$} {count}", format_time(start), "#".repeat(count * HISTOGRAM_WIDTH / most))
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::timer::{inspection_penalty, Penalty};
+
+    fn solves(times: &[(u64, Penalty)]) -> Vec<Solve> {
+        let puzzle = PuzzleType::from_name("3x3").unwrap();
+        times.iter().map(|&(time, penalty)| Solve::new(&puzzle, "Default", "", Duration::from_millis(time), penalty)).collect()
+    }
+
+    fn plain(times: &[u64]) -> Vec<Solve> {
+        solves(&times.iter().map(|&x| (x, Penalty::None)).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn averages_drop_the_best_and_worst() {
+        assert_eq!(average_of(&plain(&[10000, 11000, 12000, 13000, 20000])), Some(12000));
+        assert_eq!(average_of(&plain(&[9000, 10000, 11000, 12000, 13000, 14000, 15000, 16000, 17000, 18000, 19000, 30000])), Some(14500));
+        assert_eq!(mean_of(&plain(&[10000, 11000, 15000])), Some(12000));
+    }
+
+    #[test]
+    fn one_dnf_is_trimmed_and_two_are_a_dnf() {
+        let one = solves(&[(10000, Penalty::None), (11000, Penalty::Dnf), (12000, Penalty::None), (13000, Penalty::None), (14000, Penalty::None)]);
+        assert_eq!(average_of(&one), Some(13000));
+        let two = solves(&[(10000, Penalty::Dnf), (11000, Penalty::Dnf), (12000, Penalty::None), (13000, Penalty::None), (14000, Penalty::None)]);
+        assert_eq!(average_of(&two), None);
+        assert_eq!(mean_of(&one[..3]), None);
+    }
+
+    #[test]
+    fn plus_two_counts() {
+        let times = solves(&[(10000, Penalty::PlusTwo), (11000, Penalty::None), (12000, Penalty::None), (13000, Penalty::None), (15000, Penalty::None)]);
+        assert_eq!(average_of(&times), Some(12333));
+        assert_eq!(times[0].result(), Some(12000));
+    }
+
+    #[test]
+    fn inspection() {
+        assert_eq!(inspection_penalty(Duration::from_millis(14999)), Penalty::None);
+        assert_eq!(inspection_penalty(Duration::from_millis(16000)), Penalty::PlusTwo);
+        assert_eq!(inspection_penalty(Duration::from_millis(17001)), Penalty::Dnf);
+    }
+
+    #[test]
+    fn statistics() {
+        let times = solves(&[(10000, Penalty::None), (20000, Penalty::None), (12000, Penalty::Dnf), (11000, Penalty::None), (13000, Penalty::None), (9000, Penalty::None)]);
+        let statistics = Statistics::new(&times);
+        assert_eq!((statistics.solves, statistics.dnfs), (6, 1));
+        assert_eq!((statistics.best, statistics.worst), (Some(9000), Some(20000)));
+        let ao5 = statistics.averages.iter().find(|x| x.name == "ao5").unwrap();
+        assert_eq!(ao5.current, Some(Some(14666)));
+        assert_eq!(ao5.best, Some(Some(14666)));
+        let mo3 = statistics.averages.iter().find(|x| x.name == "mo3").unwrap();
+        assert_eq!(mo3.best, Some(Some(11000)));
+        assert_eq!(statistics.averages.iter().find(|x| x.name == "ao12").unwrap().current, None);
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{stdin, stdout, Write};
+use std::path::Path;
+use colored::Colorize;
+use termion::event::Key;
+use termion::input::TermRead;
+use termion::raw::IntoRawMode;
+use termion::screen::IntoAlternateScreen;
+use termion::{clear, cursor};
+
+use crate::models::PuzzleType;
+
+const LAST_MOVES_SHOWN: usize = 12;
+
+/// Keys laid out like most keyboard-controlled virtual cubes: the right hand
+/// turns R and U, the left hand L and U', and F/B/D sit around them.
+pub fn default_key_map(puzzle: &PuzzleType) -> HashMap<char, String> {
+    let keys: &[(char, &str)] = match puzzle {
+        PuzzleType::RubiksCube(_) | PuzzleType::RubiksCube2x2(_) => &[
+            ('j', "U"), ('f', "U'"), ('i', "R"), ('k', "R'"), ('d', "L"), ('e', "L'"),
+            ('h', "F"), ('g', "F'"), ('s', "D"), ('l', "D'"), ('w', "B"), ('o', "B'"),
+        ],
+        PuzzleType::Skewb(_) | PuzzleType::Ivy(_) => &[
+            ('j', "U"), ('f', "U'"), ('i', "R"), ('k', "R'"), ('d', "L"), ('e', "L'"), ('h', "F"), ('g', "F'"),
+        ],
+        PuzzleType::Pyraminx(_) => &[
+            ('i', "R"), ('k', "R'"), ('d', "L"), ('e', "L'"), ('h', "F"), ('g', "F'"), ('s', "D"), ('l', "D'"),
+            ('u', "T"), ('y', "T'"), ('o', "E"), ('p', "E'"), ('w', "K"), ('q', "K'"), ('b', "B"), ('n', "B'"),
+        ],
+        PuzzleType::Megaminx(_) => &[
+            ('j', "U"), ('f', "U'"), ('i', "R"), ('k', "R'"), ('d', "L"), ('e', "L'"), ('h', "F"), ('g', "F'"),
+            ('s', "D"), ('l', "D'"), ('w', "B"), ('o', "B'"), ('a', "V"), ('z', "V'"), ('r', "P"), ('v', "P'"),
+            ('u', "G"), ('m', "G'"), ('t', "O"), ('y', "O'"), ('x', "S"), ('c', "S'"), ('b', "J"), ('n', "J'"),
+        ]
+    };
+
+    keys.iter().map(|&(key, turn)| (key, turn.to_string())).collect()
+}
+
+/// Reads `key = "move"` pairs from a `.toml` or `.json` file, each of which
+/// must be a single move on `puzzle`.
+pub fn load_key_map(path: &Path, puzzle: &PuzzleType) -> Result<HashMap<char, String>, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+
+    let entries: HashMap<String, String> = match path.extension().and_then(|x| x.to_str()) {
+        Some("json") => serde_json::from_str(&contents).map_err(|e| e.to_string())?,
+        Some("toml") => toml::from_str(&contents).map_err(|e| e.to_string())?,
+        _ => return Err(format!("{} is not a .toml or .json file", path.display()))
+    };
+
+    let mut key_map = HashMap::new();
+    for (key, turn) in entries {
+        let mut chars = key.chars();
+        let (Some(c), None) = (chars.next(), chars.next()) else {
+            return Err(format!("'{key}' is not a single key"));
+        };
+        if puzzle.inverse_move(&turn).is_none() {
+            return Err(format!("'{key}' is bound to '{turn}', which is not a {} move", puzzle.name()));
+        }
+        key_map.insert(c, turn);
+    }
+    Ok(key_map)
+}
+
+/// The default key map for the puzzle, with any bindings from `keymap.toml`
+/// or `keymap.json` in the working directory laid over the top.
+pub fn key_map(puzzle: &PuzzleType) -> Result<HashMap<char, String>, String> {
+    let mut key_map = default_key_map(puzzle);
+
+    for path in ["keymap.toml", "keymap.json"] {
+        if Path::new(path).exists() {
+            key_map.extend(load_key_map(Path::new(path), puzzle)?);
+        }
+    }
+    Ok(key_map)
+}
+
+fn draw(stdout: &mut impl Write, puzzle: &PuzzleType, moves: &[String], message: &str) {
+    write!(stdout, "{}{}", clear::All, cursor::Goto(1, 1)).unwrap();
+    for row in puzzle.render() {
+        write!(stdout, "{row}\r\n").unwrap();
+    }
+
+    let last_moves = &moves[moves.len().saturating_sub(LAST_MOVES_SHOWN)..];
+    let solved = if puzzle.is_solved() { "Solved".green() } else { "Unsolved".red() };
+
+    write!(stdout, "\r\n{} | Moves: {} | {} | Last: {}\r\n", puzzle.name(), moves.len(), solved, last_moves.join(" ")).unwrap();
+    write!(stdout, "Esc to leave{}\r\n", message).unwrap();
+    stdout.flush().unwrap();
+}
+
+/// Full-screen mode where each keypress in `key_map` turns the puzzle and the
+/// net is redrawn in place. Returns the moves applied, in order.
+pub fn run(puzzle: &mut PuzzleType, key_map: &HashMap<char, String>) -> Vec<String> {
+    let stdin = stdin();
+    let mut stdout = stdout().into_raw_mode().unwrap().into_alternate_screen().unwrap();
+    let mut moves = Vec::new();
+
+    write!(stdout, "{}", cursor::Hide).unwrap();
+    draw(&mut stdout, puzzle, &moves, "");
+
+    for c in stdin.keys() {
+        let message = match c.unwrap() {
+            Key::Esc | Key::Ctrl('c') => break,
+            Key::Char(key) => match key_map.get(&key) {
+                Some(turn) => {
+                    puzzle.input_moves(turn);
+                    moves.push(turn.clone());
+                    String::new()
+                }
+                None => format!(" | '{key}' is not bound to a move")
+            },
+            _ => String::new()
+        };
+
+        draw(&mut stdout, puzzle, &moves, &message);
+    }
+
+    write!(stdout, "{}", cursor::Show).unwrap();
+    moves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(name: &str, contents: &str, puzzle: &str) -> Result<HashMap<char, String>, String> {
+        let path = std::env::temp_dir().join(format!("rubiks-rust-{}-{name}", std::process::id()));
+        fs::write(&path, contents).unwrap();
+        let key_map = load_key_map(&path, &PuzzleType::from_name(puzzle).unwrap());
+        fs::remove_file(&path).unwrap();
+        key_map
+    }
+
+    #[test]
+    fn bindings_are_read() {
+        let key_map = load("keymap.toml", "a = \"R\"\nb = \"U'\"", "3x3").unwrap();
+        assert_eq!((key_map[&'a'].as_str(), key_map[&'b'].as_str()), ("R", "U'"));
+        assert_eq!(load("keymap.json", r#"{"t": "T'"}"#, "Pyraminx").unwrap()[&'t'], "T'");
+    }
+
+    #[test]
+    fn bad_bindings_are_errors() {
+        assert!(load("keymap.toml", "ab = \"R\"", "3x3").is_err_and(|x| x.contains("'ab'")));
+        assert!(load("keymap.toml", "a = \"R U\"", "3x3").is_err_and(|x| x.contains("'a'")));
+        assert!(load("keymap.json", r#"{"q": "Q"}"#, "3x3").is_err_and(|x| x.contains("'q'")));
+        assert!(load("keymap.json", r#"{"t": "T"}"#, "3x3").is_err_and(|x| x.contains("'t'")));
+        assert!(load("keymap.yaml", "a: R", "3x3").is_err_and(|x| x.contains("not a .toml or .json file")));
+    }
+
+    #[test]
+    fn default_bindings_are_moves() {
+        for name in ["3x3", "2x2", "Skewb", "Pyraminx", "Megaminx", "Ivy"] {
+            let puzzle = PuzzleType::from_name(name).unwrap();
+            assert!(default_key_map(&puzzle).values().all(|x| puzzle.inverse_move(x).is_some()), "{name}");
+        }
+    }
+}